@@ -1,30 +1,350 @@
-use std::path::Path;
+pub mod lint;
 
-use x_protocol::{ast::AST, ShellState};
+use std::cell::RefCell;
+use std::ops::Range;
+
+use x_protocol::ast::{Expression, WordPart, AST};
+use x_protocol::diagnostic::Diagnostic;
+use x_protocol::theme::Role;
+use x_protocol::ShellState;
 use x_protocol::{Result, ShellErr};
 
+/// What a command name resolves to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandKind {
+    Alias,
+    Function,
+    Builtin,
+    External,
+}
+
+/// A span of input to restyle once names and paths are resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub span: Range<usize>,
+    pub role: Role,
+    /// The token names something that exists on disk.
+    pub exists: bool,
+}
+
 pub struct Checker<'a> {
     state: &'a ShellState,
+    /// Functions declared earlier in the input being checked.
+    defined: RefCell<Vec<String>>,
 }
 
 impl<'a> Checker<'a> {
     pub fn new(state: &'a ShellState) -> Self {
-        Checker { state }
+        Checker {
+            state,
+            defined: RefCell::new(vec![]),
+        }
     }
 
     pub fn check(&self, ast: &AST) -> Result<()> {
         match ast {
-            AST::Command { name, args } => self.command(name.index.clone(), name.ty.to_string())?,
+            AST::Command { name, .. } => self.command(name.span.clone(), name.ty.to_string())?,
+            AST::Function { name, .. } => self.defined.borrow_mut().push(name.ty.to_string()),
+            AST::Pipeline(commands) => {
+                for command in commands {
+                    self.check(command)?;
+                }
+            }
             _ => {}
         }
         Ok(())
     }
 
-    fn command(&self, index: usize, name: String) -> Result<()> {
-        if self.state.commands.iter().find(|command| { command.get_name() == name }).is_some() {
+    /// Resolve a command name the way `execute` does: aliases first, then
+    /// functions, builtins, and executables from `PATH`.
+    pub fn resolve(&self, name: &str) -> Option<CommandKind> {
+        if self.state.aliases.contains_key(name) {
+            return Some(CommandKind::Alias);
+        }
+        if self.state.functions.contains_key(name) || self.defined.borrow().iter().any(|f| f == name) {
+            return Some(CommandKind::Function);
+        }
+        self.state.command(name).map(|command| {
+            if command.is_builtin() {
+                CommandKind::Builtin
+            } else {
+                CommandKind::External
+            }
+        })
+    }
+
+    /// The known command name closest to `name`, if any is close enough to
+    /// be a likely typo.
+    pub fn suggest(&self, name: &str) -> Option<String> {
+        // a path names one program, not something to guess at
+        if name.contains('/') {
+            return None;
+        }
+        let len = name.chars().count();
+        let max = (len / 3).max(1);
+        let defined = self.defined.borrow();
+        let commands = self.state.command_names();
+        let candidates = self
+            .state
+            .functions
+            .keys()
+            .chain(self.state.aliases.keys())
+            .chain(defined.iter())
+            .chain(commands.iter())
+            .map(String::as_str);
+
+        // a name made only of punctuation, like `.`, is no typo of a word
+        candidates
+            .filter(|candidate| *candidate != name && candidate.chars().any(char::is_alphanumeric))
+            .filter(|candidate| candidate.chars().count().abs_diff(len) <= max)
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            // replacing every char is no typo either
+            .filter(|(distance, _)| *distance <= max && *distance < len)
+            .min()
+            .map(|(_, candidate)| candidate.to_string())
+    }
+
+    /// Explain `err` found in `source`, with a suggestion for unknown commands.
+    pub fn diagnostic(&self, err: &ShellErr, source: &str) -> Diagnostic {
+        let diagnostic = Diagnostic::from_err(err, source);
+        match err {
+            ShellErr::UnknownCommand(_, name) => match self.suggest(name) {
+                Some(suggestion) => diagnostic.help(&format!("did you mean `{}`?", suggestion)),
+                None => diagnostic,
+            },
+            _ => diagnostic,
+        }
+    }
+
+    /// Semantic styling for `ast`: command names by what they resolve to and
+    /// path arguments that exist.
+    pub fn highlights(&self, ast: &AST) -> Vec<Highlight> {
+        let mut highlights = vec![];
+        self.collect_highlights(ast, &mut highlights);
+        highlights
+    }
+
+    fn collect_highlights(&self, ast: &AST, highlights: &mut Vec<Highlight>) {
+        match ast {
+            AST::Command { name, args } => {
+                if let Some(kind) = self.resolve(&name.ty.to_string()) {
+                    highlights.push(Highlight {
+                        span: name.span.clone(),
+                        role: match kind {
+                            CommandKind::Alias => Role::Command,
+                            CommandKind::Function => Role::Function,
+                            CommandKind::Builtin => Role::Builtin,
+                            CommandKind::External => Role::Command,
+                        },
+                        exists: false,
+                    });
+                }
+                for arg in args {
+                    let Expression::Word(word) = arg else {
+                        continue;
+                    };
+                    let path = word.literal().filter(|text| text.starts_with(['.', '/', '~']));
+                    if path.is_some_and(|path| self.state.fs_cache.exists(&self.state.resolve_path(&path))) {
+                        highlights.push(Highlight {
+                            span: word.span.clone(),
+                            role: Role::Path,
+                            exists: true,
+                        });
+                    }
+                    word.walk(&mut |part| {
+                        if let WordPart::CommandSubstitution { stmts, .. } = part {
+                            for stmt in stmts {
+                                self.collect_highlights(stmt, highlights);
+                            }
+                        }
+                    });
+                }
+            }
+            AST::Function { block, .. } => {
+                for stmt in &block.stmts {
+                    self.collect_highlights(stmt, highlights);
+                }
+            }
+            AST::Pipeline(commands) => {
+                for command in commands {
+                    self.collect_highlights(command, highlights);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn command(&self, span: Range<usize>, name: String) -> Result<()> {
+        let name = self.unalias(name);
+        if self.resolve(&name).is_some() {
             Ok(())
         } else {
-            Err(ShellErr::UnknownCommand(index, name))
+            Err(ShellErr::UnknownCommand(span, name))
+        }
+    }
+
+    /// The command an alias runs in the end, or `name` when it is not one.
+    fn unalias(&self, mut name: String) -> String {
+        let mut seen = vec![];
+        while let Some(value) = self.state.aliases.get(&name) {
+            seen.push(name.clone());
+            match value.split_whitespace().next() {
+                // an alias of itself, as in `alias ls='ls -F'`, or of nothing
+                Some(next) if !seen.iter().any(|name| name == next) => name = next.to_string(),
+                _ => return name,
+            }
+        }
+        name
+    }
+}
+
+/// Edits (insertions, deletions, substitutions and swaps of neighbouring
+/// characters) needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows i - 2, i - 1 and i of the distance table
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
         }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod checker_test {
+    use std::path::PathBuf;
+
+    use x_parser::{Lexer, Parser};
+    use x_protocol::command::EnvCommand;
+    use x_protocol::theme::Role;
+    use x_protocol::{ShellErr, ShellState};
+
+    use crate::{edit_distance, Checker, CommandKind};
+
+    fn state() -> ShellState {
+        let mut state = ShellState::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")), "user".into());
+        let ls = EnvCommand::new("ls".into(), PathBuf::from("/bin/ls"));
+        state.init_commands(vec![Box::new(ls)]);
+        state
+    }
+
+    #[test]
+    fn resolve() {
+        let state = state();
+        let checker = Checker::new(&state);
+        let mut parser = Parser::new(Lexer::new("def f [] { ls; }".chars()));
+        checker.check(&parser.parse().unwrap().unwrap()).unwrap();
+
+        assert_eq!(checker.resolve("ls"), Some(CommandKind::External));
+        assert_eq!(checker.resolve("f"), Some(CommandKind::Function));
+        assert_eq!(checker.resolve("nope"), None);
+    }
+
+    #[test]
+    fn aliases() {
+        let mut state = state();
+        state.aliases.insert("ll".into(), "ls -la".into());
+        state.aliases.insert("ls".into(), "ls -F".into());
+        state.aliases.insert("broken".into(), "lss -la".into());
+        let checker = Checker::new(&state);
+
+        assert_eq!(checker.resolve("ll"), Some(CommandKind::Alias));
+        assert_eq!(checker.suggest("lll"), Some("ll".into()));
+        let check = |source: &str| {
+            let mut parser = Parser::new(Lexer::new(source.chars()));
+            checker.check(&parser.parse().unwrap().unwrap())
+        };
+        assert!(check("ll").is_ok());
+        assert!(check("ls").is_ok());
+        assert!(matches!(check("broken"), Err(ShellErr::UnknownCommand(span, name)) if span == (0..6) && name == "lss"));
+
+        let highlights = checker.highlights(&Parser::new(Lexer::new("ll".chars())).parse().unwrap().unwrap());
+        assert_eq!(highlights[0].role, Role::Command);
+    }
+
+    #[test]
+    fn suggest() {
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+
+        let mut state = state();
+        // a builtin like `source`'s `.`
+        state.init_commands(vec![Box::new(EnvCommand::new(".".into(), PathBuf::from("/bin/true")))]);
+        let checker = Checker::new(&state);
+        let mut parser = Parser::new(Lexer::new("def hello [] { ls; }".chars()));
+        checker.check(&parser.parse().unwrap().unwrap()).unwrap();
+
+        assert_eq!(checker.suggest("sl"), Some("ls".into()));
+        assert_eq!(checker.suggest("helo"), Some("hello".into()));
+        assert_eq!(checker.suggest("cargo"), None);
+        assert_eq!(checker.suggest("x"), None);
+        assert_eq!(checker.suggest("x=1"), None);
+    }
+
+    fn lint(source: &str) -> Vec<String> {
+        let state = state();
+        let checker = Checker::new(&state);
+        let tree = Parser::new(Lexer::new(source.chars())).parse_tree();
+        checker
+            .lint(&tree, source)
+            .into_iter()
+            .map(|diagnostic| diagnostic.code.unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn lint_passes() {
+        let source = "def f [a] { ls $a $b; }\n\
+                      f 1 2\n\
+                      def f [] { ls; }\n\
+                      ls > ./missing/out\n\
+                      lss\n\
+                      exit\n\
+                      ls";
+        // `exit` is no builtin here, so it is unknown as well
+        assert_eq!(
+            lint(source),
+            ["XS101", "XS102", "XS105", "XS104", "XS002", "XS002", "XS103"]
+        );
+        assert_eq!(lint("ls $"), ["XS001"]);
+    }
+
+    #[test]
+    fn lint_silenced() {
+        let source = "ls $nope # xshell disable=XS101\n\
+                      # xshell disable=XS002,XS103\n\
+                      lss\n\
+                      lss\n\
+                      ls \"xshell disable=XS101\" $nope";
+        // only comments silence, not the same text in an argument
+        assert_eq!(lint(source), ["XS002", "XS101"]);
+    }
+
+    #[test]
+    fn existing_paths() {
+        let state = state();
+        let checker = Checker::new(&state);
+        let mut parser = Parser::new(Lexer::new("ls ./Cargo.toml ./missing".chars()));
+        let ast = parser.parse().unwrap().unwrap();
+        let highlights = checker.highlights(&ast);
+
+        assert_eq!(highlights.len(), 2);
+        assert_eq!(highlights[0].role, Role::Command);
+        assert_eq!((highlights[1].span.clone(), highlights[1].exists), (3..15, true));
     }
 }
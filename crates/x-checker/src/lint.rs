@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use x_protocol::ast::{Expression, SyntaxTree, WordPart, AST};
+use x_protocol::diagnostic::Diagnostic;
+use x_protocol::{Token, Tokens};
+
+use crate::Checker;
+
+/// Use of a variable that is neither a parameter, a shell variable nor in
+/// the environment.
+pub const UNDEFINED_VARIABLE: &str = "XS101";
+/// A function called with more or fewer arguments than it has parameters.
+pub const ARGUMENT_COUNT: &str = "XS102";
+/// Statements after `exit`.
+pub const UNREACHABLE: &str = "XS103";
+/// A redirection into a directory that does not exist.
+pub const MISSING_DIRECTORY: &str = "XS104";
+/// A function defined twice.
+pub const DUPLICATE_FUNCTION: &str = "XS105";
+
+/// Comment silencing codes on its own line and the line after, as in
+/// `# xshell disable=XS101,XS104`.
+const DISABLE: &str = "xshell disable=";
+
+impl Checker<'_> {
+    /// Check a whole script without running it: syntax errors, unknown
+    /// commands, and the passes whose codes are listed above. Diagnostics
+    /// come in input order, minus the ones silenced with a
+    /// `# xshell disable=CODE` comment.
+    pub fn lint(&self, tree: &SyntaxTree, source: &str) -> Vec<Diagnostic> {
+        let mut lint = Lint {
+            checker: self,
+            source,
+            functions: HashMap::new(),
+            diagnostics: tree
+                .errors
+                .iter()
+                .map(|err| Diagnostic::from_err(err, source))
+                .collect(),
+        };
+        lint.definitions(&tree.stmts);
+        lint.stmts(&tree.stmts, &[]);
+
+        let mut diagnostics: Vec<Diagnostic> = lint
+            .diagnostics
+            .into_iter()
+            .filter(|diagnostic| !silenced(tree, source, diagnostic))
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.primary.span.start);
+        diagnostics
+    }
+}
+
+struct Lint<'a, 'b> {
+    checker: &'a Checker<'b>,
+    source: &'a str,
+    /// Parameter count and name span of every function the script defines.
+    functions: HashMap<String, (usize, Range<usize>)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Lint<'_, '_> {
+    fn definitions(&mut self, stmts: &[AST]) {
+        for stmt in stmts {
+            let AST::Function { name, parameters, block } = stmt else {
+                continue;
+            };
+            let function = name.ty.to_string();
+            if let Some((_, first)) = self.functions.get(&function) {
+                self.diagnostics.push(
+                    Diagnostic::warning(
+                        &format!("Function `{}` is defined more than once.", function),
+                        name.span.clone(),
+                        "redefined here",
+                    )
+                    .label(first.clone(), "first defined here")
+                    .code(DUPLICATE_FUNCTION),
+                );
+            } else {
+                self.functions
+                    .insert(function, (parameters.variables.len(), name.span.clone()));
+            }
+            self.definitions(&block.stmts);
+        }
+    }
+
+    /// `scope` holds the parameters of the enclosing function.
+    fn stmts(&mut self, stmts: &[AST], scope: &[String]) {
+        for (i, stmt) in stmts.iter().enumerate() {
+            // the commands of a pipeline are checked one by one below
+            if !matches!(stmt, AST::Pipeline(_)) {
+                self.check(stmt);
+            }
+            match stmt {
+                AST::Command { name, args } => {
+                    self.command(name, args, scope);
+
+                    let rest = &stmts[i + 1..];
+                    if name.ty.to_string() == "exit" && !rest.is_empty() {
+                        let span = rest[0].span().start..rest[rest.len() - 1].span().end;
+                        self.diagnostics.push(
+                            Diagnostic::warning("Unreachable code.", span, "never runs")
+                                .label(name.span.clone(), "the shell exits here")
+                                .code(UNREACHABLE),
+                        );
+                        break;
+                    }
+                }
+                AST::Function { parameters, block, .. } => {
+                    let scope: Vec<String> =
+                        parameters.variables.iter().map(|v| v.ty.to_string()).collect();
+                    self.stmts(&block.stmts, &scope);
+                }
+                AST::Pipeline(commands) => {
+                    for command in commands {
+                        let AST::Command { name, args } = command else {
+                            continue;
+                        };
+                        self.check(command);
+                        self.command(name, args, scope);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn command(&mut self, name: &Token, args: &[Expression], scope: &[String]) {
+        for arg in args {
+            self.word(arg, scope);
+        }
+        self.redirections(args);
+        self.arguments(&name.ty.to_string(), &name.span, args);
+    }
+
+    fn check(&mut self, stmt: &AST) {
+        if let Err(err) = self.checker.check(stmt) {
+            self.diagnostics.push(self.checker.diagnostic(&err, self.source));
+        }
+    }
+
+    /// Check the parameters and command substitutions in `arg`.
+    fn word(&mut self, arg: &Expression, scope: &[String]) {
+        let Expression::Word(word) = arg else {
+            return;
+        };
+        word.walk(&mut |part| match part {
+            WordPart::Parameter { name, span } => self.variable(name, span, scope),
+            WordPart::CommandSubstitution { stmts, .. } => self.stmts(stmts, scope),
+            _ => {}
+        });
+    }
+
+    fn variable(&mut self, name: &str, span: &Range<usize>, scope: &[String]) {
+        // special parameters like `$?` are always set
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            return;
+        }
+        if scope.iter().any(|variable| variable == name) || self.checker.state.get_variable(name).is_some() {
+            return;
+        }
+        self.diagnostics.push(
+            Diagnostic::warning(
+                &format!("Variable `${}` is not defined.", name),
+                span.clone(),
+                "expands to nothing",
+            )
+            .code(UNDEFINED_VARIABLE),
+        );
+    }
+
+    fn arguments(&mut self, function: &str, span: &Range<usize>, args: &[Expression]) {
+        let Some((expected, definition)) = self.functions.get(function) else {
+            return;
+        };
+        let given = arguments(args);
+        if given == *expected {
+            return;
+        }
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        self.diagnostics.push(
+            Diagnostic::warning(
+                &format!(
+                    "`{}` takes {} argument{} but {} {} given.",
+                    function,
+                    expected,
+                    plural(*expected),
+                    given,
+                    if given == 1 { "was" } else { "were" }
+                ),
+                span.clone(),
+                "",
+            )
+            .label(definition.clone(), "defined here")
+            .code(ARGUMENT_COUNT),
+        );
+    }
+
+    fn redirections(&mut self, args: &[Expression]) {
+        let state = self.checker.state;
+        for target in redirect_targets(args) {
+            let Expression::Word(word) = target else {
+                continue;
+            };
+            let Some(path) = word.literal() else {
+                continue;
+            };
+            let path = state.resolve_path(&path);
+            let Some(dir) = path.parent() else {
+                continue;
+            };
+            if dir.as_os_str().is_empty() || state.fs_cache.exists(dir) {
+                continue;
+            }
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    &format!("Directory `{}` does not exist.", dir.display()),
+                    word.span.clone(),
+                    "redirected here",
+                )
+                .code(MISSING_DIRECTORY),
+            );
+        }
+    }
+}
+
+fn is_redirect(arg: &Expression) -> bool {
+    matches!(arg, Expression::Symbol(token) if matches!(token.ty, Tokens::Symbol('<' | '>')))
+}
+
+/// Arguments a command receives, leaving out redirections.
+fn arguments(args: &[Expression]) -> usize {
+    let targets = redirect_targets(args).len();
+    args.len() - args.iter().filter(|arg| is_redirect(arg)).count() - targets
+}
+
+/// The word after each `<`, `>` or `>>`.
+fn redirect_targets(args: &[Expression]) -> Vec<&Expression> {
+    let mut targets = vec![];
+    let mut i = 0;
+    while i < args.len() {
+        if is_redirect(&args[i]) {
+            while i < args.len() && is_redirect(&args[i]) {
+                i += 1;
+            }
+            if let Some(target) = args.get(i) {
+                targets.push(target);
+            }
+        }
+        i += 1;
+    }
+    targets
+}
+
+/// Whether a `# xshell disable=` comment on the diagnostic's line or the
+/// line before names its code.
+fn silenced(tree: &SyntaxTree, source: &str, diagnostic: &Diagnostic) -> bool {
+    let Some(code) = &diagnostic.code else {
+        return false;
+    };
+    let line_of = |at: usize| source.chars().take(at).filter(|c| *c == '\n').count();
+    let line = line_of(diagnostic.primary.span.start);
+
+    tree.tokens
+        .iter()
+        .filter_map(|token| match &token.ty {
+            Tokens::Comment(text) => Some((line_of(token.span.start), text)),
+            _ => None,
+        })
+        .filter(|(at, _)| *at == line || *at + 1 == line)
+        .filter_map(|(_, text)| text.trim_start_matches('#').trim_start().strip_prefix(DISABLE))
+        .any(|codes| {
+            codes
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .any(|disabled| disabled == code)
+        })
+}
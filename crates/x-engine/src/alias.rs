@@ -0,0 +1,148 @@
+use x_parser::{Lexer, Parser};
+use x_protocol::ast::{Expression, Word, WordPart, AST};
+use x_protocol::{ShellState, Token, Tokens};
+
+/// Replace an alias naming the command with the statements it stands for.
+/// An alias is not expanded again inside its own expansion, so
+/// `alias ls='ls -F'` runs the program, and one whose value ends in a space
+/// also expands the word after it, as in `alias sudo='sudo '`.
+pub(crate) fn expand_alias(state: &ShellState, name: Token, args: Vec<Expression>) -> Vec<AST> {
+    expand(state, name, args, &mut vec![])
+}
+
+fn expand(state: &ShellState, name: Token, mut args: Vec<Expression>, seen: &mut Vec<String>) -> Vec<AST> {
+    let text = name.ty.to_string();
+    let Some(value) = state.aliases.get(&text).filter(|_| !seen.contains(&text)) else {
+        return vec![AST::Command { name, args }];
+    };
+    seen.push(text);
+    if value.ends_with([' ', '\t']) {
+        args = chain(state, args, seen);
+    }
+
+    let mut stmts = Parser::new(Lexer::new(value.chars())).parse_tree().stmts;
+    let last = match stmts.pop() {
+        Some(AST::Command { name, args: own }) => {
+            expand(state, name, own.into_iter().chain(args).collect(), &mut seen.clone())
+        }
+        // an alias of nothing leaves the arguments to run as a command
+        last => {
+            stmts.extend(last);
+            let mut args = args.into_iter();
+            match args.next().as_ref().and_then(command_name) {
+                Some(name) => expand(state, name, args.collect(), &mut seen.clone()),
+                None => vec![],
+            }
+        }
+    };
+    let mut expanded = vec![];
+    for stmt in stmts {
+        match stmt {
+            AST::Command { name, args } => expanded.extend(expand(state, name, args, &mut seen.clone())),
+            stmt => expanded.push(stmt),
+        }
+    }
+    expanded.extend(last);
+    expanded
+}
+
+/// Expand the alias `args` starts with, if any, into the words of its
+/// command, which may start with an alias again, and go on with the word
+/// after it when its value also ends in a space.
+fn chain(state: &ShellState, mut args: Vec<Expression>, seen: &mut Vec<String>) -> Vec<Expression> {
+    let Some(name) = args.first().and_then(command_name) else {
+        return args;
+    };
+    let text = name.ty.to_string();
+    let Some(value) = state.aliases.get(&text).filter(|_| !seen.contains(&text)) else {
+        return args;
+    };
+    let tree = Parser::new(Lexer::new(value.chars())).parse_tree();
+    let [AST::Command { name, args: own }] = &tree.stmts[..] else {
+        return args;
+    };
+    seen.push(text);
+    let rest = args.split_off(1);
+    let rest = if value.ends_with([' ', '\t']) { chain(state, rest, &mut seen.clone()) } else { rest };
+    let words = std::iter::once(word(name)).chain(own.iter().cloned()).chain(rest).collect();
+    chain(state, words, seen)
+}
+
+/// The word `arg` as a command name, when it is written plainly.
+fn command_name(arg: &Expression) -> Option<Token> {
+    let Expression::Word(word) = arg else {
+        return None;
+    };
+    let text = word.literal()?;
+    let plain = matches!(&word.parts[..], [WordPart::Literal { .. }]);
+    plain.then(|| Token {
+        ty: Tokens::Ident(text),
+        span: word.span.clone(),
+        index: 0,
+    })
+}
+
+/// A command name as a plain word.
+fn word(name: &Token) -> Expression {
+    Expression::Word(Word {
+        parts: vec![WordPart::Literal {
+            text: name.ty.to_string(),
+            span: name.span.clone(),
+        }],
+        span: name.span.clone(),
+    })
+}
+
+#[cfg(test)]
+mod alias_test {
+    use std::path::PathBuf;
+
+    use x_parser::{Lexer, Parser};
+    use x_protocol::ast::{Expression, AST};
+    use x_protocol::ShellState;
+
+    use crate::alias::expand_alias;
+
+    /// The commands `source` runs once aliases are expanded, as words.
+    fn expanded(state: &ShellState, source: &str) -> Vec<String> {
+        let mut stmts = Parser::new(Lexer::new(source.chars())).parse_tree().stmts;
+        let Some(AST::Command { name, args }) = stmts.pop() else {
+            panic!("not a command");
+        };
+        expand_alias(state, name, args)
+            .into_iter()
+            .map(|stmt| match stmt {
+                AST::Command { name, args } => {
+                    let words = args.iter().map(|arg| match arg {
+                        Expression::Word(word) => word.literal().unwrap_or_default(),
+                        Expression::Symbol(token) => token.ty.to_string(),
+                    });
+                    std::iter::once(name.ty.to_string()).chain(words).collect::<Vec<_>>().join(" ")
+                }
+                stmt => format!("{:?}", stmt),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expansion() {
+        let mut state = ShellState::new(PathBuf::from("/"), "user".into());
+        for (name, value) in [
+            ("ll", "ls -la"),
+            ("ls", "ls -F"),
+            ("sudo", "sudo "),
+            ("both", "cd /; ll"),
+            ("a", "b"),
+            ("b", "a x"),
+            ("nothing", ""),
+        ] {
+            state.aliases.insert(name.into(), value.into());
+        }
+
+        assert_eq!(expanded(&state, "ll src"), ["ls -F -la src"]);
+        assert_eq!(expanded(&state, "sudo ll"), ["sudo ls -F -la"]);
+        assert_eq!(expanded(&state, "both"), ["cd /", "ls -F -la"]);
+        assert_eq!(expanded(&state, "a 1"), ["a x 1"]);
+        assert_eq!(expanded(&state, "nothing ll"), ["ls -F -la"]);
+    }
+}
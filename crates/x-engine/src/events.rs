@@ -1,13 +1,17 @@
+use std::path::PathBuf;
 use std::time::Duration;
 
-use x_input::Input;
+use x_input::{History, Input};
 use x_protocol::crossterm::event::{read, Event, poll};
 use x_protocol::crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use x_protocol::crossterm::Result;
-use x_protocol::state::ShellState;
+use x_protocol::io::Io;
+use x_protocol::state::{InputState, ShellState};
 use x_render::Render;
+use x_util::home_dir;
 
 use crate::repl::repl;
+use crate::trap::{run_exit_trap, run_idle_traps};
 
 pub struct XShellEvent {
     state: ShellState,
@@ -20,25 +24,59 @@ impl XShellEvent {
 
     pub fn listen_start(&mut self) -> Result<()> {
         let mut render = Render::default();
-        let mut input = Input::default();
+        let mut input = Input {
+            history: History::open(self.history_path()),
+            ..Default::default()
+        };
 
+        self.state.interactive = true;
         enable_raw_mode()?;
         render.output_state(&self.state)?;
-        self.state.updata();
+        // programs resolve on their own, the scan is for highlighting and
+        // completion, which pick it up when it is done
+        self.state.commands.scan(&self.state.search_path());
+        let mut scanning = true;
  
         while !self.state.is_exit {
+            if scanning && !self.state.commands.scanning() {
+                scanning = false;
+                repl(&mut render, &mut input, &mut self.state)?;
+            }
+            // a trap runs as its signal comes, not with the next command
+            if run_idle_traps(&mut self.state, &mut render, &mut Io::terminal())? {
+                render.output_state(&self.state)?;
+                repl(&mut render, &mut input, &mut self.state)?;
+            }
             if poll(Duration::from_millis(100))? {
                 match read()? {
                     Event::Key(key) => input.input(&key, &mut self.state),
+                    Event::Resize(width, _) => {
+                        input.state = InputState::NONE;
+                        render.resize(width);
+                    }
                     _ => {}
                 }
             
                 repl(&mut render, &mut input, &mut self.state)?;
-            } else {
             }
         }
 
-        self.exit()
+        let result = self.exit();
+        run_exit_trap(&mut self.state);
+        result
+    }
+
+    /// The status the shell exits with, `$?` or the one given to `exit`.
+    pub fn status(&self) -> i32 {
+        self.state.status
+    }
+
+    /// `$HISTFILE`, or `~/.xshell_history`.
+    fn history_path(&self) -> PathBuf {
+        match self.state.get_variable("HISTFILE") {
+            Some(path) => PathBuf::from(path),
+            None => home_dir().unwrap_or_default().join(".xshell_history"),
+        }
     }
 
     fn exit(&self) -> Result<()> {
@@ -54,6 +92,12 @@ impl Default for XShellEvent {
 
 #[test]
 fn test() {
+    use std::io::IsTerminal;
+
+    // keys are read from a terminal, which tests may run without
+    if !std::io::stdin().is_terminal() {
+        return;
+    }
     let mut x_shell_event = XShellEvent::new(ShellState::default());
     x_shell_event.listen_start().unwrap();
 }
@@ -1,21 +1,339 @@
-use x_protocol::{ast::{AST, Expression}, ShellState, crossterm::terminal::{disable_raw_mode, enable_raw_mode}};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use x_protocol::{ast::{AST, Expression}, command::{Command, ExitStatus}, io::{Input, Io, Sink}, io_message, ShellErr, ShellState, Tokens, crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled}};
+use x_checker::Checker;
 use x_render::Render;
 
-pub fn execute(state: &mut ShellState, render: &mut Render, asts: Vec<AST>) {
+use crate::alias::expand_alias;
+use crate::expand::expand;
+use crate::pipeline::pipeline;
+use crate::trap::run_traps;
+
+/// User function run in place of commands that are not found.
+const NOT_FOUND_HANDLER: &str = "command_not_found";
+
+/// Run `asts` in order, until one of them exits the shell.
+pub fn execute(state: &mut ShellState, render: &mut Render, asts: Vec<AST>, io: &mut Io) {
     for ast in asts {
         match ast {
-            AST::Command { name, args } => command(state, render, name.ty.to_string(), args),
-            _ => {}
+            // the expansion is complete, so what it gives runs as it is
+            AST::Command { name, args } => {
+                for ast in expand_alias(state, name, args) {
+                    if state.is_exit {
+                        break;
+                    }
+                    statement(state, render, ast, io);
+                }
+            }
+            ast => statement(state, render, ast, io),
+        }
+        if state.is_exit {
+            break;
         }
-    } 
+        run_traps(state, render, io);
+    }
+    // commands may have created or removed files
+    state.fs_cache.clear();
 }
 
-fn command(state: &mut ShellState, render: &mut Render, name: String, args: Vec<Expression>) {
-    let Some(command) = state.commands.iter().find(|command| { command.get_name() == name }) else {
+pub(crate) fn statement(state: &mut ShellState, render: &mut Render, ast: AST, io: &mut Io) {
+    match ast {
+        AST::Command { name, args } => command(state, render, name.ty.to_string(), args, io),
+        AST::Function { ref name, .. } => {
+            state.functions.insert(name.ty.to_string(), ast.clone());
+        }
+        AST::Pipeline(commands) => pipeline(state, render, commands, io),
+        _ => {}
+    }
+}
+
+/// Run `stmts` and return what they print, without trailing newlines.
+pub(crate) fn capture(state: &mut ShellState, stmts: Vec<AST>) -> String {
+    let mut io = Io::capture();
+    execute(state, &mut Render::default(), stmts, &mut io);
+    // `exit` only ends the substitution, as it would a subshell
+    state.is_exit = false;
+    let mut output = io.stdout.take().string;
+    output.truncate(output.trim_end_matches('\n').len());
+    output
+}
+
+/// A stream a command gets in place of the one it would inherit.
+pub(crate) enum Redirection {
+    Stdin(Input),
+    Stdout(Sink),
+    Stderr(Sink),
+}
+
+fn command(state: &mut ShellState, render: &mut Render, name: String, args: Vec<Expression>, io: &mut Io) {
+    let Some((expanded, mut redirections)) = arguments(state, &args, io) else {
         return;
     };
-    
-    disable_raw_mode().unwrap();
-    command.clone().run(state, args.iter().map(|e| { e.to_string() }).collect());
-    enable_raw_mode().unwrap();
+
+    // swap the redirected streams in, and back once the command is done
+    for redirection in &mut redirections {
+        swap(io, redirection);
+    }
+    run(state, render, name, expanded, io);
+    for redirection in redirections.iter_mut().rev() {
+        swap(io, redirection);
+    }
+}
+
+/// The words `args` expand to and the redirections among them. What goes
+/// wrong is reported and fails the command.
+pub(crate) fn arguments(state: &mut ShellState, args: &[Expression], io: &mut Io) -> Option<(Vec<String>, Vec<Redirection>)> {
+    let mut expanded = vec![];
+    let mut redirections = vec![];
+    let mut i = 0;
+    while i < args.len() {
+        let redirection = match redirection(state, args, &mut i) {
+            Ok(redirection) => redirection,
+            Err(e) => {
+                error(io, &format!("xshell: {}", e));
+                state.set_status(vec![ExitStatus::FAILURE]);
+                return None;
+            }
+        };
+        match redirection {
+            Some(redirection) => redirections.push(redirection),
+            None => match &args[i] {
+                Expression::Word(word) => match expand(state, word) {
+                    Ok(words) => expanded.extend(words),
+                    Err(e) => {
+                        error(io, &format!("xshell: {}", e));
+                        state.set_status(vec![ExitStatus::FAILURE]);
+                        return None;
+                    }
+                },
+                Expression::Symbol(token) => expanded.push(token.ty.to_string()),
+            },
+        }
+        i += 1;
+    }
+    Some((expanded, redirections))
+}
+
+pub(crate) fn swap(io: &mut Io, redirection: &mut Redirection) {
+    match redirection {
+        Redirection::Stdin(input) => std::mem::swap(&mut io.stdin, input),
+        Redirection::Stdout(sink) => std::mem::swap(&mut io.stdout, sink),
+        Redirection::Stderr(sink) => std::mem::swap(&mut io.stderr, sink),
+    }
+}
+
+/// The redirection starting at `args[*i]`, if there is one, leaving `i` at
+/// its last argument. `<`, `>` and `>>` may follow a file descriptor
+/// written right before them, as in `2>`.
+fn redirection(state: &mut ShellState, args: &[Expression], i: &mut usize) -> Result<Option<Redirection>, String> {
+    let symbol = |at: usize| match args.get(at) {
+        Some(Expression::Symbol(token)) => match token.ty {
+            Tokens::Symbol(c @ ('<' | '>')) => Some((c, token.span.clone())),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let mut at = *i;
+    let mut fd = None;
+    if let Some(Expression::Word(word)) = args.get(at) {
+        match (word.literal().as_deref(), symbol(at + 1)) {
+            (Some(digit @ ("0" | "1" | "2")), Some((_, span))) if word.span.end == span.start => {
+                fd = digit.parse().ok();
+                at += 1;
+            }
+            _ => return Ok(None),
+        }
+    }
+    let Some((operator, span)) = symbol(at) else {
+        return Ok(None);
+    };
+    let append = operator == '>' && symbol(at + 1).is_some_and(|(c, next)| c == '>' && next.start == span.end);
+    if append {
+        at += 1;
+    }
+
+    let Some(Expression::Word(target)) = args.get(at + 1) else {
+        return Err(format!("syntax error: `{}` needs a file", operator));
+    };
+    let path = match expand(state, target)?.as_slice() {
+        [path] => path.clone(),
+        _ => return Err("ambiguous redirect".into()),
+    };
+    *i = at + 1;
+
+    let resolved = state.resolve_path(&path);
+    let opened = if operator == '<' {
+        File::open(&resolved)
+    } else {
+        OpenOptions::new().write(true).create(true).append(append).truncate(!append).open(&resolved)
+    };
+    let file = opened.map_err(|e| format!("{}: {}", path, io_message(&e)))?;
+    Ok(Some(match (operator, fd.unwrap_or(if operator == '<' { 0 } else { 1 })) {
+        ('<', _) => Redirection::Stdin(Input::File(file)),
+        (_, 2) => Redirection::Stderr(Sink::File(file)),
+        _ => Redirection::Stdout(Sink::File(file)),
+    }))
+}
+
+/// How deep functions may call each other, well before the stack runs out.
+const MAX_DEPTH: usize = 200;
+
+fn run(state: &mut ShellState, render: &mut Render, name: String, args: Vec<String>, io: &mut Io) {
+    if let Some(AST::Function { parameters, block, .. }) = state.functions.get(&name).cloned() {
+        if state.depth >= MAX_DEPTH {
+            error(io, &format!("xshell: {}: maximum function nesting level exceeded ({})", name, MAX_DEPTH));
+            return state.set_status(vec![ExitStatus::FAILURE]);
+        }
+        // the parameters hide variables of the same name until it returns
+        let mut shadowed = vec![];
+        for (variable, value) in parameters.variables.iter().zip(args) {
+            let variable = variable.ty.to_string();
+            let old = state.variables.insert(variable.clone(), value);
+            shadowed.push((variable, old));
+        }
+        state.depth += 1;
+        execute(state, render, block.stmts, io);
+        state.depth -= 1;
+        for (variable, old) in shadowed.into_iter().rev() {
+            match old {
+                Some(old) => state.variables.insert(variable, old),
+                None => state.variables.remove(&variable),
+            };
+        }
+        return;
+    }
+
+    // a name with a `/` is the path of a program, never looked up
+    let command = if name.contains('/') {
+        state.program_at(&name).map(|program| Box::new(program) as Box<dyn Command>)
+    } else {
+        match state.command(&name) {
+            Some(command) => Ok(command),
+            None => return not_found(state, render, name, args, io),
+        }
+    };
+
+    let status = command.and_then(|command| {
+        // scripts run without raw mode
+        let raw = is_raw_mode_enabled().unwrap_or(false);
+        if raw {
+            disable_raw_mode().unwrap();
+        }
+        let status = command.run(state, args, io);
+        if raw {
+            enable_raw_mode().unwrap();
+        }
+        status
+    });
+    let status = status.unwrap_or_else(|e| failed(io, e));
+    state.set_status(vec![status]);
+}
+
+/// Report why a program could not run, giving the status it fails with.
+pub(crate) fn failed(io: &mut Io, e: ShellErr) -> ExitStatus {
+    error(io, &format!("xshell: {}", e));
+    // the codes POSIX shells use for a program that cannot be found or run
+    ExitStatus::code(if matches!(e, ShellErr::NotFound(_)) { 127 } else { 126 })
+}
+
+/// Hand an unknown command to the `command_not_found` function, with the
+/// name as first argument, or report it.
+fn not_found(state: &mut ShellState, render: &mut Render, name: String, args: Vec<String>, io: &mut Io) {
+    if name != NOT_FOUND_HANDLER && state.functions.contains_key(NOT_FOUND_HANDLER) {
+        let args = std::iter::once(name).chain(args).collect();
+        return run(state, render, NOT_FOUND_HANDLER.into(), args, io);
+    }
+
+    let message = match Checker::new(state).suggest(&name) {
+        Some(suggestion) => format!("xshell: {}: command not found, did you mean `{}`?", name, suggestion),
+        None => format!("xshell: {}: command not found", name),
+    };
+    error(io, &message);
+    state.set_status(vec![ExitStatus::code(127)]);
+}
+
+/// Print `message` on its own line of the command's stderr, in raw mode
+/// too.
+fn error(io: &mut Io, message: &str) {
+    let raw = matches!(io.stderr, Sink::Stderr) && is_raw_mode_enabled().unwrap_or(false);
+    let end = if raw { "\r\n" } else { "\n" };
+    let _ = write!(io.stderr, "{}{}", message, end);
+}
+
+#[cfg(test)]
+mod execute_test {
+    use std::path::PathBuf;
+
+    use x_protocol::command::EnvCommand;
+    use x_protocol::io::{Io, Sink};
+    use x_protocol::output::Output;
+    use x_protocol::ShellState;
+
+    use crate::script::run_script_with;
+
+    /// Run `source` with `echo`, `tr` and `false`, giving what it printed
+    /// and `$?`.
+    fn run(source: &str) -> (String, String, i32) {
+        let mut state = ShellState::new(PathBuf::from("/"), "user".into());
+        state.init_commands(
+            ["echo", "tr", "false"]
+                .into_iter()
+                .map(|name| Box::new(EnvCommand::new(name.into(), PathBuf::from("/usr/bin").join(name))) as _)
+                .collect(),
+        );
+        state.variables.insert("a".into(), "outer".into());
+        let mut io = Io {
+            stderr: Sink::Capture(Output::default()),
+            ..Io::capture()
+        };
+        let status = run_script_with(&mut state, source, "-c", &mut io);
+        (io.stdout.take().string, io.stderr.take().string, status)
+    }
+
+    #[test]
+    fn functions() {
+        // a function defined in a body exists once the body has run
+        let (stdout, ..) = run("def f [] { def g [] { echo g; } } g; f; g");
+        assert_eq!(stdout, "g\n");
+    }
+
+    #[test]
+    fn function_parameters() {
+        let (stdout, ..) = run("def f [a b] { echo $a $b; } f 1 2; echo $a; echo $b");
+        assert_eq!(stdout, "1 2\nouter\n\n");
+    }
+
+    #[test]
+    fn recursion_limit() {
+        let (stdout, stderr, status) = run("def r [] { r; } r; echo after");
+        assert_eq!(stdout, "after\n");
+        assert_eq!(stderr.lines().next(), Some("xshell: r: maximum function nesting level exceeded (200)"));
+        assert_eq!(status, 0);
+        let (_, _, status) = run("def r [] { r; } r");
+        assert_eq!(status, 1);
+    }
+
+    #[test]
+    fn pipeline() {
+        let (stdout, ..) = run("echo hi | tr a-z A-Z | tr H J; echo $PIPESTATUS");
+        assert_eq!(stdout, "JI\n0 0 0\n");
+        let (stdout, _, status) = run("echo hi | false");
+        assert_eq!((stdout.as_str(), status), ("", 1));
+        let (stdout, _, status) = run("false | tr a b; echo $PIPESTATUS");
+        assert_eq!((stdout.as_str(), status), ("1 0\n", 0));
+    }
+
+    #[test]
+    fn concurrent_pipeline() {
+        // `yes` only stops once `head` is gone
+        let (stdout, ..) = run("/usr/bin/yes | /usr/bin/head -1");
+        assert_eq!(stdout, "y\n");
+        // functions and programs feed each other either way
+        let (stdout, ..) = run("def f [] { echo fn; } f | tr a-z A-Z | tr F G");
+        assert_eq!(stdout, "GN\n");
+        let (stdout, ..) = run("/usr/bin/yes | /usr/bin/head -2 | tr y n");
+        assert_eq!(stdout, "n\nn\n");
+    }
 }
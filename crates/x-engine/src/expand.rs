@@ -0,0 +1,401 @@
+use std::fs;
+use std::path::PathBuf;
+
+use x_protocol::ast::{Word, WordPart};
+use x_protocol::ShellState;
+use x_util::{home_dir, user_home};
+
+use crate::execute::capture;
+
+/// One char of a word once expanded, with what it matches as a glob.
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Char(char),
+    /// `*`
+    Any,
+    /// `?`
+    One,
+    /// `[a-z]`, as ranges of chars.
+    Class { ranges: Vec<(char, char)>, negated: bool },
+}
+
+impl Piece {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Piece::Char(p) => *p == c,
+            Piece::Any | Piece::One => true,
+            Piece::Class { ranges, negated } => ranges.iter().any(|(from, to)| (*from..=*to).contains(&c)) != *negated,
+        }
+    }
+}
+
+/// Expand `word` into the arguments it stands for: parameters, tildes,
+/// arithmetic and command substitutions are replaced, quotes removed, and
+/// an unquoted glob becomes the files it matches, sorted, or stays as
+/// written when none do.
+pub fn expand(state: &mut ShellState, word: &Word) -> Result<Vec<String>, String> {
+    let mut pieces = vec![];
+    // the word as written where globs are, for when nothing matches
+    let mut text = String::new();
+    let mut glob = false;
+    parts(state, &word.parts, &mut pieces, &mut text, &mut glob)?;
+
+    if glob {
+        let mut matches = matches(state, &pieces);
+        if !matches.is_empty() {
+            matches.sort();
+            return Ok(matches);
+        }
+    }
+    Ok(vec![text])
+}
+
+fn parts(
+    state: &mut ShellState,
+    parts: &[WordPart],
+    pieces: &mut Vec<Piece>,
+    text: &mut String,
+    glob: &mut bool,
+) -> Result<(), String> {
+    for part in parts {
+        match part {
+            WordPart::Literal { text: value, .. } | WordPart::SingleQuoted { text: value, .. } => {
+                push(pieces, text, value)
+            }
+            WordPart::DoubleQuoted { parts: inner, .. } => self::parts(state, inner, pieces, text, glob)?,
+            WordPart::Parameter { name, .. } => push(pieces, text, &parameter(state, name)),
+            WordPart::CommandSubstitution { stmts, .. } => {
+                let output = capture(state, stmts.clone());
+                push(pieces, text, &output)
+            }
+            WordPart::Arithmetic { expression, .. } => {
+                push(pieces, text, &Arithmetic::new(state, expression).evaluate()?.to_string())
+            }
+            WordPart::Glob { pattern, .. } => {
+                *glob = true;
+                text.push_str(pattern);
+                pieces.push(glob_piece(pattern));
+            }
+            WordPart::Tilde { user, .. } => push(pieces, text, &tilde(state, user)),
+        }
+    }
+    Ok(())
+}
+
+/// `~`, the home directory of `$HOME`, or `~user`, that of `user`. The
+/// name of a user that does not exist is left as it is.
+fn tilde(state: &ShellState, user: &str) -> String {
+    let home = if user.is_empty() {
+        match state.get_variable("HOME") {
+            Some(home) => return home.clone(),
+            None => home_dir(),
+        }
+    } else {
+        user_home(user)
+    };
+    match home {
+        Some(home) => home.to_string_lossy().into_owned(),
+        None => format!("~{}", user),
+    }
+}
+
+/// Add text that matches only itself.
+fn push(pieces: &mut Vec<Piece>, text: &mut String, value: &str) {
+    pieces.extend(value.chars().map(Piece::Char));
+    text.push_str(value);
+}
+
+fn parameter(state: &ShellState, name: &str) -> String {
+    match name {
+        "?" => state.status.to_string(),
+        "PIPESTATUS" => {
+            let statuses: Vec<String> = state.pipestatus.iter().map(|status| status.status().to_string()).collect();
+            statuses.join(" ")
+        }
+        "$" => std::process::id().to_string(),
+        name => state.get_variable(name).cloned().unwrap_or_default(),
+    }
+}
+
+fn glob_piece(pattern: &str) -> Piece {
+    match pattern {
+        "*" => Piece::Any,
+        "?" => Piece::One,
+        class => {
+            let mut chars: Vec<char> = class[1..class.len() - 1].chars().collect();
+            let negated = matches!(chars.first(), Some('!' | '^'));
+            if negated {
+                chars.remove(0);
+            }
+            let mut ranges = vec![];
+            let mut i = 0;
+            while i < chars.len() {
+                if i + 2 < chars.len() && chars[i + 1] == '-' {
+                    ranges.push((chars[i], chars[i + 2]));
+                    i += 3;
+                } else {
+                    ranges.push((chars[i], chars[i]));
+                    i += 1;
+                }
+            }
+            Piece::Class { ranges, negated }
+        }
+    }
+}
+
+/// Paths matching `pieces`, relative to the working directory unless the
+/// pattern is absolute.
+fn matches(state: &ShellState, pieces: &[Piece]) -> Vec<String> {
+    let components: Vec<&[Piece]> = pieces.split(|piece| *piece == Piece::Char('/')).collect();
+    let (mut found, components) = match components.split_first() {
+        Some(([], rest)) => (vec![("/".to_string(), PathBuf::from("/"))], rest),
+        _ => (vec![(String::new(), state.resolve_path(""))], &components[..]),
+    };
+
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        let mut next = vec![];
+        for (shown, dir) in found {
+            let separator = if shown.is_empty() || shown.ends_with('/') { "" } else { "/" };
+            if component.iter().all(|piece| matches!(piece, Piece::Char(_))) {
+                let name: String = component
+                    .iter()
+                    .map(|piece| if let Piece::Char(c) = piece { *c } else { '*' })
+                    .collect();
+                let path = dir.join(&name);
+                if path.exists() {
+                    next.push((format!("{}{}{}", shown, separator, name), path));
+                }
+                continue;
+            }
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                // hidden files only match a pattern starting with `.`
+                if name.starts_with('.') && component.first() != Some(&Piece::Char('.')) {
+                    continue;
+                }
+                let chars: Vec<char> = name.chars().collect();
+                if glob_match(component, &chars) && (last || entry.path().is_dir()) {
+                    next.push((format!("{}{}{}", shown, separator, name), entry.path()));
+                }
+            }
+        }
+        found = next;
+    }
+    found.into_iter().map(|(shown, _)| shown).collect()
+}
+
+fn glob_match(pieces: &[Piece], name: &[char]) -> bool {
+    match pieces.split_first() {
+        None => name.is_empty(),
+        Some((Piece::Any, rest)) => (0..=name.len()).any(|i| glob_match(rest, &name[i..])),
+        Some((piece, rest)) => name
+            .split_first()
+            .is_some_and(|(c, name)| piece.matches(*c) && glob_match(rest, name)),
+    }
+}
+
+/// An integer expression of `$((...))`: `+ - * / %`, parentheses, numbers
+/// and variables, which count as 0 when they are not numbers.
+struct Arithmetic<'a> {
+    state: &'a ShellState,
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl<'a> Arithmetic<'a> {
+    fn new(state: &'a ShellState, expression: &str) -> Self {
+        Arithmetic {
+            state,
+            chars: expression.chars().collect(),
+            at: 0,
+        }
+    }
+
+    fn evaluate(mut self) -> Result<i64, String> {
+        let value = self.sum()?;
+        match self.peek() {
+            None => Ok(value),
+            Some(c) => Err(format!("arithmetic: unexpected `{}`", c)),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.at).is_some_and(|c| c.is_whitespace()) {
+            self.at += 1;
+        }
+        self.chars.get(self.at).copied()
+    }
+
+    fn sum(&mut self) -> Result<i64, String> {
+        let mut value = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.at += 1;
+            let rhs = self.product()?;
+            value = if op == '+' { value.wrapping_add(rhs) } else { value.wrapping_sub(rhs) };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<i64, String> {
+        let mut value = self.unary()?;
+        while let Some(op @ ('*' | '/' | '%')) = self.peek() {
+            self.at += 1;
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value.wrapping_mul(rhs),
+                _ if rhs == 0 => return Err("arithmetic: division by zero".into()),
+                '/' => value.wrapping_div(rhs),
+                _ => value.wrapping_rem(rhs),
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        match self.peek() {
+            Some('-') => {
+                self.at += 1;
+                Ok(self.unary()?.wrapping_neg())
+            }
+            Some('+') => {
+                self.at += 1;
+                self.unary()
+            }
+            Some('(') => {
+                self.at += 1;
+                let value = self.sum()?;
+                if self.peek() != Some(')') {
+                    return Err("arithmetic: missing `)`".into());
+                }
+                self.at += 1;
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let digits = self.take(|c| c.is_ascii_alphanumeric());
+                digits
+                    .parse()
+                    .map_err(|_| format!("arithmetic: `{}` is not a number", digits))
+            }
+            Some(c) if c == '$' || c.is_ascii_alphabetic() || c == '_' => {
+                if c == '$' {
+                    self.at += 1;
+                }
+                let name = self.take(|c| c.is_ascii_alphanumeric() || c == '_');
+                Ok(parameter(self.state, &name).trim().parse().unwrap_or(0))
+            }
+            Some(c) => Err(format!("arithmetic: unexpected `{}`", c)),
+            None => Err("arithmetic: expression ends early".into()),
+        }
+    }
+
+    fn take(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.at;
+        while self.chars.get(self.at).is_some_and(|c| f(*c)) {
+            self.at += 1;
+        }
+        self.chars[start..self.at].iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod expand_test {
+    use std::path::PathBuf;
+
+    use x_parser::{Lexer, Parser};
+    use x_protocol::ast::{Expression, AST};
+    use x_protocol::command::EnvCommand;
+    use x_protocol::ShellState;
+
+    use crate::expand::expand;
+
+    /// Expand each argument of `echo <args>`.
+    fn echo(state: &mut ShellState, args: &str) -> Vec<Result<Vec<String>, String>> {
+        let source = format!("echo {}", args);
+        let tree = Parser::new(Lexer::new(source.chars())).parse_tree();
+        let AST::Command { args, .. } = &tree.stmts[0] else {
+            panic!("not a command");
+        };
+        args.iter()
+            .map(|arg| match arg {
+                Expression::Word(word) => expand(state, word),
+                Expression::Symbol(_) => panic!("not a word"),
+            })
+            .collect()
+    }
+
+    fn state() -> ShellState {
+        let mut state = ShellState::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")), "user".into());
+        state.variables.insert("a".into(), "1 2".into());
+        state
+    }
+
+    #[test]
+    fn quotes_and_parameters() {
+        let mut state = state();
+        let words = echo(&mut state, r#"'$a' "$a!" x${a}y \$a $none"#);
+        let expected = ["$a", "1 2!", "x1 2y", "$a", ""];
+        assert_eq!(words, expected.map(|word| Ok(vec![word.to_string()])));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut state = state();
+        assert_eq!(echo(&mut state, "$((1 + 2 * (3 - $b) % 4))"), [Ok(vec!["3".to_string()])]);
+        assert_eq!(echo(&mut state, "$((-7 / 2))"), [Ok(vec!["-3".to_string()])]);
+        assert_eq!(echo(&mut state, "$((1 / 0))"), [Err("arithmetic: division by zero".to_string())]);
+    }
+
+    #[test]
+    fn command_substitution() {
+        let mut state = state();
+        state.init_commands(vec![Box::new(EnvCommand::new("sh".into(), PathBuf::from("/bin/sh")))]);
+        let words = echo(&mut state, r#"x$(sh -c 'echo 1; echo; echo')y "$(sh -c 'exit 3')$?""#);
+        assert_eq!(words, [Ok(vec!["x1y".to_string()]), Ok(vec!["3".to_string()])]);
+    }
+
+    #[test]
+    fn globs() {
+        let mut state = state();
+        let words = echo(&mut state, "Cargo.t?ml src/[d-f]x*.rs '*' nothing*");
+        let expected = [
+            vec!["Cargo.toml"],
+            vec!["src/execute.rs", "src/expand.rs"],
+            vec!["*"],
+            vec!["nothing*"],
+        ];
+        assert_eq!(words, expected.map(|words| Ok(words.iter().map(|w| w.to_string()).collect())));
+    }
+
+    #[test]
+    fn tilde() {
+        let mut state = state();
+        state.variables.insert("HOME".into(), "/home/user".into());
+        let words = echo(&mut state, r#"~ ~/x a~ "~" '~/x' ~no-such-user/x"#);
+        let expected = ["/home/user", "/home/user/x", "a~", "~", "~/x", "~no-such-user/x"];
+        assert_eq!(words, expected.map(|word| Ok(vec![word.to_string()])));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tilde_user() {
+        let home = x_util::user_home("root").unwrap();
+        let words = echo(&mut state(), "~root/src");
+        assert_eq!(words, vec![Ok(vec![format!("{}/src", home.display())])]);
+    }
+
+    #[test]
+    fn globs_in_working_directory() {
+        // the shell starts where the process is, which for tests is the crate
+        let mut state = ShellState::default();
+        assert_eq!(echo(&mut state, "Cargo.t?ml src/l*.rs"), [
+            Ok(vec!["Cargo.toml".to_string()]),
+            Ok(vec!["src/lib.rs".to_string()]),
+        ]);
+    }
+}
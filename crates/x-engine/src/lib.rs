@@ -1,8 +1,17 @@
+mod alias;
 mod events;
 mod execute;
+mod expand;
+mod pipeline;
 mod repl;
+mod script;
+mod trap;
 
 pub use events::XShellEvent;
+pub use script::{run_script, run_script_with};
+pub use trap::run_exit_trap;
 pub use x_protocol::ShellState;
-pub use x_protocol::command::Command;
+pub use x_protocol::command::{Command, ExitStatus};
+pub use x_protocol::io::Io;
 pub use x_protocol::Result;
+pub use x_protocol::theme::Theme;
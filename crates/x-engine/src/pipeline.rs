@@ -0,0 +1,258 @@
+use std::io::{self, Cursor, Write};
+use std::mem;
+use std::process::{Child, ChildStdout, Stdio};
+use std::thread::{self, JoinHandle};
+
+use x_protocol::ast::{Expression, AST};
+use x_protocol::command::{EnvCommand, ExitStatus};
+use x_protocol::crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
+use x_protocol::io::{channel, ChannelReader, ChannelWriter, Input, Io, Sink};
+use x_protocol::{ShellState, Token};
+use x_render::Render;
+use x_util::Foreground;
+
+use crate::alias::expand_alias;
+use crate::execute::{arguments, failed, statement, swap, Redirection};
+
+/// A command of a pipeline.
+enum Stage {
+    /// A program, started with the others as the pipeline starts.
+    Program(EnvCommand, Vec<String>, Vec<Redirection>),
+    /// What the shell runs itself, builtins, functions and aliases, once
+    /// the programs are started.
+    Shell(Vec<AST>),
+    /// A program whose arguments could not be expanded.
+    Failed,
+}
+
+/// How a command passes what it prints to the next one.
+enum Link {
+    /// A pipe between two programs.
+    Pipe(io::PipeReader),
+    /// A pipe in memory, when the shell runs one of the two.
+    Channel(ChannelReader),
+}
+
+/// Run the commands of a pipeline at the same time, each reading what the
+/// one before it prints, and record how every one of them finished.
+///
+/// Programs are connected by pipes and start first. The commands the shell
+/// runs itself then run in order, and write to the next command through
+/// memory, so that they never wait for it.
+pub(crate) fn pipeline(state: &mut ShellState, render: &mut Render, commands: Vec<AST>, io: &mut Io) {
+    let stages: Vec<Stage> = commands
+        .into_iter()
+        .filter_map(|ast| match ast {
+            AST::Command { name, args } => Some(stage(state, name, args, io)),
+            _ => None,
+        })
+        .collect();
+    let programs: Vec<bool> = stages.iter().map(|stage| matches!(stage, Stage::Program(..))).collect();
+    let last = stages.len().saturating_sub(1);
+
+    // programs run without raw mode
+    let raw = is_raw_mode_enabled().unwrap_or(false);
+    if raw {
+        disable_raw_mode().unwrap();
+    }
+    // they stay in the shell's process group, which has the terminal
+    let foreground = Foreground::new(false);
+
+    let mut statuses = vec![ExitStatus::SUCCESS; stages.len()];
+    let mut children: Vec<(usize, Child)> = vec![];
+    let mut shell = vec![];
+    // the threads passing data between programs and the shell, and the
+    // ones collecting what programs write to a captured stderr
+    let mut pumps: Vec<JoinHandle<()>> = vec![];
+    let mut errors: Vec<JoinHandle<Vec<u8>>> = vec![];
+    // what the last program prints, when it goes to memory
+    let mut output: Option<ChildStdout> = None;
+
+    let mut link: Option<Link> = None;
+    for (i, stage) in stages.into_iter().enumerate() {
+        let input = link.take();
+        // what this command writes to for the next one to read
+        let mut next = None;
+        if i < last {
+            if programs[i] && programs[i + 1] {
+                match io::pipe() {
+                    Ok((reader, writer)) => {
+                        link = Some(Link::Pipe(reader));
+                        next = Some(Ok(writer));
+                    }
+                    Err(e) => {
+                        statuses[i] = failed(io, e.into());
+                        continue;
+                    }
+                }
+            } else {
+                let (writer, reader) = channel();
+                link = Some(Link::Channel(reader));
+                next = Some(Err(writer));
+            }
+        }
+
+        match stage {
+            Stage::Program(program, args, mut redirections) => {
+                let mut command = program.command(state, args);
+                let redirected = |redirections: &mut Vec<Redirection>, fd: usize| {
+                    let at = redirections.iter().position(|redirection| match redirection {
+                        Redirection::Stdin(_) => fd == 0,
+                        Redirection::Stdout(_) => fd == 1,
+                        Redirection::Stderr(_) => fd == 2,
+                    })?;
+                    Some(redirections.remove(at))
+                };
+
+                let mut feed = None;
+                match (redirected(&mut redirections, 0), input) {
+                    (Some(Redirection::Stdin(file)), _) => {
+                        command.stdin(file.stdio().unwrap_or_else(|_| Stdio::null()));
+                    }
+                    (_, Some(Link::Pipe(reader))) => {
+                        command.stdin(reader);
+                    }
+                    (_, Some(Link::Channel(reader))) => {
+                        command.stdin(Stdio::piped());
+                        feed = Some(Input::Channel(reader));
+                    }
+                    (_, None) => {
+                        command.stdin(io.stdin.stdio().unwrap_or_else(|_| Stdio::null()));
+                        if matches!(io.stdin, Input::Buffer(_) | Input::Channel(_)) {
+                            // read by the program from now on
+                            feed = Some(mem::replace(&mut io.stdin, Input::Buffer(Cursor::default())));
+                        }
+                    }
+                }
+
+                let mut drain = None;
+                match (redirected(&mut redirections, 1), next) {
+                    (Some(Redirection::Stdout(file)), _) => {
+                        command.stdout(file.stdio().unwrap_or_else(|_| Stdio::null()));
+                    }
+                    (_, Some(Ok(writer))) => {
+                        command.stdout(writer);
+                    }
+                    (_, Some(Err(writer))) => {
+                        command.stdout(Stdio::piped());
+                        drain = Some(writer);
+                    }
+                    (_, None) => {
+                        command.stdout(io.stdout.stdio().unwrap_or_else(|_| Stdio::null()));
+                    }
+                }
+                let stderr = match redirected(&mut redirections, 2) {
+                    Some(Redirection::Stderr(file)) => file.stdio(),
+                    _ => io.stderr.stdio(),
+                };
+                command.stderr(stderr.unwrap_or_else(|_| Stdio::null()));
+
+                let mut child = match program.spawn(command, &foreground) {
+                    Ok(child) => child,
+                    Err(e) => {
+                        statuses[i] = failed(io, e);
+                        continue;
+                    }
+                };
+                if let (Some(mut input), Some(mut stdin)) = (feed, child.stdin.take()) {
+                    // the program may exit without reading it all
+                    pumps.push(thread::spawn(move || drop(io::copy(&mut input, &mut stdin))));
+                }
+                if let Some(stdout) = child.stdout.take() {
+                    match drain {
+                        Some(writer) => pumps.push(thread::spawn(move || pass_on(stdout, writer))),
+                        None => output = Some(stdout),
+                    }
+                }
+                if let Some(mut stderr) = child.stderr.take() {
+                    errors.push(thread::spawn(move || {
+                        let mut bytes = vec![];
+                        let _ = io::Read::read_to_end(&mut stderr, &mut bytes);
+                        bytes
+                    }));
+                }
+                children.push((i, child));
+            }
+            Stage::Shell(stmts) => {
+                let mut redirections = vec![];
+                if let Some(Link::Channel(reader)) = input {
+                    redirections.push(Redirection::Stdin(Input::Channel(reader)));
+                }
+                if let Some(Err(writer)) = next {
+                    redirections.push(Redirection::Stdout(Sink::Channel(writer)));
+                }
+                shell.push((i, stmts, redirections));
+            }
+            Stage::Failed => statuses[i] = ExitStatus::FAILURE,
+        }
+    }
+
+    for (i, stmts, mut redirections) in shell {
+        for redirection in &mut redirections {
+            swap(io, redirection);
+        }
+        for ast in stmts {
+            statement(state, render, ast, io);
+        }
+        for redirection in redirections.iter_mut().rev() {
+            swap(io, redirection);
+        }
+        statuses[i] = state.last_status();
+        // the next command sees the end of what this one wrote
+        drop(redirections);
+    }
+
+    if let Some(mut stdout) = output {
+        let _ = io::copy(&mut stdout, &mut io.stdout);
+    }
+    for (i, mut child) in children {
+        statuses[i] = foreground.wait(&mut child).map_or(ExitStatus::FAILURE, Into::into);
+    }
+    for pump in pumps {
+        let _ = pump.join();
+    }
+    for errors in errors {
+        let _ = io.stderr.write_all(&errors.join().unwrap_or_default());
+    }
+
+    drop(foreground);
+    if raw {
+        enable_raw_mode().unwrap();
+    }
+    state.set_status(statuses);
+}
+
+/// How the command `name` runs in a pipeline.
+fn stage(state: &mut ShellState, name: Token, args: Vec<Expression>, io: &mut Io) -> Stage {
+    let stmts = expand_alias(state, name, args);
+    let program = match &stmts[..] {
+        [AST::Command { name, args }] => program(state, &name.ty.to_string()).map(|program| (program, args)),
+        _ => None,
+    };
+    let Some((program, args)) = program else {
+        return Stage::Shell(stmts);
+    };
+    match arguments(state, args, io) {
+        Some((args, redirections)) => Stage::Program(program, args, redirections),
+        None => Stage::Failed,
+    }
+}
+
+/// The program `name` runs, unless it is a function or a builtin.
+fn program(state: &ShellState, name: &str) -> Option<EnvCommand> {
+    if state.functions.contains_key(name) {
+        return None;
+    }
+    // one that cannot run is reported when the shell runs it
+    if name.contains('/') {
+        return state.program_at(name).ok();
+    }
+    let command = state.command(name)?;
+    command.get_path().map(|path| EnvCommand::new(name.into(), path.into()))
+}
+
+/// Copy what a program prints to the command after it, until that one
+/// stops reading.
+fn pass_on(mut stdout: ChildStdout, mut writer: ChannelWriter) {
+    let _ = io::copy(&mut stdout, &mut writer);
+}
@@ -1,78 +1,58 @@
 use x_checker::Checker;
+use x_highlighter::{highlight, paint};
 use x_input::Input;
 use x_parser::{Lexer, Parser};
-use x_protocol::{
-    ast::AST,
-    crossterm::style::{StyledContent, Stylize},
-    crossterm::Result,
-    ShellErr, ShellState,
-};
-use x_render::Render;
+use x_protocol::{crossterm::Result, io::Io, theme::Role, ShellErr, ShellState};
+use x_render::{report, Render};
 use crate::execute::execute;
 
 // read eval print loop
 pub fn repl(render: &mut Render, input: &mut Input, shell_state: &mut ShellState) -> Result<()> {
     use x_protocol::InputState::*;
     let raw_input = input.user_input.clone();
-    let lexer = Lexer::new(raw_input.chars());
+    let tree = Parser::new(Lexer::new(raw_input.chars())).parse_tree();
     let checker = Checker::new(shell_state);
-    let mut parser = Parser::new(lexer);
-    let mut output: Vec<StyledContent<String>> = vec![];
-    let mut asts: Vec<AST> = vec![];
-
-    let is_error = loop {
-        match parser.parse() {
-            Ok(ast) => {
-                output.append(&mut parser.output.clone());
-
-                if let Some(ast) = ast {
-                    if let Err(e) = checker.check(&ast) {
-                        error_header(e, &raw_input, &mut output, &mut parser);
-                    };
-                    asts.push(ast);
-                } else {
-                    break false;
-                }
-            }
-            Err(e) => {
-                output.append(&mut parser.output.clone());
-                error_header(e.clone(), &raw_input, &mut output, &mut parser);
-                break true;
-            }
+    let mut diagnostics = tree.errors.clone();
+    let mut highlights = vec![];
+    for stmt in &tree.stmts {
+        if let Err(e) = checker.check(stmt) {
+            diagnostics.push(e);
         }
-    };
-    let output_str = format!(
-        "{} ",
-        output
-            .iter()
-            .map(|s| { s.to_string() })
-            .collect::<Vec<_>>()
-            .join("")
-    );
-    let cursor_index = {
-        let mut index = 0;
+        highlights.append(&mut checker.highlights(stmt));
+    }
+    let spans = highlight(&raw_input, &tree, &highlights, &diagnostics, &shell_state.theme);
+    let output_str = paint(&raw_input, &spans);
+    let is_error = !tree.errors.is_empty();
+    let correction = correction(&checker, &raw_input, &diagnostics);
+    input.correction = correction
+        .as_ref()
+        .map(|(range, name, _)| (range.clone(), name.clone()));
+    let asts = tree.stmts;
 
-        input.user_input[input.cursor..input.user_input.len()]
-            .chars()
-            .for_each(|c| {
-                index += if c.is_ascii() { 1 } else { 2 };
-            });
-        index + 1
-    };
+    let hint = shell_state
+        .theme
+        .paint(Role::Suggestion, input.suggestion().unwrap_or_default().to_string());
 
     render.clear_line()?;
-    render.render(output_str, cursor_index)?;
+    render.render(output_str, &raw_input, input.cursor, &hint)?;
     match input.state {
+        Execute if is_error => {
+            // keep editing, with every problem explained under the prompt
+            input.state = NONE;
+            let (mut text, mut plain) = (String::new(), String::new());
+            for diagnostic in &diagnostics {
+                let diagnostic = checker.diagnostic(diagnostic, &raw_input);
+                text.push_str(&report(&diagnostic, &raw_input, "<stdin>", true));
+                plain.push_str(&report(&diagnostic, &raw_input, "<stdin>", false));
+            }
+            render.below(&text, &plain)?;
+        }
         Execute => {
             render.debug(format!("{:?}", asts))?;
-            if is_error {
-                input.state = NONE;
-                repl(render, input, shell_state)?;
-            } else {
-                input.clear();
-                // check ast and run ast
-                execute(shell_state, render, asts);
-            }
+            input.history.push(&raw_input);
+            input.clear();
+            // check ast and run ast
+            execute(shell_state, render, asts, &mut Io::terminal());
             if !shell_state.is_exit {
                 render.new_line(shell_state)?;
             }
@@ -81,43 +61,31 @@ pub fn repl(render: &mut Render, input: &mut Input, shell_state: &mut ShellState
             input.clear();
             render.new_line(shell_state)?;
         }
-        _ => {}
+        _ => {
+            if let Some((_, name, true)) = &correction {
+                let plain = format!("did you mean `{}`? Alt-S to fix", name);
+                let text = shell_state.theme.paint(Role::Suggestion, plain.clone());
+                render.below(&text.to_string(), &plain)?;
+            }
+        }
     }
     Ok(())
 }
 
-fn error_header(
-    e: ShellErr,
-    raw_input: &String,
-    output: &mut Vec<StyledContent<String>>,
-    parser: &mut Parser,
-) {
-    match e {
-        ShellErr::Syntax(range, _) => {
-            output.push(raw_input[range].to_string().red());
-
-            loop {
-                match parser.eat_remaining_token() {
-                    Ok(t) => output.push(t),
-                    Err(e) => {
-                        if let ShellErr::EOF = e {
-                            break;
-                        } else {
-                            error_header(e, raw_input, output, parser)
-                        }
-                    }
-                }
-            }
-        }
-        ShellErr::UnterminatedStr(range) => output.push(
-            format!(
-                "{}{}",
-                raw_input[range.clone()].red(),
-                raw_input[range.end..].green()
-            )
-            .stylize(),
-        ),
-        ShellErr::Unterminated(_, i, _) | ShellErr::UnknownCommand(i, _) => output[i] = output[i].clone().red(),
-        _ => {}
-    }
+/// A fix for the first mistyped command in `raw_input`: the byte range of
+/// the name, the suggested name, and whether the name looks finished, i.e.
+/// something was typed after it.
+fn correction(
+    checker: &Checker,
+    raw_input: &str,
+    diagnostics: &[ShellErr],
+) -> Option<(std::ops::Range<usize>, String, bool)> {
+    let (span, name) = diagnostics.iter().find_map(|diagnostic| match diagnostic {
+        ShellErr::UnknownCommand(span, name) => Some((span, name)),
+        _ => None,
+    })?;
+    let suggestion = checker.suggest(name)?;
+    let byte = |i: usize| raw_input.char_indices().nth(i).map_or(raw_input.len(), |(b, _)| b);
+    let finished = span.end < raw_input.chars().count();
+    Some((byte(span.start)..byte(span.end), suggestion, finished))
 }
@@ -0,0 +1,31 @@
+use std::io::{stderr, IsTerminal, Write};
+
+use x_parser::{Lexer, Parser};
+use x_protocol::diagnostic::Diagnostic;
+use x_protocol::io::{Io, Sink};
+use x_protocol::ShellState;
+use x_render::{report, Render};
+
+use crate::execute::execute;
+
+/// Run `source` without the line editor. When it has syntax errors, all of
+/// them are reported on stderr and nothing runs. Returns the exit status.
+pub fn run_script(state: &mut ShellState, source: &str, origin: &str) -> i32 {
+    run_script_with(state, source, origin, &mut Io::terminal())
+}
+
+/// [`run_script`] with the streams of a command, as `source` runs scripts.
+pub fn run_script_with(state: &mut ShellState, source: &str, origin: &str, io: &mut Io) -> i32 {
+    let tree = Parser::new(Lexer::new(source.chars())).parse_tree();
+    if !tree.errors.is_empty() {
+        let color = matches!(io.stderr, Sink::Stderr) && stderr().is_terminal();
+        for err in &tree.errors {
+            let _ = writeln!(io.stderr, "{}", report(&Diagnostic::from_err(err, source), source, origin, color));
+        }
+        return 2;
+    }
+
+    let mut render = Render::default();
+    execute(state, &mut render, tree.stmts, io);
+    state.status
+}
@@ -0,0 +1,101 @@
+use x_parser::{Lexer, Parser};
+use x_protocol::crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
+use x_protocol::crossterm::Result;
+use x_protocol::io::Io;
+use x_protocol::ShellState;
+use x_render::Render;
+use x_util::take_pending;
+
+use crate::execute::execute;
+
+/// Run the traps of the signals caught since they last ran.
+pub(crate) fn run_traps(state: &mut ShellState, render: &mut Render, io: &mut Io) {
+    for action in pending(state) {
+        run_action(state, render, &action, io);
+    }
+}
+
+/// Run the traps of the signals caught while the prompt waits for keys,
+/// on lines of their own below it. Whether any ran, and the prompt has to
+/// be drawn again.
+pub(crate) fn run_idle_traps(state: &mut ShellState, render: &mut Render, io: &mut Io) -> Result<bool> {
+    let actions = pending(state);
+    if actions.is_empty() {
+        return Ok(false);
+    }
+    let raw = is_raw_mode_enabled().unwrap_or(false);
+    if raw {
+        render.break_line()?;
+        disable_raw_mode()?;
+    }
+    for action in actions {
+        run_action(state, render, &action, io);
+    }
+    if raw {
+        enable_raw_mode()?;
+    }
+    Ok(true)
+}
+
+/// The commands of the traps set for the signals caught since they last
+/// ran.
+fn pending(state: &ShellState) -> Vec<String> {
+    take_pending()
+        .into_iter()
+        .filter_map(|signal| state.traps.get(&signal).cloned())
+        .collect()
+}
+
+/// Run the `EXIT` trap, if one is set, as the shell exits. It runs once.
+pub fn run_exit_trap(state: &mut ShellState) {
+    if let Some(action) = state.traps.remove(&0) {
+        run_action(state, &mut Render::default(), &action, &mut Io::terminal());
+    }
+}
+
+/// Run the command of a trap, which leaves `$?` as it was.
+fn run_action(state: &mut ShellState, render: &mut Render, action: &str, io: &mut Io) {
+    let tree = Parser::new(Lexer::new(action.chars())).parse_tree();
+    if !tree.errors.is_empty() {
+        return;
+    }
+    let (status, pipestatus) = (state.status, state.pipestatus.clone());
+    execute(state, render, tree.stmts, io);
+    state.status = status;
+    state.pipestatus = pipestatus;
+}
+
+#[cfg(test)]
+mod trap_test {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use x_protocol::io::Io;
+    use x_protocol::ShellState;
+    use x_render::Render;
+    use x_util::{set_disposition, Disposition, SIGNALS};
+
+    use crate::trap::{run_idle_traps, run_traps};
+
+    #[test]
+    fn caught_signal() {
+        let (_, usr1) = SIGNALS.iter().find(|(name, _)| *name == "USR1").unwrap();
+        let mut state = ShellState::new(PathBuf::from("/"), "user".into());
+        state.traps.insert(*usr1, "/bin/echo caught".into());
+        set_disposition(*usr1, Disposition::Catch).unwrap();
+
+        let pid = std::process::id().to_string();
+        assert!(Command::new("kill").args(["-USR1", &pid]).status().unwrap().success());
+        let mut io = Io::capture();
+        run_traps(&mut state, &mut Render::default(), &mut io);
+        assert_eq!(io.stdout.take().string, "caught\n");
+
+        // and as the prompt waits, with nothing run to make them
+        assert!(Command::new("kill").args(["-USR1", &pid]).status().unwrap().success());
+        assert!(run_idle_traps(&mut state, &mut Render::default(), &mut io).unwrap());
+        assert_eq!(io.stdout.take().string, "caught\n");
+        assert!(!run_idle_traps(&mut state, &mut Render::default(), &mut io).unwrap());
+
+        set_disposition(*usr1, Disposition::Default).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use std::ops::Range;
+
+use x_parser::{Lexer, Parser};
+use x_protocol::ast::{Expression, Word, WordPart, AST};
+use x_protocol::{ShellErr, Token, Tokens};
+
+const INDENT: &str = "    ";
+
+/// Rewrite `source` in the canonical style: one statement per line,
+/// blocks indented by four spaces, single spaces between arguments and
+/// around redirections, double quotes where they mean the same as single
+/// ones, and at most one blank line in a row. Comments are kept, on their
+/// own line or after the statement they followed.
+///
+/// Input with syntax errors is not touched; the errors are returned.
+pub fn format(source: &str) -> Result<String, Vec<ShellErr>> {
+    let tree = Parser::new(Lexer::new(source.chars())).parse_tree();
+    if !tree.errors.is_empty() {
+        return Err(tree.errors);
+    }
+
+    let mut formatter = Formatter {
+        chars: source.chars().collect(),
+        comments: tree
+            .tokens
+            .iter()
+            .filter(|token| matches!(token.ty, Tokens::Comment(_)))
+            .collect(),
+        next: 0,
+        out: String::new(),
+        depth: 0,
+        last: None,
+    };
+    formatter.stmts(&tree.stmts, usize::MAX);
+    Ok(formatter.out)
+}
+
+struct Formatter<'a> {
+    chars: Vec<char>,
+    comments: Vec<&'a Token>,
+    /// Index of the first comment not written yet.
+    next: usize,
+    out: String,
+    depth: usize,
+    /// End of what was written last, to keep the blank lines after it.
+    /// `None` at the start of the input or of a block.
+    last: Option<usize>,
+}
+
+impl Formatter<'_> {
+    /// Write `stmts`, then the comments before `end`.
+    fn stmts(&mut self, stmts: &[AST], end: usize) {
+        for stmt in stmts {
+            let span = stmt.span();
+            self.comments_before(span.start);
+            self.line(span.start);
+            match stmt {
+                AST::Function { name, parameters, block } => {
+                    let parameters: Vec<String> =
+                        parameters.variables.iter().map(|v| v.ty.to_string()).collect();
+                    self.out.push_str(&format!(
+                        "def {} [{}] {{",
+                        self.text(&name.span),
+                        parameters.join(" ")
+                    ));
+                    let right = block.right.span.start;
+                    if block.stmts.is_empty() && !self.comment_before(right) {
+                        self.out.push('}');
+                    } else {
+                        self.out.push('\n');
+                        self.depth += 1;
+                        self.last = None;
+                        self.stmts(&block.stmts, right);
+                        self.depth -= 1;
+                        self.out.push_str(&INDENT.repeat(self.depth));
+                        self.out.push('}');
+                    }
+                }
+                AST::Command { name, args } => self.command(name, args),
+                AST::Call { name } => self.out.push_str(&self.text(&name.span)),
+                AST::Pipeline(commands) => {
+                    for (i, command) in commands.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push_str(" | ");
+                        }
+                        if let AST::Command { name, args } = command {
+                            self.command(name, args);
+                        }
+                    }
+                }
+                AST::Error(span) => self.out.push_str(&self.text(span)),
+            }
+            self.trailing_comment(span.end);
+            self.out.push('\n');
+            self.last = Some(span.end);
+        }
+        self.comments_before(end);
+    }
+
+    fn command(&mut self, name: &Token, args: &[Expression]) {
+        self.out.push_str(&self.text(&name.span));
+
+        let (mut end, mut redirect) = (name.span.end, false);
+        for arg in args {
+            let span = arg.span();
+            // words written without space in between stay together, except
+            // around redirections
+            if span.start != end || redirect != is_redirect(arg) {
+                self.out.push(' ');
+            }
+            let text = match arg {
+                Expression::Word(word) => self.word(word),
+                Expression::Symbol(_) => self.text(&span),
+            };
+            self.out.push_str(&text);
+            (end, redirect) = (span.end, is_redirect(arg));
+        }
+    }
+
+    /// `word` as written, with single quotes normalized.
+    fn word(&self, word: &Word) -> String {
+        word.parts
+            .iter()
+            .map(|part| match part {
+                WordPart::SingleQuoted { text, .. } => quote(text).unwrap_or_else(|| self.text(part.span())),
+                _ => self.text(part.span()),
+            })
+            .collect()
+    }
+
+    fn text(&self, span: &Range<usize>) -> String {
+        self.chars[span.start.min(self.chars.len())..span.end.min(self.chars.len())]
+            .iter()
+            .collect()
+    }
+
+    /// Start a line for input at `start`, after a blank line if the input
+    /// had one there.
+    fn line(&mut self, start: usize) {
+        if let Some(last) = self.last {
+            let newlines = self.chars[last..start].iter().filter(|c| **c == '\n').count();
+            if newlines > 1 {
+                self.out.push('\n');
+            }
+        }
+        self.out.push_str(&INDENT.repeat(self.depth));
+    }
+
+    fn comment_before(&self, end: usize) -> bool {
+        self.comments
+            .get(self.next)
+            .is_some_and(|comment| comment.span.start < end)
+    }
+
+    /// Write the comments before `end`, each on its own line.
+    fn comments_before(&mut self, end: usize) {
+        while self.comment_before(end) {
+            let comment = self.comments[self.next];
+            self.next += 1;
+            self.line(comment.span.start);
+            self.out.push_str(comment.ty.source().trim_end());
+            self.out.push('\n');
+            self.last = Some(comment.span.end);
+        }
+    }
+
+    /// Write the comment that follows `end` on the same line, if any.
+    fn trailing_comment(&mut self, end: usize) {
+        let Some(comment) = self.comments.get(self.next) else {
+            return;
+        };
+        if comment.span.start < end
+            || !self.chars[end..comment.span.start]
+                .iter()
+                .all(|c| *c == ';' || (c.is_whitespace() && *c != '\n'))
+        {
+            return;
+        }
+        self.next += 1;
+        self.out.push(' ');
+        self.out.push_str(comment.ty.source().trim_end());
+    }
+}
+
+fn is_redirect(arg: &Expression) -> bool {
+    matches!(arg, Expression::Symbol(_))
+}
+
+/// Single-quoted `text` in double quotes, unless it has characters that
+/// mean something else between double quotes.
+fn quote(text: &str) -> Option<String> {
+    (!text.contains(['"', '$', '`', '\\'])).then(|| format!("\"{}\"", text))
+}
+
+#[cfg(test)]
+mod formatter_test {
+    use super::format;
+
+    fn assert_format(source: &str, expected: &str) {
+        let formatted = format(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn statements() {
+        assert_format("ls -la;echo   a.txt $HOME; cd ..\n", "ls -la\necho a.txt $HOME\ncd ..\n");
+        assert_format("echo a'b'\"$c\"  $(ls  'x')", "echo a\"b\"\"$c\" $(ls  'x')\n");
+        assert_format("", "");
+    }
+
+    #[test]
+    fn blocks() {
+        assert_format(
+            "def f [a   b] { echo $a; def g [] {}; g; }\nf 1 2",
+            "def f [a b] {\n    echo $a\n    def g [] {}\n    g\n}\nf 1 2\n",
+        );
+    }
+
+    #[test]
+    fn redirections_and_quotes() {
+        assert_format("echo 'a b'>out.txt", "echo \"a b\" > out.txt\n");
+        assert_format("echo '$a' \"x\" >>  log", "echo '$a' \"x\" >> log\n");
+        assert_format("ls; echo 'a'>x # c", "ls\necho \"a\" > x # c\n");
+    }
+
+    #[test]
+    fn comments() {
+        assert_format(
+            "#!/usr/bin/env xshell\n# list\nls   # all\n\n\n\ndef f [] {\n# body\n  ls\n  # end\n}\n# done",
+            "#!/usr/bin/env xshell\n# list\nls # all\n\ndef f [] {\n    # body\n    ls\n    # end\n}\n# done\n",
+        );
+    }
+
+    #[test]
+    fn pipelines() {
+        assert_format("ls -l|grep  a  |wc", "ls -l | grep a | wc\n");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(format("def f [] { ls").is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use x_checker::Highlight;
+use x_protocol::ast::{Expression, SyntaxTree, WordPart, AST};
+use x_protocol::crossterm::style::{Attribute, ContentStyle};
+use x_protocol::theme::{Role, Theme};
+use x_protocol::{ShellErr, Tokens};
+
+/// A run of input characters drawn with one style. Spans are char offsets,
+/// like the ones the lexer produces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledSpan {
+    pub span: Range<usize>,
+    pub style: ContentStyle,
+}
+
+/// A run of input characters with one role.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleSpan {
+    pub span: Range<usize>,
+    pub role: Role,
+}
+
+/// Per-character roles, filled in passes where later passes win.
+struct Layers {
+    roles: Vec<Option<Role>>,
+    underline: Vec<bool>,
+}
+
+impl Layers {
+    fn new(len: usize) -> Self {
+        Layers {
+            roles: vec![None; len],
+            underline: vec![false; len],
+        }
+    }
+
+    fn set(&mut self, span: &Range<usize>, role: Role) {
+        let end = span.end.min(self.roles.len());
+        for role_at in self.roles.iter_mut().take(end).skip(span.start) {
+            *role_at = Some(role);
+        }
+    }
+
+    fn underline(&mut self, span: &Range<usize>) {
+        let end = span.end.min(self.underline.len());
+        for underline in self.underline.iter_mut().take(end).skip(span.start) {
+            *underline = true;
+        }
+    }
+}
+
+/// Style `source` from its syntax tree: token kinds first, then what the
+/// tree says about them, then the checker's `highlights`, and `diagnostics`
+/// on top.
+pub fn highlight(
+    source: &str,
+    tree: &SyntaxTree,
+    highlights: &[Highlight],
+    diagnostics: &[ShellErr],
+    theme: &Theme,
+) -> Vec<StyledSpan> {
+    let layers = layers(source, tree, highlights, diagnostics);
+
+    let mut spans: Vec<StyledSpan> = vec![];
+    for (i, (role, underline)) in layers.roles.iter().zip(&layers.underline).enumerate() {
+        let mut style = role.map(|role| theme.style(role)).unwrap_or_default();
+        if *underline {
+            style.attributes.set(Attribute::Underlined);
+        }
+        match spans.last_mut() {
+            Some(last) if last.style == style => last.span.end = i + 1,
+            _ => spans.push(StyledSpan { span: i..i + 1, style }),
+        }
+    }
+    spans
+}
+
+/// The roles [`highlight`] styles `source` with, for clients that pick
+/// their own styles. Unstyled input is left out.
+pub fn roles(
+    source: &str,
+    tree: &SyntaxTree,
+    highlights: &[Highlight],
+    diagnostics: &[ShellErr],
+) -> Vec<RoleSpan> {
+    let layers = layers(source, tree, highlights, diagnostics);
+
+    let mut spans: Vec<RoleSpan> = vec![];
+    for (i, role) in layers.roles.iter().enumerate() {
+        let Some(role) = *role else {
+            continue;
+        };
+        match spans.last_mut() {
+            Some(last) if last.role == role && last.span.end == i => last.span.end = i + 1,
+            _ => spans.push(RoleSpan { span: i..i + 1, role }),
+        }
+    }
+    spans
+}
+
+fn layers(source: &str, tree: &SyntaxTree, highlights: &[Highlight], diagnostics: &[ShellErr]) -> Layers {
+    let len = source.chars().count();
+    let mut layers = Layers::new(len);
+
+    for token in &tree.tokens {
+        let role = match token.ty {
+            Tokens::Symbol('$') => Some(Role::Variable),
+            _ => token.ty.role(),
+        };
+        if let Some(role) = role {
+            layers.set(&token.span, role);
+        }
+    }
+
+    for stmt in &tree.stmts {
+        syntax(stmt, &mut layers);
+    }
+
+    for highlight in highlights {
+        layers.set(&highlight.span, highlight.role);
+        if highlight.exists {
+            layers.underline(&highlight.span);
+        }
+    }
+
+    for diagnostic in diagnostics {
+        match diagnostic {
+            ShellErr::Syntax(span, _) | ShellErr::Unterminated(span, _) => layers.set(span, Role::Error),
+            ShellErr::UnterminatedStr(span) => {
+                layers.set(&(span.end..len), Role::String);
+                layers.set(span, Role::Error);
+            }
+            ShellErr::UnknownCommand(span, _) => layers.set(span, Role::UnknownCommand),
+            ShellErr::EOF
+            | ShellErr::NotFound(_)
+            | ShellErr::PermissionDenied(_)
+            | ShellErr::ExecFormat(_)
+            | ShellErr::Spawn(..)
+            | ShellErr::Io(_) => {}
+        }
+    }
+    layers
+}
+
+fn syntax(ast: &AST, layers: &mut Layers) {
+    match ast {
+        AST::Command { name, args } => {
+            if !matches!(name.ty, Tokens::Keyword(_)) {
+                layers.set(&name.span, Role::Command);
+            }
+            for arg in args {
+                if let Expression::Word(word) = arg {
+                    word_parts(&word.parts, layers);
+                }
+            }
+        }
+        AST::Function {
+            name,
+            parameters,
+            block,
+        } => {
+            layers.set(&name.span, Role::Function);
+            for variable in &parameters.variables {
+                layers.set(&variable.span, Role::Variable);
+            }
+            for stmt in &block.stmts {
+                syntax(stmt, layers);
+            }
+        }
+        AST::Call { name } => layers.set(&name.span, Role::Function),
+        AST::Pipeline(commands) => {
+            for command in commands {
+                syntax(command, layers);
+            }
+        }
+        AST::Error(_) => {}
+    }
+}
+
+fn word_parts(parts: &[WordPart], layers: &mut Layers) {
+    for part in parts {
+        match part {
+            WordPart::Literal { .. } => {}
+            WordPart::SingleQuoted { span, .. } => layers.set(span, Role::String),
+            WordPart::DoubleQuoted { parts: inner, span } => {
+                layers.set(span, Role::String);
+                word_parts(inner, layers);
+            }
+            WordPart::Parameter { span, .. } => layers.set(span, Role::Variable),
+            WordPart::CommandSubstitution { stmts, span } => {
+                layers.set(&(span.start..span.start + 2), Role::Operator);
+                layers.set(&(span.end - 1..span.end), Role::Operator);
+                for stmt in stmts {
+                    syntax(stmt, layers);
+                }
+            }
+            WordPart::Arithmetic { span, .. } => layers.set(span, Role::Number),
+            WordPart::Glob { span, .. } => layers.set(span, Role::Operator),
+            WordPart::Tilde { span, .. } => layers.set(span, Role::Path),
+        }
+    }
+}
+
+/// Render `source` with `spans` as a string of terminal escapes.
+pub fn paint(source: &str, spans: &[StyledSpan]) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    spans
+        .iter()
+        .map(|span| {
+            let text: String = chars[span.span.clone()].iter().collect();
+            span.style.apply(text).to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod highlighter_test {
+    use x_parser::{Lexer, Parser};
+    use x_protocol::theme::{Role, Theme};
+    use x_protocol::ShellErr;
+
+    use crate::{highlight, StyledSpan};
+
+    fn spans(source: &str, diagnostics: &[ShellErr]) -> Vec<StyledSpan> {
+        let tree = Parser::new(Lexer::new(source.chars())).parse_tree();
+        let mut diagnostics = diagnostics.to_vec();
+        diagnostics.extend(tree.errors.iter().cloned());
+        highlight(source, &tree, &[], &diagnostics, &Theme::dark())
+    }
+
+    #[test]
+    fn roles() {
+        let theme = Theme::dark();
+        let spans = spans("ls $HOME 'a b'", &[]);
+        let styles: Vec<_> = spans.iter().map(|s| (s.span.clone(), s.style)).collect();
+
+        assert_eq!(styles[0], (0..2, theme.style(Role::Command)));
+        assert_eq!(styles[2], (3..8, theme.style(Role::Variable)));
+        assert_eq!(styles[4], (9..14, theme.style(Role::String)));
+    }
+
+    #[test]
+    fn comments() {
+        let theme = Theme::dark();
+        let spans = spans("#!/bin/xshell\nls # all", &[]);
+        let styles: Vec<_> = spans.iter().map(|s| (s.span.clone(), s.style)).collect();
+
+        assert_eq!(styles[0], (0..13, theme.style(Role::Comment)));
+        assert_eq!(styles.last().unwrap(), &(17..22, theme.style(Role::Comment)));
+    }
+
+    #[test]
+    fn role_spans() {
+        let tree = Parser::new(Lexer::new("ls  'a'".chars())).parse_tree();
+        let roles: Vec<_> = crate::roles("ls  'a'", &tree, &[], &[])
+            .into_iter()
+            .map(|span| (span.span, span.role))
+            .collect();
+        assert_eq!(roles, [(0..2, Role::Command), (4..7, Role::String)]);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let theme = Theme::dark();
+        let spans = spans("nope \"a", &[ShellErr::UnknownCommand(0..4, "nope".into())]);
+
+        assert_eq!(spans[0].style, theme.style(Role::UnknownCommand));
+        assert_eq!((spans[2].span.clone(), spans[2].style), (5..6, theme.style(Role::Error)));
+        assert_eq!(spans[3].style, theme.style(Role::String));
+    }
+
+    #[test]
+    fn wide_chars() {
+        let spans = spans("echo 你好", &[]);
+        assert_eq!(spans.last().unwrap().span, 5..7);
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Entries kept in memory, older ones are dropped.
+const MAX_ENTRIES: usize = 10_000;
+
+/// Previously executed lines, oldest first, optionally backed by a file.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// Load the history file at `path`; new entries are appended to it.
+    pub fn open(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        let mut history = History {
+            entries,
+            path: Some(path),
+        };
+        history.truncate();
+        history
+    }
+
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() || line.contains('\n') || self.entries.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.entries.push(line.to_string());
+        self.truncate();
+
+        if let Some(path) = &self.path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// The rest of the most recent entry that starts with `prefix`.
+    pub fn suggest(&self, prefix: &str) -> Option<&str> {
+        if prefix.is_empty() {
+            return None;
+        }
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.len() > prefix.len() && entry.starts_with(prefix))
+            .map(|entry| &entry[prefix.len()..])
+    }
+
+    fn truncate(&mut self) {
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+    }
+}
+
+#[cfg(test)]
+mod history_test {
+    use super::History;
+
+    #[test]
+    fn suggest_most_recent() {
+        let mut history = History::default();
+        history.push("git status");
+        history.push("git stash");
+        history.push("ls");
+
+        assert_eq!(history.suggest("git st"), Some("ash"));
+        assert_eq!(history.suggest("ls"), None);
+        assert_eq!(history.suggest(""), None);
+    }
+
+    #[test]
+    fn skip_duplicates() {
+        let mut history = History::default();
+        history.push("ls");
+        history.push("ls ");
+        history.push("");
+        assert_eq!(history.entries(), ["ls"]);
+    }
+}
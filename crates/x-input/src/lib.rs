@@ -1,12 +1,22 @@
+mod history;
+
+use std::ops::Range;
+
 use x_protocol::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use x_protocol::state::InputState;
 use x_protocol::ShellState;
 
+pub use history::History;
+
 #[derive(Debug, Clone)]
 pub struct Input {
     pub user_input: String,
     pub cursor: usize,
     pub state: InputState,
+    pub history: History,
+    /// A fix for a mistyped command: the byte range of the name and what
+    /// to replace it with. Applied with Alt-S.
+    pub correction: Option<(Range<usize>, String)>,
 }
 
 fn char_len(c: &char) -> usize {
@@ -22,10 +32,79 @@ impl Input {
         self.state = InputState::NONE;
         match code.modifiers {
             KeyModifiers::CONTROL => self.ctrl(code, state),
+            KeyModifiers::ALT => self.alt(code),
             _ => self.normal_input(code),
         }
     }
 
+    /// The part of the latest matching history entry past the input.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.history.suggest(&self.user_input)
+    }
+
+    fn at_end(&self) -> bool {
+        self.cursor >= self.user_input.len()
+    }
+
+    /// Append the whole suggestion, if any. Only applies at end of line.
+    fn accept_suggestion(&mut self) -> bool {
+        match self.suggestion().map(String::from) {
+            Some(suggestion) if self.at_end() => {
+                self.user_input.push_str(&suggestion);
+                self.cursor = self.user_input.len();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Append the next word of the suggestion, if any.
+    fn accept_suggestion_word(&mut self) -> bool {
+        let Some(suggestion) = self.suggestion().filter(|_| self.at_end()) else {
+            return false;
+        };
+        let start = suggestion.len() - suggestion.trim_start().len();
+        let end = suggestion[start..]
+            .find(char::is_whitespace)
+            .map_or(suggestion.len(), |i| start + i);
+        let word = suggestion[..end].to_string();
+        self.user_input.push_str(&word);
+        self.cursor = self.user_input.len();
+        true
+    }
+
+    fn alt(&mut self, code: &KeyEvent) {
+        match code.code {
+            KeyCode::Char('f') if !self.accept_suggestion_word() => self.forward_word(),
+            KeyCode::Char('s') => self.correct(),
+            _ => {}
+        }
+    }
+
+    /// Replace a mistyped command name with the suggested one.
+    fn correct(&mut self) {
+        let Some((range, name)) = self.correction.take() else {
+            return;
+        };
+        if self.user_input.get(range.clone()).is_none() {
+            return;
+        }
+        if self.cursor >= range.end {
+            self.cursor = self.cursor - range.len() + name.len();
+        } else if self.cursor > range.start {
+            self.cursor = range.start + name.len();
+        }
+        self.user_input.replace_range(range, &name);
+    }
+
+    fn forward_word(&mut self) {
+        let rest = &self.user_input[self.cursor..];
+        let start = rest.len() - rest.trim_start().len();
+        self.cursor += rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |i| start + i);
+    }
+
     pub fn clear(&mut self) {
         self.cursor = 0;
         self.user_input.clear();
@@ -54,11 +133,13 @@ impl Input {
             KeyCode::Up => self.state = InputState::Up,
             KeyCode::Down => self.state = InputState::Down,
             KeyCode::Left => self.left(),
-            KeyCode::Right => self.right(),
+            KeyCode::Right if !self.accept_suggestion() => self.right(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End if !self.accept_suggestion() => self.cursor = self.user_input.len(),
             KeyCode::Enter => self.state = InputState::Execute,
             KeyCode::Backspace => {
                 self.left();
-                if self.user_input.len() != 0 {
+                if !self.user_input.is_empty() {
                     self.user_input.remove(self.cursor);
                 }
             }
@@ -92,6 +173,47 @@ impl Default for Input {
             user_input: String::new(),
             cursor: 0,
             state: InputState::NONE,
+            history: History::default(),
+            correction: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod input_test {
+    use x_protocol::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use x_protocol::ShellState;
+
+    use crate::Input;
+
+    #[test]
+    fn accept_suggestion() {
+        let mut state = ShellState::default();
+        let mut input = Input::default();
+        input.history.push("git commit --amend");
+        for c in "git c".chars() {
+            input.input(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), &mut state);
+        }
+        assert_eq!(input.suggestion(), Some("ommit --amend"));
+
+        input.input(&KeyEvent::new(KeyCode::Char('f'), KeyModifiers::ALT), &mut state);
+        assert_eq!(input.user_input, "git commit");
+        input.input(&KeyEvent::new(KeyCode::End, KeyModifiers::NONE), &mut state);
+        assert_eq!(input.user_input, "git commit --amend");
+        assert_eq!(input.cursor, input.user_input.len());
+    }
+
+    #[test]
+    fn correct() {
+        let mut state = ShellState::default();
+        let mut input = Input::default();
+        for c in "gti status".chars() {
+            input.input(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), &mut state);
         }
+        input.correction = Some((0..3, "git".into()));
+        input.input(&KeyEvent::new(KeyCode::Char('s'), KeyModifiers::ALT), &mut state);
+        assert_eq!(input.user_input, "git status");
+        assert_eq!(input.cursor, input.user_input.len());
+        assert_eq!(input.correction, None);
     }
 }
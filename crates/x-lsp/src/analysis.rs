@@ -0,0 +1,359 @@
+use std::fs;
+use std::ops::Range;
+
+use serde_json::{json, Value};
+use x_checker::Checker;
+use x_highlighter::roles;
+use x_parser::{Lexer, Parser};
+use x_protocol::ast::{Expression, Parameters, SyntaxTree, WordPart, AST};
+use x_protocol::diagnostic::Severity;
+use x_protocol::theme::Role;
+use x_protocol::{ShellState, Token};
+
+use crate::document::Document;
+
+/// Semantic token types, indexed by [`token_type`].
+pub(crate) const TOKEN_TYPES: [&str; 9] = [
+    "function", "macro", "string", "variable", "keyword", "operator", "number", "parameter", "comment",
+];
+
+fn token_type(role: Role) -> Option<usize> {
+    Some(match role {
+        Role::Command | Role::Function | Role::UnknownCommand => 0,
+        Role::Builtin => 1,
+        Role::String | Role::Path => 2,
+        Role::Variable => 3,
+        Role::Keyword => 4,
+        Role::Operator => 5,
+        Role::Number => 6,
+        Role::Argument => 7,
+        Role::Comment => 8,
+        Role::Error | Role::Suggestion => return None,
+    })
+}
+
+// CompletionItemKind values
+const FUNCTION: u8 = 3;
+const KEYWORD: u8 = 14;
+const FILE: u8 = 17;
+const FOLDER: u8 = 19;
+
+fn parse(document: &Document) -> SyntaxTree {
+    Parser::new(Lexer::new(document.text.chars())).parse_tree()
+}
+
+/// What `xshell --check` reports, as LSP diagnostics.
+pub(crate) fn diagnostics(state: &ShellState, uri: &str, document: &Document) -> Value {
+    let tree = parse(document);
+    let diagnostics: Vec<Value> = Checker::new(state)
+        .lint(&tree, &document.text)
+        .into_iter()
+        .map(|diagnostic| {
+            let mut message = diagnostic.message;
+            for note in &diagnostic.notes {
+                message.push_str(&format!("\nnote: {}", note));
+            }
+            for help in &diagnostic.help {
+                message.push_str(&format!("\nhelp: {}", help));
+            }
+            let related: Vec<Value> = diagnostic
+                .secondary
+                .iter()
+                .map(|label| {
+                    json!({
+                        "location": { "uri": uri, "range": document.range(&label.span) },
+                        "message": label.message,
+                    })
+                })
+                .collect();
+            json!({
+                "range": document.range(&diagnostic.primary.span),
+                "severity": match diagnostic.severity {
+                    Severity::Error => 1,
+                    Severity::Warning => 2,
+                },
+                "code": diagnostic.code,
+                "source": "xshell",
+                "message": message,
+                "relatedInformation": related,
+            })
+        })
+        .collect();
+    json!({ "uri": uri, "diagnostics": diagnostics })
+}
+
+/// The highlighter's roles, encoded as relative semantic tokens.
+pub(crate) fn semantic_tokens(state: &ShellState, document: &Document) -> Value {
+    let tree = parse(document);
+    let checker = Checker::new(state);
+    let mut highlights = vec![];
+    for stmt in &tree.stmts {
+        let _ = checker.check(stmt);
+        highlights.append(&mut checker.highlights(stmt));
+    }
+
+    let chars: Vec<char> = document.text.chars().collect();
+    // position of `chars[at]`, moved forward as tokens are encoded
+    let (mut at, mut line, mut col) = (0, 0, 0);
+    let (mut last_line, mut last_col) = (0, 0);
+    let mut data = vec![];
+    for span in roles(&document.text, &tree, &highlights, &[]) {
+        let Some(ty) = token_type(span.role) else {
+            continue;
+        };
+        // tokens may not cross lines
+        let mut pieces = vec![];
+        let mut start = span.span.start;
+        for i in span.span.clone() {
+            if chars[i] == '\n' {
+                pieces.push(start..i);
+                start = i + 1;
+            }
+        }
+        pieces.push(start..span.span.end);
+
+        for piece in pieces.into_iter().filter(|piece| !piece.is_empty()) {
+            while at < piece.start {
+                if chars[at] == '\n' {
+                    (line, col) = (line + 1, 0);
+                } else {
+                    col += chars[at].len_utf16();
+                }
+                at += 1;
+            }
+            let length: usize = chars[piece].iter().map(|c| c.len_utf16()).sum();
+            let delta_col = if line == last_line { col - last_col } else { col };
+            data.extend([line - last_line, delta_col, length, ty, 0]);
+            (last_line, last_col) = (line, col);
+        }
+    }
+    json!({ "data": data })
+}
+
+/// What is under the cursor.
+enum Target<'a> {
+    Command(&'a Token),
+    Definition(&'a Token, &'a Parameters),
+    /// A variable by name, with the parameters of the functions around
+    /// it, innermost last.
+    Variable(String, Vec<&'a Parameters>),
+}
+
+fn contains(span: &Range<usize>, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+fn target<'a>(stmts: &'a [AST], offset: usize, scopes: &mut Vec<&'a Parameters>) -> Option<Target<'a>> {
+    for stmt in stmts {
+        match stmt {
+            AST::Command { name, args } => {
+                if contains(&name.span, offset) {
+                    return Some(Target::Command(name));
+                }
+                for arg in args {
+                    let Expression::Word(word) = arg else {
+                        continue;
+                    };
+                    if !contains(&word.span, offset) {
+                        continue;
+                    }
+                    let mut found = None;
+                    word.walk(&mut |part| match part {
+                        WordPart::Parameter { name, span } if contains(span, offset) => {
+                            found = Some(Target::Variable(name.clone(), scopes.clone()));
+                        }
+                        WordPart::CommandSubstitution { stmts, span } if contains(span, offset) => {
+                            found = target(stmts, offset, &mut scopes.clone());
+                        }
+                        _ => {}
+                    });
+                    return found;
+                }
+            }
+            AST::Function { name, parameters, block } => {
+                if contains(&name.span, offset) {
+                    return Some(Target::Definition(name, parameters));
+                }
+                scopes.push(parameters);
+                if let Some(variable) = parameters.variables.iter().find(|v| contains(&v.span, offset)) {
+                    return Some(Target::Variable(variable.ty.to_string(), scopes.clone()));
+                }
+                if contains(&(block.left.span.start..block.right.span.end), offset) {
+                    return target(&block.stmts, offset, scopes);
+                }
+                scopes.pop();
+            }
+            AST::Pipeline(commands) if contains(&stmt.span(), offset) => {
+                return target(commands, offset, scopes);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The first function named `name` defined in `stmts`, at any depth.
+fn definition_of<'a>(stmts: &'a [AST], name: &str) -> Option<(&'a Token, &'a Parameters)> {
+    stmts.iter().find_map(|stmt| match stmt {
+        AST::Function { name: function, parameters, block } => {
+            if function.ty.to_string() == name {
+                Some((function, parameters))
+            } else {
+                definition_of(&block.stmts, name)
+            }
+        }
+        _ => None,
+    })
+}
+
+fn signature(name: &Token, parameters: &Parameters) -> String {
+    let parameters: Vec<String> = parameters.variables.iter().map(|v| v.ty.to_string()).collect();
+    format!("```xshell\ndef {} [{}]\n```", name.ty, parameters.join(" "))
+}
+
+/// Usage and examples of the command under the cursor.
+pub(crate) fn hover(state: &ShellState, document: &Document, offset: usize) -> Value {
+    let tree = parse(document);
+    let (span, contents) = match target(&tree.stmts, offset, &mut vec![]) {
+        Some(Target::Definition(name, parameters)) => (&name.span, signature(name, parameters)),
+        Some(Target::Command(name)) => {
+            let command = name.ty.to_string();
+            let contents = if let Some((function, parameters)) = definition_of(&tree.stmts, &command) {
+                signature(function, parameters)
+            } else if let Some(found) = state.command(&command) {
+                let mut contents = match found.get_usage() {
+                    "" if found.is_builtin() => format!("`{}`: builtin", command),
+                    "" => format!("`{}`: external command", command),
+                    usage => format!("```\n{}\n```", usage),
+                };
+                let examples = found.get_example();
+                if !examples.is_empty() {
+                    contents.push_str("\n\n**Examples**\n");
+                    for example in examples {
+                        contents.push_str(&format!("\n- `{}`: {}", example.example, example.description));
+                    }
+                }
+                contents
+            } else {
+                return Value::Null;
+            };
+            (&name.span, contents)
+        }
+        _ => return Value::Null,
+    };
+    json!({
+        "contents": { "kind": "markdown", "value": contents },
+        "range": document.range(span),
+    })
+}
+
+/// Where the function or parameter under the cursor is defined.
+pub(crate) fn definition(document: &Document, uri: &str, offset: usize) -> Value {
+    let tree = parse(document);
+    let token = match target(&tree.stmts, offset, &mut vec![]) {
+        Some(Target::Definition(name, _)) => name,
+        Some(Target::Command(name)) => match definition_of(&tree.stmts, &name.ty.to_string()) {
+            Some((function, _)) => function,
+            None => return Value::Null,
+        },
+        Some(Target::Variable(name, scopes)) => {
+            let parameter = scopes
+                .iter()
+                .rev()
+                .find_map(|parameters| parameters.variables.iter().find(|v| v.ty.to_string() == name));
+            match parameter {
+                Some(parameter) => parameter,
+                None => return Value::Null,
+            }
+        }
+        None => return Value::Null,
+    };
+    json!({ "uri": uri, "range": document.range(&token.span) })
+}
+
+/// Command names where a command goes, paths everywhere else.
+pub(crate) fn completion(state: &ShellState, document: &Document, offset: usize) -> Value {
+    let chars: Vec<char> = document.text.chars().collect();
+    let offset = offset.min(chars.len());
+    let start = chars[..offset]
+        .iter()
+        .rposition(|c| c.is_whitespace() || matches!(c, ';' | '{' | '}'))
+        .map_or(0, |i| i + 1);
+    let word: String = chars[start..offset].iter().collect();
+    let before = chars[..start].iter().rev().find(|c| !matches!(c, ' ' | '\t'));
+
+    if matches!(before, None | Some('\n' | ';' | '{' | '}')) && !word.contains('/') {
+        Value::Array(commands(state, document, &word))
+    } else {
+        Value::Array(paths(state, document, offset, &word))
+    }
+}
+
+fn commands(state: &ShellState, document: &Document, prefix: &str) -> Vec<Value> {
+    fn defined(stmts: &[AST], names: &mut Vec<String>) {
+        for stmt in stmts {
+            if let AST::Function { name, block, .. } = stmt {
+                names.push(name.ty.to_string());
+                defined(&block.stmts, names);
+            }
+        }
+    }
+    let mut functions = vec![];
+    defined(&parse(document).stmts, &mut functions);
+    functions.extend(state.functions.keys().cloned());
+
+    let mut items: Vec<(String, u8, &str)> = functions
+        .into_iter()
+        .map(|name| (name, FUNCTION, "function"))
+        .chain(state.aliases.keys().map(|name| (name.clone(), FUNCTION, "alias")))
+        .chain(state.commands.added().map(|command| {
+            if command.is_builtin() {
+                (command.get_name().to_string(), KEYWORD, "builtin")
+            } else {
+                (command.get_name().to_string(), FUNCTION, "external command")
+            }
+        }))
+        // programs from `PATH`, which the stable sort keeps after the above
+        .chain(state.command_names().into_iter().map(|name| (name, FUNCTION, "external command")))
+        .filter(|(name, ..)| name.starts_with(prefix))
+        .collect();
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+    items.dedup_by(|a, b| a.0 == b.0);
+    items
+        .into_iter()
+        .map(|(name, kind, detail)| json!({ "label": name, "kind": kind, "detail": detail }))
+        .collect()
+}
+
+fn paths(state: &ShellState, document: &Document, offset: usize, word: &str) -> Vec<Value> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => (&word[..=i], &word[i + 1..]),
+        None => ("", word),
+    };
+    let Ok(entries) = fs::read_dir(state.resolve_path(dir)) else {
+        return vec![];
+    };
+    let range = document.range(&(offset - prefix.chars().count()..offset));
+
+    let mut items: Vec<(String, bool)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let is_dir = entry.file_type().ok()?.is_dir();
+            Some((name, is_dir))
+        })
+        .filter(|(name, _)| name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.')))
+        .collect();
+    items.sort();
+    items
+        .into_iter()
+        .map(|(name, is_dir)| {
+            let text = if is_dir { format!("{}/", name) } else { name };
+            json!({
+                "label": text,
+                "kind": if is_dir { FOLDER } else { FILE },
+                "textEdit": { "range": range, "newText": text },
+            })
+        })
+        .collect()
+}
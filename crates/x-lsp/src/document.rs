@@ -0,0 +1,76 @@
+use std::ops::Range;
+
+use serde_json::{json, Value};
+
+/// An open script. Positions from the client count lines and UTF-16 code
+/// units; everything else in xshell uses char offsets, so this converts
+/// between the two.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Document {
+    pub(crate) text: String,
+}
+
+impl Document {
+    pub(crate) fn new(text: String) -> Self {
+        Document { text }
+    }
+
+    /// Char offset of an LSP `Position`, clamped to its line.
+    pub(crate) fn offset(&self, position: &Value) -> usize {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+        let mut offset = 0;
+        let mut lines = self.text.split('\n');
+        for text in lines.by_ref().take(line) {
+            offset += text.chars().count() + 1;
+        }
+        let Some(text) = lines.next() else {
+            return self.text.chars().count();
+        };
+        let mut units = 0;
+        for c in text.chars() {
+            if units >= character {
+                break;
+            }
+            units += c.len_utf16();
+            offset += 1;
+        }
+        offset
+    }
+
+    /// LSP `Position` of a char offset.
+    pub(crate) fn position(&self, offset: usize) -> Value {
+        let (mut line, mut character) = (0, 0);
+        for c in self.text.chars().take(offset) {
+            if c == '\n' {
+                line += 1;
+                character = 0;
+            } else {
+                character += c.len_utf16();
+            }
+        }
+        json!({ "line": line, "character": character })
+    }
+
+    pub(crate) fn range(&self, span: &Range<usize>) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+
+    /// Apply one entry of `contentChanges`: a replaced range, or the whole
+    /// text when it has none.
+    pub(crate) fn change(&mut self, change: &Value) {
+        let text = change["text"].as_str().unwrap_or_default();
+        let range = &change["range"];
+        if range.is_null() {
+            self.text = text.into();
+            return;
+        }
+        let (start, end) = (self.offset(&range["start"]), self.offset(&range["end"]));
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut changed: String = chars[..start].iter().collect();
+        changed.push_str(text);
+        changed.extend(&chars[end.max(start)..]);
+        self.text = changed;
+    }
+}
@@ -0,0 +1,322 @@
+mod analysis;
+mod document;
+mod rpc;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+use x_protocol::ShellState;
+
+use analysis::TOKEN_TYPES;
+use document::Document;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Full and incremental `TextDocumentSyncKind`.
+const INCREMENTAL: u8 = 2;
+
+/// Serve the Language Server Protocol for xshell scripts, reading messages
+/// from `input` and writing to `output` until the client sends `exit`.
+/// Returns the exit status the protocol asks for: 0 when `shutdown` came
+/// first, 1 otherwise.
+pub fn serve(state: ShellState, mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server {
+        state,
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+
+    while let Some(body) = rpc::read(&mut input)? {
+        let message: Value = match serde_json::from_str(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                rpc::write(&mut output, &rpc::error(&Value::Null, PARSE_ERROR, &e.to_string()))?;
+                continue;
+            }
+        };
+        // responses from the client, which is never asked anything
+        let Some(method) = message["method"].as_str() else {
+            continue;
+        };
+        if method == "exit" {
+            return Ok(if server.shutdown { 0 } else { 1 });
+        }
+
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => rpc::write(&mut output, &server.request(id, method, params))?,
+            None => {
+                for notification in server.notification(method, params) {
+                    rpc::write(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+    Ok(1)
+}
+
+struct Server {
+    state: ShellState,
+    /// Open scripts by URI.
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl Server {
+    fn request(&mut self, id: &Value, method: &str, params: &Value) -> Value {
+        if self.shutdown {
+            return rpc::error(id, INVALID_REQUEST, "The server is shut down.");
+        }
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self.documents.get(uri);
+        let offset = |document: &Document| document.offset(&params["position"]);
+
+        let result = match (method, document) {
+            ("initialize", _) => capabilities(),
+            ("shutdown", _) => {
+                self.shutdown = true;
+                Value::Null
+            }
+            ("textDocument/hover", Some(document)) => analysis::hover(&self.state, document, offset(document)),
+            ("textDocument/definition", Some(document)) => analysis::definition(document, uri, offset(document)),
+            ("textDocument/completion", Some(document)) => {
+                analysis::completion(&self.state, document, offset(document))
+            }
+            ("textDocument/semanticTokens/full", Some(document)) => {
+                analysis::semantic_tokens(&self.state, document)
+            }
+            (
+                "textDocument/hover"
+                | "textDocument/definition"
+                | "textDocument/completion"
+                | "textDocument/semanticTokens/full",
+                None,
+            ) => Value::Null,
+            _ => return rpc::error(id, METHOD_NOT_FOUND, &format!("Unknown method `{}`.", method)),
+        };
+        rpc::response(id, result)
+    }
+
+    /// Handle a notification, returning the ones to send back.
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), Document::new(text.into()));
+            }
+            "textDocument/didChange" => {
+                let Some(document) = self.documents.get_mut(&uri) else {
+                    return vec![];
+                };
+                for change in params["contentChanges"].as_array().into_iter().flatten() {
+                    document.change(change);
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                let params = json!({ "uri": uri, "diagnostics": [] });
+                return vec![rpc::notification("textDocument/publishDiagnostics", params)];
+            }
+            _ => return vec![],
+        }
+
+        let params = analysis::diagnostics(&self.state, &uri, &self.documents[&uri]);
+        vec![rpc::notification("textDocument/publishDiagnostics", params)]
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": { "openClose": true, "change": INCREMENTAL },
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "completionProvider": { "triggerCharacters": ["/"] },
+            "semanticTokensProvider": {
+                "legend": { "tokenTypes": TOKEN_TYPES, "tokenModifiers": [] },
+                "full": true,
+            },
+        },
+        "serverInfo": { "name": "xshell", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+#[cfg(test)]
+mod lsp_test {
+    use std::path::PathBuf;
+
+    use serde_json::{json, Value};
+    use x_protocol::command::{Command, EnvCommand};
+    use x_protocol::example::Example;
+    use x_protocol::ShellState;
+
+    use crate::{rpc, serve};
+
+    #[derive(Debug, Clone)]
+    struct Echo;
+
+    impl Command for Echo {
+        fn get_name(&self) -> &str {
+            "echo"
+        }
+
+        fn get_usage(&self) -> &str {
+            "echo [STRING]..."
+        }
+
+        fn get_example(&self) -> Vec<Example> {
+            vec![Example {
+                example: "echo hi",
+                description: "print hi",
+            }]
+        }
+
+        fn is_builtin(&self) -> bool {
+            true
+        }
+    }
+
+    const URI: &str = "file:///a.xsh";
+
+    /// Feed `messages` to the server over an in-memory stdio pair and
+    /// collect what it writes back, along with its exit status.
+    fn session(messages: &[Value]) -> (Vec<Value>, i32) {
+        let mut state = ShellState::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")), "user".into());
+        let ls = EnvCommand::new("ls".into(), PathBuf::from("/bin/ls"));
+        state.init_commands(vec![Box::new(ls), Box::new(Echo)]);
+
+        let mut input = vec![];
+        for message in messages {
+            rpc::write(&mut input, message).unwrap();
+        }
+        let mut output = vec![];
+        let status = serve(state, &input[..], &mut output).unwrap();
+
+        let mut replies = vec![];
+        let mut output = &output[..];
+        while let Some(body) = rpc::read(&mut output).unwrap() {
+            replies.push(serde_json::from_str(&body).unwrap());
+        }
+        (replies, status)
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn open(text: &str) -> Value {
+        rpc::notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "languageId": "xshell", "version": 1, "text": text } }),
+        )
+    }
+
+    fn at(id: u64, method: &str, line: u64, character: u64) -> Value {
+        request(
+            id,
+            method,
+            json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } }),
+        )
+    }
+
+    /// The result of the request with `id`.
+    fn result(replies: &[Value], id: u64) -> Value {
+        replies.iter().find(|reply| reply["id"] == id).unwrap()["result"].clone()
+    }
+
+    #[test]
+    fn lifecycle() {
+        let (replies, status) = session(&[
+            request(1, "initialize", json!({ "capabilities": {} })),
+            rpc::notification("initialized", json!({})),
+            request(2, "nope", json!({})),
+            request(3, "shutdown", Value::Null),
+            rpc::notification("exit", Value::Null),
+        ]);
+        assert_eq!(status, 0);
+        assert_eq!(result(&replies, 1)["capabilities"]["textDocumentSync"]["change"], 2);
+        assert_eq!(replies[1]["error"]["code"], -32601);
+        assert_eq!(result(&replies, 3), Value::Null);
+
+        let (_, status) = session(&[rpc::notification("exit", Value::Null)]);
+        assert_eq!(status, 1);
+    }
+
+    #[test]
+    fn incremental_diagnostics() {
+        let change = rpc::notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [
+                    { "range": { "start": { "line": 0, "character": 1 }, "end": { "line": 0, "character": 3 } }, "text": "s" },
+                    { "range": { "start": { "line": 1, "character": 13 }, "end": { "line": 1, "character": 13 } }, "text": "; }" },
+                ],
+            }),
+        );
+        let (replies, _) = session(&[open("lss\ndef f [] { ls"), change]);
+
+        let diagnostics = &replies[0]["params"]["diagnostics"];
+        let codes: Vec<&Value> = diagnostics.as_array().unwrap().iter().map(|d| &d["code"]).collect();
+        assert_eq!(codes, ["XS002", "XS001"]);
+        assert_eq!(diagnostics[0]["range"]["end"], json!({ "line": 0, "character": 3 }));
+        assert_eq!(diagnostics[0]["message"], "Unknown command `lss`.\nhelp: did you mean `ls`?");
+        assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn hover_and_definition() {
+        let (replies, _) = session(&[
+            open("def f [a] { echo $a; }\nf 1"),
+            at(1, "textDocument/hover", 0, 13),
+            at(2, "textDocument/hover", 1, 0),
+            at(3, "textDocument/definition", 1, 1),
+            at(4, "textDocument/definition", 0, 18),
+        ]);
+
+        let usage = result(&replies, 1)["contents"]["value"].clone();
+        assert_eq!(usage, "```\necho [STRING]...\n```\n\n**Examples**\n\n- `echo hi`: print hi");
+        assert_eq!(result(&replies, 2)["contents"]["value"], "```xshell\ndef f [a]\n```");
+        let range = |start: u64, end: u64| {
+            json!({ "start": { "line": 0, "character": start }, "end": { "line": 0, "character": end } })
+        };
+        assert_eq!(result(&replies, 3)["range"], range(4, 5));
+        assert_eq!(result(&replies, 4)["range"], range(7, 8));
+    }
+
+    #[test]
+    fn completion() {
+        let (replies, _) = session(&[
+            open("def e [] { ls; }\nec\nls ./Car"),
+            at(1, "textDocument/completion", 1, 1),
+            at(2, "textDocument/completion", 2, 8),
+        ]);
+
+        let labels = |id| -> Vec<Value> {
+            result(&replies, id)
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["label"].clone())
+                .collect()
+        };
+        assert_eq!(labels(1), ["e", "echo"]);
+        assert_eq!(labels(2), ["Cargo.toml"]);
+        assert_eq!(result(&replies, 2)[0]["textEdit"]["range"]["start"]["character"], 5);
+    }
+
+    #[test]
+    fn semantic_tokens() {
+        let (replies, _) = session(&[
+            open("ls 'a'\n# b"),
+            request(1, "textDocument/semanticTokens/full", json!({ "textDocument": { "uri": URI } })),
+        ]);
+        // function, string, then a comment on the next line
+        assert_eq!(result(&replies, 1)["data"], json!([0, 0, 2, 0, 0, 0, 3, 3, 2, 0, 1, 0, 3, 8, 0]));
+    }
+}
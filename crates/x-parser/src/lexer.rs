@@ -1,5 +1,5 @@
 use std::{
-    iter::{Enumerate, Peekable},
+    iter::{Enumerate, Peekable, Skip, Take},
     ops::Range,
     str::Chars,
 };
@@ -9,10 +9,14 @@ use x_protocol::{Result, ShellErr};
 use x_protocol::{Kwd, Token, Tokens};
 
 pub struct Lexer<'a> {
-    input_stream: Peekable<Enumerate<Chars<'a>>>,
-    end: Range<usize>,
+    /// The whole input, for the parser to read words from.
+    pub(crate) source: Chars<'a>,
+    input_stream: Peekable<Take<Skip<Enumerate<Chars<'a>>>>>,
+    pub(crate) end: Range<usize>,
     is_eof: bool,
     index: usize,
+    /// The next token starts a word, so `#` starts a comment.
+    word_start: bool,
 }
 
 fn token_type(s: String) -> Tokens {
@@ -27,16 +31,26 @@ impl<'a> Lexer<'a> {
     /// # Create a new Lexer.
     /// ## Example
     /// ```
-    /// let mut s = r#"123abc"#;
+    /// use x_parser::Lexer;
+    ///
+    /// let s = r#"123abc"#;
     /// let lexer = Lexer::new(s.chars());
     /// ```
     pub fn new(chars: Chars<'a>) -> Self {
         let end = chars.clone().count();
+        Lexer::range(chars, 0..end)
+    }
+
+    /// Lex the chars of `range` only, with spans still counted from the
+    /// start of `chars`.
+    pub(crate) fn range(chars: Chars<'a>, range: Range<usize>) -> Self {
         Lexer {
-            input_stream: chars.enumerate().peekable(),
-            end: end..end,
+            source: chars.clone(),
+            input_stream: chars.enumerate().skip(range.start).take(range.len()).peekable(),
+            end: range.end..range.end,
             is_eof: false,
             index: 0,
+            word_start: true,
         }
     }
 
@@ -46,6 +60,7 @@ impl<'a> Lexer<'a> {
             let token = match c {
                 '\n' => Token::new(Tokens::NewLine, i..i + 1, self.index),
                 c if c.is_whitespace() => Token::new(Tokens::Space(c), i..i + 1, self.index),
+                '#' if self.word_start => self.comment(i),
                 '"' | '\'' => self.str_lex((i, c), c == '"')?,
                 '|' => self.or(i),
                 '&' => self.and(i),
@@ -58,6 +73,7 @@ impl<'a> Lexer<'a> {
                 _ => self.ident_lex((i, c))?,
             };
             self.index += 1;
+            self.word_start = matches!(token.ty, Tokens::Space(_) | Tokens::NewLine | Tokens::Symbol(';'));
             token
         } else {
             self.is_eof = true;
@@ -65,6 +81,17 @@ impl<'a> Lexer<'a> {
         })
     }
 
+    /// `#` at the start of a word comments out the rest of the line, which
+    /// also makes a `#!` line at the top of a script a comment.
+    fn comment(&mut self, start: usize) -> Token {
+        let mut comment = String::from('#');
+        while let Some((_, c)) = self.input_stream.next_if(|(_, c)| *c != '\n') {
+            comment.push(c);
+        }
+        let end = start + comment.chars().count();
+        Token::new(Tokens::Comment(comment), start..end, self.index)
+    }
+
     fn or(&mut self, start: usize) -> Token {
         if let Some((end, _)) = self.input_stream.next_if(|(_, c)| c.eq(&'|')) {
             Token::new(Tokens::PipeLine, start..end + 1, self.index)
@@ -83,29 +110,26 @@ impl<'a> Lexer<'a> {
 
     fn path(&mut self, (start, c): (usize, char)) -> Result<Token> {
         let mut path = String::from(c);
-        let mut end = start.clone();
+        let mut end = start;
 
-        loop {
-            if let Some((i, c)) = self.input_stream.next_if(|(_, c)| {
-                !c.is_whitespace()
-                    && !c.eq(&'|')
-                    && !c.eq(&'<')
-                    && !c.eq(&'>')
-                    && !c.eq(&':')
-                    && !c.eq(&'"')
-                    && !c.eq(&'?')
-                    && !c.eq(&'*')
-            }) {
-                end = if c.eq(&'\\') {
-                    path.push(self.escape_char()?);
-                    i + 1
-                } else {
-                    i
-                };
-                path.push(c);
+        while let Some((i, c)) = self.input_stream.next_if(|(_, c)| {
+            !c.is_whitespace()
+                && !c.eq(&'|')
+                && !c.eq(&'<')
+                && !c.eq(&'>')
+                && !c.eq(&':')
+                && !c.eq(&'"')
+                && !c.eq(&'?')
+                && !c.eq(&'*')
+                && !matches!(c, ';' | '&' | '\'' | '$' | '`' | '(' | ')')
+        }) {
+            end = if c.eq(&'\\') {
+                path.push(self.escape_char()?);
+                i + 1
             } else {
-                break;
-            }
+                i
+            };
+            path.push(c);
         }
 
         Ok(Token::new(Tokens::Path(path), start..end + 1, self.index))
@@ -118,7 +142,7 @@ impl<'a> Lexer<'a> {
                 'r' => '\r',
                 't' => '\t',
                 '0' => '\0',
-                c @ _ => c,
+                c => c,
             })
         } else {
             Err(ShellErr::EOF)
@@ -171,10 +195,10 @@ impl<'a> Lexer<'a> {
                     }
                     c if c.is_ascii_punctuation() || c.is_whitespace() => break Ok(()),
                     _ => {
-                        let end = self.eat(start.clone(), |c| {
+                        let end = self.eat(start, |c| {
                             !c.is_whitespace() && !c.is_ascii_punctuation()
                         });
-                        break Err(ShellErr::Syntax(start..end + 1, "".into()));
+                        break Err(ShellErr::Syntax(start..end + 1, "Invalid decimal number.".into()));
                     }
                 }
             } else {
@@ -195,10 +219,10 @@ impl<'a> Lexer<'a> {
                     }
                     c if c.is_ascii_punctuation() || c.is_whitespace() => break Ok(()),
                     _ => {
-                        let end = self.eat(start.clone(), |c| {
+                        let end = self.eat(start, |c| {
                             !c.is_whitespace() && !c.is_ascii_punctuation()
                         });
-                        break Err(ShellErr::Syntax(start..end, "".into()));
+                        break Err(ShellErr::Syntax(start..end + 1, "Invalid hex number.".into()));
                     }
                 }
             } else {
@@ -219,10 +243,10 @@ impl<'a> Lexer<'a> {
                     }
                     c if c.is_ascii_punctuation() || c.is_whitespace() => break Ok(()),
                     _ => {
-                        let end = self.eat(start.clone(), |c| {
+                        let end = self.eat(start, |c| {
                             !c.is_whitespace() && !c.is_ascii_punctuation()
                         });
-                        break Err(ShellErr::Syntax(start..end, "".into()));
+                        break Err(ShellErr::Syntax(start..end + 1, "Invalid binary number.".into()));
                     }
                 }
             } else {
@@ -255,12 +279,8 @@ impl<'a> Lexer<'a> {
     {
         let mut end = start;
 
-        loop {
-            if let Some((i, _)) = self.input_stream.next_if(|(_, c)| func(c)) {
-                end = i;
-            } else {
-                break;
-            }
+        while let Some((i, _)) = self.input_stream.next_if(|(_, c)| func(c)) {
+            end = i;
         }
 
         end
@@ -355,6 +375,40 @@ mod test_lexer {
         assert_token(s, &assert_token_arr);
     }
 
+    #[test]
+    fn test_comment() {
+        let s = "ls # a #b\necho a#b";
+        let assert_token_arr = [
+            Ident("ls".into()),
+            Space(' '),
+            Comment("# a #b".into()),
+            NewLine,
+            Ident("echo".into()),
+            Space(' '),
+            Ident("a".into()),
+            Symbol('#'),
+            Ident("b".into()),
+            EOF,
+        ];
+
+        assert_token(s, &assert_token_arr);
+    }
+
+    #[test]
+    fn test_shebang() {
+        let s = "#!/usr/bin/env xshell\nls;#done";
+        let assert_token_arr = [
+            Comment("#!/usr/bin/env xshell".into()),
+            NewLine,
+            Ident("ls".into()),
+            Symbol(';'),
+            Comment("#done".into()),
+            EOF,
+        ];
+
+        assert_token(s, &assert_token_arr);
+    }
+
     fn assert_token(s: &str, arr: &[Tokens]) {
         let mut lexer = Lexer::new(s.chars());
 
@@ -1,48 +1,126 @@
 mod lexer;
 mod syntax;
 
-use std::iter::Enumerate;
-use std::{iter::Peekable, ops::Range};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::vec::IntoIter;
 
 pub use lexer::Lexer;
-use x_protocol::ast::AST;
-use x_protocol::crossterm::style::Stylize;
+use x_protocol::ast::{SyntaxTree, AST};
 use x_protocol::shell_err::Result;
 use x_protocol::ShellErr;
-use x_protocol::{crossterm::style::StyledContent, Kwd, Token, Tokens};
+use x_protocol::{Kwd, Token, Tokens};
 
-pub struct Parser<'a> {
-    lexer: Peekable<Enumerate<Lexer<'a>>>,
-    pub output: Vec<StyledContent<String>>,
+pub struct Parser {
+    /// The whole input, and the chars of the part being parsed, which
+    /// words are read from.
+    text: String,
+    chars: Vec<char>,
+    tokens: Peekable<IntoIter<Result<Token>>>,
+    /// Every token the lexer produced, kept for the syntax tree.
+    source: Vec<Token>,
+    /// Errors recovered from so far.
+    errors: Vec<ShellErr>,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(lexer: Lexer<'a>) -> Self {
+impl Parser {
+    pub fn new(lexer: Lexer) -> Self {
+        let text: String = lexer.source.clone().collect();
+        let end = lexer.end.start;
+        let tokens: Vec<Result<Token>> = lexer.collect();
+        let source = tokens.iter().filter_map(|token| token.clone().ok()).collect();
         Parser {
-            lexer: lexer.enumerate().peekable(),
-            output: vec![],
+            chars: text.chars().take(end).collect(),
+            text,
+            tokens: tokens.into_iter().peekable(),
+            source,
+            errors: vec![],
         }
     }
 
+    /// Parse the whole input. Syntax errors do not stop the parse: each one
+    /// becomes an [`AST::Error`] node and the parser carries on with the next
+    /// statement, so the tree holds every error of the input.
+    pub fn parse_tree(mut self) -> SyntaxTree {
+        let mut stmts = vec![];
+        while let Some(ast) = self.statement() {
+            stmts.push(ast);
+        }
+
+        SyntaxTree {
+            stmts,
+            tokens: self.source,
+            errors: self.errors,
+        }
+    }
+
+    /// Parse one statement, recovering from a syntax error in it.
+    fn statement(&mut self) -> Option<AST> {
+        match self.parse() {
+            Ok(ast) => ast,
+            Err(e) => {
+                let span = e.span();
+                self.errors.push(e);
+                Some(AST::Error(self.synchronize(span)))
+            }
+        }
+    }
+
+    /// Skip to the end of the broken statement: past the next `;` or newline,
+    /// or up to a closing brace that ends the enclosing block. Blocks opened
+    /// inside the statement are skipped whole, and errors of the lexer met
+    /// on the way are recorded. Returns the span of the skipped input.
+    fn synchronize(&mut self, error: Option<Range<usize>>) -> Range<usize> {
+        let mut span = error.unwrap_or_else(|| match self.tokens.peek() {
+            Some(Ok(token)) => token.span.start..token.span.start,
+            _ => self.source.last().map(|t| t.span.end..t.span.end).unwrap_or_default(),
+        });
+        let mut depth = 0;
+        while let Some(token) = self.tokens.next_if(|t| match t {
+            Ok(Token { ty: Tokens::Symbol('}'), .. }) => depth > 0,
+            Ok(Token { ty: Tokens::EOF, .. }) => false,
+            _ => true,
+        }) {
+            let token = match token {
+                Ok(token) => token,
+                Err(e) => {
+                    if let Some(at) = e.span() {
+                        span.end = span.end.max(at.end);
+                    }
+                    self.errors.push(e);
+                    continue;
+                }
+            };
+            span.end = span.end.max(token.span.end);
+            match token.ty {
+                Tokens::Symbol('{') => depth += 1,
+                Tokens::Symbol('}') => depth -= 1,
+                Tokens::Symbol(';') | Tokens::NewLine if depth == 0 => break,
+                _ => {}
+            }
+        }
+        span
+    }
+
     pub fn parse(&mut self) -> Result<Option<AST>> {
-        self.output.clear();
-        Ok(if let Some((_, token)) = self.lexer.next() {
+        self.eat_separators();
+        Ok(if let Some(token) = self.tokens.next() {
             let token = token?;
-            self.output_str(token.ty.default_highlighter());
             let token = match &token.ty {
-                Tokens::Keyword(k) => self.builtin(&k)?,
+                Tokens::Keyword(k) => self.builtin(k)?,
                 Tokens::EOF => return Ok(None),
-                _ => self.command(token)?,
+                Tokens::Symbol('}') => {
+                    return Err(ShellErr::Syntax(token.span, "Unexpected right bracket.".into()))
+                }
+                _ => self.pipeline(token)?,
             };
-            if let Some((_, t)) = self.lexer.next_if(|(_, t)| {
+            self.tokens.next_if(|t| {
                 if let Ok(t) = t {
                     t.eq(Tokens::Symbol(';'))
                 } else {
                     false
                 }
-            }) {
-                self.output_str(t?.ty.default_highlighter());
-            };
+            });
             Some(token)
         } else {
             None
@@ -55,120 +133,65 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn output_str(&mut self, s: StyledContent<String>) {
-        self.output.push(s);
-    }
-
-    fn eat_token_eq<F, E, S>(
-        &mut self,
-        eq_func: F,
-        err: E,
-        custom_style: Option<S>,
-    ) -> Result<(usize, Token)>
+    fn eat_token_eq<F, E>(&mut self, eq_func: F, err: E) -> Result<Token>
     where
         F: FnOnce(&Token) -> bool,
-        E: FnOnce(Range<usize>, usize) -> ShellErr,
-        S: FnOnce(String) -> StyledContent<String>,
+        E: FnOnce(Range<usize>) -> ShellErr,
     {
-        self.eat_whitespace()?;
-        let token = self.lexer.next();
-        if let Some((i, token)) = token {
-            let token = token?;
-            if eq_func(&token) {
-                let output = if let Some(highlighter) = custom_style {
-                    token.ty.highlighter(highlighter)
-                } else {
-                    token.ty.default_highlighter()
-                };
-                self.output_str(output);
-                Ok((i, token))
-            } else {
-                Err(err(token.span, i))
-            }
-        } else {
-            Err(ShellErr::EOF)
+        self.eat_whitespace();
+        match self.tokens.peek() {
+            // leave a wrong token for the next statement to recover with
+            Some(Ok(token)) if !eq_func(token) => Err(err(token.span.clone())),
+            Some(_) => self.tokens.next().unwrap(),
+            None => Err(ShellErr::EOF),
         }
     }
 
-    fn eat_token_eq_default<F>(&mut self, eq_func: F, err_message: &str) -> Result<(usize, Token)>
+    fn eat_token_eq_default<F>(&mut self, eq_func: F, err_message: &str) -> Result<Token>
     where
         F: FnOnce(&Token) -> bool,
     {
-        self.eat_token_eq(
-            eq_func,
-            |span, _| ShellErr::Syntax(span, err_message.into()),
-            None::<Box<dyn FnOnce(String) -> StyledContent<String>>>,
-        )
+        self.eat_token_eq(eq_func, |span| ShellErr::Syntax(span, err_message.into()))
     }
 
-    fn eat_token_eq_custom_err<F, E>(&mut self, eq_func: F, err: E) -> Result<(usize, Token)>
-    where
-        F: FnOnce(&Token) -> bool,
-        E: FnOnce(Range<usize>, usize) -> ShellErr,
-    {
-        self.eat_token_eq(
-            eq_func,
-            err,
-            None::<Box<dyn FnOnce(String) -> StyledContent<String>>>,
-        )
-    }
-
-    fn eat_token_eq_custom_color<F, S>(
-        &mut self,
-        eq_func: F,
-        err_message: &str,
-        custom_style: S,
-    ) -> Result<(usize, Token)>
-    where
-        F: FnOnce(&Token) -> bool,
-        S: FnOnce(String) -> StyledContent<String>,
-    {
-        self.eat_token_eq(
-            eq_func,
-            |span, _| ShellErr::Syntax(span, err_message.into()),
-            Some(custom_style),
-        )
-    }
-
-    fn eat_whitespace(&mut self) -> Result<()> {
-        loop {
-            if let Some((_, t)) = self.lexer.peek() {
-                match t {
-                    Ok(t) => match t.ty {
-                        Tokens::Space(c) => {
-                            self.output.push(c.to_string().stylize());
-                            self.lexer.next()
-                        }
-                        _ => break Ok(()),
-                    },
-                    Err(_) => break Ok(()),
-                };
-            } else {
-                break Ok(());
-            };
-        }
+    /// Skip blank lines and empty statements.
+    fn eat_separators(&mut self) {
+        while self
+            .tokens
+            .next_if(|t| {
+                matches!(
+                    t,
+                    Ok(Token {
+                        ty: Tokens::Space(_) | Tokens::Comment(_) | Tokens::NewLine | Tokens::Symbol(';'),
+                        ..
+                    })
+                )
+            })
+            .is_some()
+        {}
     }
 
-    pub fn eat_remaining_token(&mut self) -> Result<StyledContent<String>> {
-        if let Some((_, token)) = self.lexer.next() {
-            let token = token?;
-            Ok(token.ty.default_highlighter())
-        } else {
-            Err(ShellErr::EOF)
-        }
+    fn eat_whitespace(&mut self) {
+        while self
+            .tokens
+            .next_if(|t| matches!(t, Ok(Token { ty: Tokens::Space(_) | Tokens::Comment(_), .. })))
+            .is_some()
+        {}
     }
 }
 
 #[cfg(test)]
 mod parser_test {
     use crate::{lexer::Lexer, Parser};
+    use x_protocol::ast::{Expression, WordPart, AST};
     use x_util::LevelFilter::Debug;
 
     fn init() {
         x_util::env_logger::builder()
             .is_test(true)
             .filter_level(Debug)
-            .init();
+            .try_init()
+            .ok();
     }
 
     #[test]
@@ -187,19 +210,176 @@ mod parser_test {
         parser(raw_str);
     }
 
+    #[test]
+    fn lossless() {
+        let raw_str = "def f [a] { echo $a; }; f 'x y'";
+        let tree = Parser::new(Lexer::new(raw_str.chars())).parse_tree();
+        assert!(tree.errors.is_empty());
+        assert_eq!(tree.stmts.len(), 2);
+        let source: String = tree.tokens.iter().map(|t| t.ty.source()).collect();
+        assert_eq!(source, raw_str);
+    }
+
+    #[test]
+    fn comments() {
+        let raw_str = "#!/bin/xshell\nls # a b\ndef f [a] { # body\n echo a#b; }";
+        let tree = Parser::new(Lexer::new(raw_str.chars())).parse_tree();
+        assert!(tree.errors.is_empty());
+        match &tree.stmts[..] {
+            [AST::Command { args, .. }, AST::Function { block, .. }] => {
+                assert!(args.is_empty());
+                let AST::Command { args, .. } = &block.stmts[0] else {
+                    panic!("not a command");
+                };
+                // `#` inside a word is literal
+                assert_eq!(args.len(), 1);
+            }
+            stmts => panic!("{:?}", stmts),
+        }
+    }
+
+    /// The parts of the words after the command name, as `kind:text`.
+    fn words(raw_str: &str) -> Vec<Vec<String>> {
+        let tree = Parser::new(Lexer::new(raw_str.chars())).parse_tree();
+        assert!(tree.errors.is_empty(), "{:?}", tree.errors);
+        let Some(AST::Command { args, .. }) = tree.stmts.first() else {
+            panic!("not a command");
+        };
+        let chars: Vec<char> = raw_str.chars().collect();
+        args.iter()
+            .map(|arg| {
+                let Expression::Word(word) = arg else {
+                    return vec![format!("symbol:{}", chars[arg.span()].iter().collect::<String>())];
+                };
+                let mut parts = vec![];
+                word.walk(&mut |part| {
+                    let kind = match part {
+                        WordPart::Literal { .. } => "literal",
+                        WordPart::SingleQuoted { .. } => "single",
+                        WordPart::DoubleQuoted { .. } => "double",
+                        WordPart::Parameter { .. } => "parameter",
+                        WordPart::CommandSubstitution { .. } => "command",
+                        WordPart::Arithmetic { .. } => "arithmetic",
+                        WordPart::Glob { .. } => "glob",
+                        WordPart::Tilde { .. } => "tilde",
+                    };
+                    let text: String = chars[part.span().clone()].iter().collect();
+                    parts.push(format!("{}:{}", kind, text));
+                });
+                parts
+            })
+            .collect()
+    }
+
+    #[test]
+    fn word_parts() {
+        assert_eq!(
+            words("ls foo$bar.txt ~/src/*.[ch] 'a b'\\ c>out"),
+            [
+                vec!["literal:foo", "parameter:$bar", "literal:.txt"],
+                vec!["tilde:~", "literal:/src/", "glob:*", "literal:.", "glob:[ch]"],
+                vec!["single:'a b'", "literal:\\ c"],
+                vec!["symbol:>"],
+                vec!["literal:out"],
+            ]
+        );
+        assert_eq!(
+            words("echo \"x ${y} $(ls \"a b\"; cd ..)\" $((1 + (2)))$?"),
+            [
+                vec![
+                    "double:\"x ${y} $(ls \"a b\"; cd ..)\"",
+                    "literal:x ",
+                    "parameter:${y}",
+                    "literal: ",
+                    "command:$(ls \"a b\"; cd ..)",
+                ],
+                vec!["arithmetic:$((1 + (2)))", "parameter:$?"],
+            ]
+        );
+    }
+
+    #[test]
+    fn substitution() {
+        let raw_str = "echo $(cd ..)x; ls";
+        let tree = Parser::new(Lexer::new(raw_str.chars())).parse_tree();
+        assert!(tree.errors.is_empty(), "{:?}", tree.errors);
+        assert_eq!(tree.stmts.len(), 2);
+        let AST::Command { args, .. } = &tree.stmts[0] else {
+            panic!("not a command");
+        };
+        let Expression::Word(word) = &args[0] else {
+            panic!("not a word");
+        };
+        assert_eq!(word.span, 5..14);
+        match &word.parts[0] {
+            WordPart::CommandSubstitution { stmts, .. } => assert_eq!(stmts[0].span(), 7..12),
+            part => panic!("{:?}", part),
+        }
+        assert!(Parser::new(Lexer::new("echo \"$(ls\"".chars())).parse_tree().errors.len() == 1);
+    }
+
+    #[test]
+    fn recover() {
+        let raw_str = "echo $; ls\ndef [] { ls; }\ndef f [a] { 0x1g; echo }";
+        let tree = Parser::new(Lexer::new(raw_str.chars())).parse_tree();
+        let errors: Vec<_> = tree.errors.iter().map(|e| e.span()).collect();
+        assert_eq!(errors, [Some(6..7), Some(15..16), Some(38..42), Some(36..37)]);
+
+        let kinds: Vec<_> = tree
+            .stmts
+            .iter()
+            .map(|ast| match ast {
+                AST::Command { name, .. } => name.ty.to_string(),
+                AST::Function { .. } => "def".into(),
+                AST::Error(_) => "error".into(),
+                AST::Call { .. } => "call".into(),
+                AST::Pipeline(_) => "pipeline".into(),
+            })
+            .collect();
+        assert_eq!(kinds, ["error", "ls", "error", "error"]);
+    }
+
+    #[test]
+    fn recover_lexer_errors() {
+        // both bad numbers are skipped with the statement, but reported
+        let raw_str = "echo $ 0x1g 0b12; ls";
+        let tree = Parser::new(Lexer::new(raw_str.chars())).parse_tree();
+        let errors: Vec<_> = tree.errors.iter().map(|e| e.span()).collect();
+        assert_eq!(errors, [Some(6..7), Some(7..11), Some(12..16)]);
+        assert!(matches!(&tree.stmts[..], [AST::Error(span), AST::Command { .. }] if *span == (6..17)));
+    }
+
+    #[test]
+    fn pipeline() {
+        let tree = Parser::new(Lexer::new("ls -l | grep a|wc; ls".chars())).parse_tree();
+        assert!(tree.errors.is_empty(), "{:?}", tree.errors);
+        let [AST::Pipeline(commands), AST::Command { .. }] = &tree.stmts[..] else {
+            panic!("{:?}", tree.stmts);
+        };
+        let names: Vec<_> = commands
+            .iter()
+            .map(|ast| match ast {
+                AST::Command { name, .. } => name.ty.to_string(),
+                ast => panic!("{:?}", ast),
+            })
+            .collect();
+        assert_eq!(names, ["ls", "grep", "wc"]);
+        assert_eq!(tree.stmts[0].span(), 0..17);
+
+        let tree = Parser::new(Lexer::new("ls |; ls".chars())).parse_tree();
+        assert_eq!(tree.errors.iter().map(|e| e.span()).collect::<Vec<_>>(), [Some(3..4)]);
+    }
+
+    #[test]
+    fn stray_bracket() {
+        let tree = Parser::new(Lexer::new("ls; } echo".chars())).parse_tree();
+        assert_eq!(tree.errors.len(), 1);
+        assert_eq!(tree.stmts.len(), 2);
+    }
+
     fn parser(s: &str) {
         let lexer = Lexer::new(s.chars());
-        let mut parser = Parser::new(lexer);
-        let ast = parser.parse().unwrap();
-        println!(
-            "{}",
-            parser
-                .output
-                .iter()
-                .map(|s| { s.to_string() })
-                .collect::<Vec<String>>()
-                .join("")
-        );
-        println!("{:?}", ast);
+        let tree = Parser::new(lexer).parse_tree();
+        println!("{:?}", tree);
     }
 }
@@ -3,30 +3,28 @@ use x_protocol::{Result, Tokens};
 
 use crate::Parser;
 
-impl Parser<'_> {
+impl Parser {
     pub fn pase_block(&mut self) -> Result<Block> {
         let mut stmts: Vec<AST> = vec![];
-        let (left_i, left) = self.eat_token_eq_default(
+        let left = self.eat_token_eq_default(
             |token| token.ty.eq(&Tokens::Symbol('{')),
             "Missing left bracket.",
         )?;
 
         let right = loop {
-            self.eat_whitespace()?;
+            self.eat_separators();
             // right bracket
-            if let Some((_, right)) = self.lexer.next_if(|(_, token)| {
+            if let Some(right) = self.tokens.next_if(|token| {
                 let Ok(token) = token else {
                     return false;
                 };
 
                 token.ty.eq(&Tokens::Symbol('}'))
             }) {
-                let right = right?;
-                self.output_str(right.ty.default_highlighter());
-                break right;
+                break right?;
             }
-            let Some(ast) = self.parse()? else {
-                return Err(x_protocol::ShellErr::Unterminated(left.span.clone(), left_i, "Missing right brackets.".into()))
+            let Some(ast) = self.statement() else {
+                return Err(x_protocol::ShellErr::Unterminated(left.span.clone(), "Missing right brackets.".into()))
             };
             stmts.push(ast);
         };
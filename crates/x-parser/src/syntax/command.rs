@@ -1,15 +1,41 @@
 use x_protocol::ast::AST;
 use x_protocol::Tokens;
-use x_protocol::{Result, Token};
+use x_protocol::{Result, ShellErr, Token};
 
 use crate::Parser;
 
-impl Parser<'_> {
+impl Parser {
+    /// Parse a command, or commands joined by `|` into a pipeline.
+    pub fn pipeline(&mut self, name: Token) -> Result<AST> {
+        let mut commands = vec![self.command(name)?];
+        while let Some(pipe) = self.tokens.next_if(|token| matches!(token, Ok(Token { ty: Tokens::Or, .. }))) {
+            let pipe = pipe?;
+            self.eat_whitespace();
+            let name = match self.tokens.peek() {
+                Some(Ok(token))
+                    if matches!(token.ty, Tokens::Ident(_) | Tokens::Str(_) | Tokens::Path(_) | Tokens::Int(_) | Tokens::Arg(_)) =>
+                {
+                    self.tokens.next().unwrap()?
+                }
+                _ => return Err(ShellErr::Syntax(pipe.span, "Expected a command after `|`.".into())),
+            };
+            commands.push(self.command(name)?);
+        }
+        Ok(match commands.len() {
+            1 => commands.pop().unwrap(),
+            _ => AST::Pipeline(commands),
+        })
+    }
+
     pub fn command(&mut self, name: Token) -> Result<AST> {
         let mut args: Vec<x_protocol::ast::Expression> = vec![];
         loop {
-            self.eat_whitespace()?;
-            if let Some((_, semicolon)) = self.lexer.next_if(|(_, token)| {
+            self.eat_whitespace();
+            // a pipe ends the command, and is left for the pipeline
+            if matches!(self.tokens.peek(), Some(Ok(Token { ty: Tokens::Or, .. }))) {
+                break;
+            }
+            if let Some(end) = self.tokens.next_if(|token| {
                 let Ok(token) = token else {
                     return false;
                 };
@@ -17,17 +43,11 @@ impl Parser<'_> {
                     || token.ty.eq(&Tokens::NewLine)
                     || token.ty.eq(&Tokens::EOF)
             }) {
-                self.output_str(semicolon?.ty.default_highlighter());
+                end?;
                 break;
             }
-            args.push(self.expressions()?)
+            args.push(self.expression()?)
         }
         Ok(AST::Command { name, args })
     }
-
-    fn and(&mut self) {}
-
-    fn or(&mut self) {}
-
-    fn pipeline(&mut self) {}
 }
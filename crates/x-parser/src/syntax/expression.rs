@@ -1,41 +1,29 @@
 use x_protocol::ast::Expression;
-use x_protocol::{Result, Tokens};
+use x_protocol::{Result, ShellErr};
 
 use crate::Parser;
 
-impl Parser<'_> {
-    pub(crate) fn expressions(&mut self) -> Result<Expression> {
+impl Parser {
+    /// Parse one argument: a redirection operator or a word.
+    pub(crate) fn expression(&mut self) -> Result<Expression> {
         use x_protocol::Tokens::*;
-        let Some((_, token)) = self.lexer.next() else {
-            return Err(x_protocol::ShellErr::EOF)
-        };
-        let token = token?;
-        self.output.push(token.ty.default_highlighter());
-        Ok(match token.ty {
-            Ident(_) => Expression::Ident(token),
-            Str(_) => Expression::Str(token),
-            Int(_) => Expression::Int(token),
-            Path(_) => Expression::Path(token),
-            Symbol(c) if c.eq(&'$') => {
-                let (_, name) = self.eat_token_eq_default(
-                    |token| {
-                        if let Tokens::Ident(_) = token.ty {
-                            true
-                        } else {
-                            false
-                        }
-                    },
-                    "Variable name error",
-                )?;
-                Expression::Variable(name)
+        let start = match self.tokens.peek() {
+            None => return Err(ShellErr::EOF),
+            Some(Ok(token)) if matches!(token.ty, Symbol('<' | '>')) => {
+                return Ok(Expression::Symbol(self.tokens.next().unwrap()?))
             }
-            Symbol(_) => Expression::Symbol(token),
-            _ => {
-                return Err(x_protocol::ShellErr::Syntax(
-                    token.span,
-                    "Not a expression".into(),
-                ))
+            Some(Ok(token)) if matches!(token.ty, Or | And | PipeLine | Background) => {
+                let token = self.tokens.next().unwrap()?;
+                return Err(ShellErr::Syntax(token.span, "Not a expression".into()));
             }
-        })
+            Some(Ok(token)) => token.span.start,
+            // the word is read again from the source, which reports what is
+            // wrong with it more precisely
+            Some(Err(err)) => match err.span() {
+                Some(span) => span.start,
+                None => return Err(self.tokens.next().unwrap().unwrap_err()),
+            },
+        };
+        Ok(Expression::Word(self.word(start)?))
     }
 }
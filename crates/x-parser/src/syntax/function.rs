@@ -1,23 +1,17 @@
-use x_protocol::ast::{Block, Parameters, AST};
+use x_protocol::ast::{Parameters, AST};
 use x_protocol::Result;
 use x_util::debug;
 
 use crate::Parser;
 use x_protocol::{Token, Tokens};
 
-impl<'a> Parser<'a> {
+impl Parser {
     pub fn function_syntax(&mut self) -> Result<AST> {
-        let (_, name) = self.eat_token_eq_default(
-            |token| {
-                if let Tokens::Ident(_) = token.ty {
-                    true
-                } else {
-                    false
-                }
-            },
+        let name = self.eat_token_eq_default(
+            |token| matches!(token.ty, Tokens::Ident(_)),
             "Missing function name",
         )?;
-        debug!("Parse function name `{}`", name.ty.to_string());
+        debug!("Parse function name `{}`", name.ty);
         let parameters = self.parameters()?;
         let block = self.pase_block()?;
 
@@ -29,7 +23,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parameters(&mut self) -> Result<Parameters> {
-        let (left_i, left) = self.eat_token_eq_default(
+        let left = self.eat_token_eq_default(
             |token| {
                 if let Tokens::Symbol(symbol) = &token.ty {
                     symbol.eq(&'[')
@@ -41,44 +35,40 @@ impl<'a> Parser<'a> {
         )?;
         let mut variables: Vec<Token> = vec![];
         let right = loop {
-            self.eat_whitespace()?;
-            if let Some((_, right)) = self.lexer.next_if(|(_, token)| {
+            self.eat_whitespace();
+            if let Some(right) = self.tokens.next_if(|token| {
                 let Ok(token) = token else {
                     return false;
                 };
                 token.ty.eq(&Tokens::Symbol(']'))
             }) {
-                let right = right?;
-                self.output_str(right.ty.default_highlighter());
-                break right;
+                break right?;
             }
-            let Some((_, token)) = self.lexer.next() else {
+            // leave anything else for the next statement to recover with
+            if let Some(Ok(token)) = self.tokens.peek() {
+                if !matches!(token.ty, Tokens::Ident(_) | Tokens::EOF) {
+                    return Err(x_protocol::ShellErr::Syntax(
+                        token.span.clone(),
+                        "This is not ident.".into(),
+                    ));
+                }
+            }
+            let Some(token) = self.tokens.next() else {
                 return Err(x_protocol::ShellErr::Unterminated(
                         left.span.clone(),
-                        left_i,
                         "Missing right square brackets.".into()
                 ));
             };
 
             let token = token?;
             match token.ty {
-                Tokens::Ident(_) => {
-                    self.output_str(token.ty.default_highlighter());
-                    variables.push(token)
-                }
-                Tokens::EOF => {
+                Tokens::Ident(_) => variables.push(token),
+                _ => {
                     return Err(x_protocol::ShellErr::Unterminated(
                         left.span.clone(),
-                        left_i,
                         "Missing right square brackets.".into(),
                     ));
                 }
-                _ => {
-                    return Err(x_protocol::ShellErr::Syntax(
-                        token.span,
-                        "This is not ident.".into(),
-                    ));
-                }
             }
         };
 
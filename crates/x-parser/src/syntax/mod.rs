@@ -2,8 +2,4 @@ mod block;
 mod command;
 mod expression;
 mod function;
-
-pub use block::*;
-pub use command::*;
-pub use expression::*;
-pub use function::*;
+mod word;
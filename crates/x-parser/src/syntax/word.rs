@@ -0,0 +1,295 @@
+use x_protocol::ast::{Word, WordPart};
+use x_protocol::{Result, ShellErr, Token};
+
+use crate::{Lexer, Parser};
+
+/// Parameters named by one punctuation character, like `$?`.
+const SPECIAL_PARAMETERS: [char; 7] = ['?', '#', '@', '*', '$', '!', '-'];
+
+/// Characters that end a word outside quotes.
+fn is_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '|' | '&' | '<' | '>')
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn literal(c: char, span: std::ops::Range<usize>) -> WordPart {
+    WordPart::Literal {
+        text: c.to_string(),
+        span,
+    }
+}
+
+/// Add `part`, joining it to a literal right before it.
+fn push(parts: &mut Vec<WordPart>, part: WordPart) {
+    if let (
+        Some(WordPart::Literal { text, span }),
+        WordPart::Literal {
+            text: more,
+            span: next,
+        },
+    ) = (parts.last_mut(), &part)
+    {
+        if span.end == next.start {
+            text.push_str(more);
+            span.end = next.end;
+            return;
+        }
+    }
+    parts.push(part);
+}
+
+impl Parser {
+    /// Read the word starting at char `start` from the source, then move the
+    /// token stream past it. Words are read from the source rather than from
+    /// tokens because quotes and `$(...)` nest in ways tokens do not.
+    pub(crate) fn word(&mut self, start: usize) -> Result<Word> {
+        let mut parts = vec![];
+        let mut at = start;
+        while let Some(&c) = self.chars.get(at) {
+            if is_boundary(c) {
+                break;
+            }
+            let part = match c {
+                '\'' => self.single_quoted(at)?,
+                '"' => self.double_quoted(at)?,
+                '$' => self.dollar(at)?.ok_or_else(|| {
+                    let span = if at + 1 < self.chars.len() { at + 1..at + 2 } else { at..at + 1 };
+                    ShellErr::Syntax(span, "Variable name error".into())
+                })?,
+                '~' if at == start => self.tilde(at),
+                '*' | '?' => WordPart::Glob {
+                    pattern: c.into(),
+                    span: at..at + 1,
+                },
+                '[' => self.bracket(at).unwrap_or_else(|| literal(c, at..at + 1)),
+                '\\' => match self.chars.get(at + 1) {
+                    // a line continuation
+                    Some('\n') => WordPart::Literal {
+                        text: String::new(),
+                        span: at..at + 2,
+                    },
+                    Some(&escaped) => literal(escaped, at..at + 2),
+                    None => literal(c, at..at + 1),
+                },
+                c => literal(c, at..at + 1),
+            };
+            at = part.span().end;
+            push(&mut parts, part);
+        }
+
+        self.resync(at);
+        Ok(Word {
+            parts,
+            span: start..at,
+        })
+    }
+
+    fn single_quoted(&self, at: usize) -> Result<WordPart> {
+        let close = self.chars[at + 1..]
+            .iter()
+            .position(|c| *c == '\'')
+            .ok_or(ShellErr::UnterminatedStr(at..at + 1))?
+            + at
+            + 1;
+        Ok(WordPart::SingleQuoted {
+            text: self.chars[at + 1..close].iter().collect(),
+            span: at..close + 1,
+        })
+    }
+
+    fn double_quoted(&mut self, at: usize) -> Result<WordPart> {
+        let mut parts = vec![];
+        let mut i = at + 1;
+        loop {
+            let Some(&c) = self.chars.get(i) else {
+                return Err(ShellErr::UnterminatedStr(at..at + 1));
+            };
+            let part = match c {
+                '"' => break,
+                '$' => self.dollar(i)?.unwrap_or_else(|| literal(c, i..i + 1)),
+                '\\' => match self.chars.get(i + 1) {
+                    Some('\n') => WordPart::Literal {
+                        text: String::new(),
+                        span: i..i + 2,
+                    },
+                    Some(&escaped @ ('$' | '`' | '"' | '\\')) => literal(escaped, i..i + 2),
+                    _ => literal(c, i..i + 1),
+                },
+                c => literal(c, i..i + 1),
+            };
+            i = part.span().end;
+            push(&mut parts, part);
+        }
+        Ok(WordPart::DoubleQuoted { parts, span: at..i + 1 })
+    }
+
+    /// The expansion started by the `$` at `at`, or `None` for a `$` that
+    /// starts none.
+    fn dollar(&mut self, at: usize) -> Result<Option<WordPart>> {
+        let chars = &self.chars;
+        Ok(Some(match chars.get(at + 1).copied() {
+            Some('(') if chars.get(at + 2) == Some(&'(') => {
+                let close = self
+                    .arithmetic_end(at + 3)
+                    .ok_or_else(|| ShellErr::Unterminated(at..at + 3, "Missing right parentheses.".into()))?;
+                WordPart::Arithmetic {
+                    expression: chars[at + 3..close].iter().collect::<String>().trim().into(),
+                    span: at..close + 2,
+                }
+            }
+            Some('(') => {
+                let close = self
+                    .closing_parenthesis(at + 2)
+                    .ok_or_else(|| ShellErr::Unterminated(at..at + 2, "Missing right parenthesis.".into()))?;
+                let tree = Parser::new(Lexer::range(self.text.chars(), at + 2..close)).parse_tree();
+                self.errors.extend(tree.errors);
+                WordPart::CommandSubstitution {
+                    stmts: tree.stmts,
+                    span: at..close + 1,
+                }
+            }
+            Some('{') => {
+                let close = chars[at + 2..]
+                    .iter()
+                    .position(|c| *c == '}')
+                    .ok_or_else(|| ShellErr::Unterminated(at..at + 2, "Missing right brace.".into()))?
+                    + at
+                    + 2;
+                let name: String = chars[at + 2..close].iter().collect();
+                let special = name.len() == 1 && name.starts_with(SPECIAL_PARAMETERS);
+                if !special && (name.is_empty() || !name.chars().all(is_name_char)) {
+                    return Err(ShellErr::Syntax(at..close + 1, "Bad substitution.".into()));
+                }
+                WordPart::Parameter {
+                    name,
+                    span: at..close + 1,
+                }
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let end = (at + 1..chars.len())
+                    .find(|i| !is_name_char(chars[*i]))
+                    .unwrap_or(chars.len());
+                WordPart::Parameter {
+                    name: chars[at + 1..end].iter().collect(),
+                    span: at..end,
+                }
+            }
+            Some(c) if c.is_ascii_digit() || SPECIAL_PARAMETERS.contains(&c) => WordPart::Parameter {
+                name: c.into(),
+                span: at..at + 2,
+            },
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Index of the `)` closing a `$(` whose contents start at `from`.
+    fn closing_parenthesis(&self, from: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut i = from;
+        while let Some(&c) = self.chars.get(i) {
+            match c {
+                '\\' => i += 1,
+                '\'' => i += self.chars[i + 1..].iter().position(|c| *c == '\'')? + 1,
+                '"' => loop {
+                    i += 1;
+                    match self.chars.get(i)? {
+                        '\\' => i += 1,
+                        '"' => break,
+                        _ => {}
+                    }
+                },
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(i),
+                ')' => depth -= 1,
+                _ => {}
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Index of the `))` closing a `$((` whose contents start at `from`.
+    fn arithmetic_end(&self, from: usize) -> Option<usize> {
+        let mut depth = 0;
+        for i in from..self.chars.len() {
+            match self.chars[i] {
+                '(' => depth += 1,
+                ')' if depth == 0 => return (self.chars.get(i + 1) == Some(&')')).then_some(i),
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn tilde(&self, at: usize) -> WordPart {
+        let end = (at + 1..self.chars.len())
+            .find(|i| {
+                let c = self.chars[*i];
+                !(is_name_char(c) || c == '-' || c == '.')
+            })
+            .unwrap_or(self.chars.len());
+        WordPart::Tilde {
+            user: self.chars[at + 1..end].iter().collect(),
+            span: at..end,
+        }
+    }
+
+    /// A bracket expression like `[a-z]` or `[!0-9]`, if it is closed
+    /// within the word.
+    fn bracket(&self, at: usize) -> Option<WordPart> {
+        let mut i = at + 1;
+        if matches!(self.chars.get(i), Some('!' | '^')) {
+            i += 1;
+        }
+        // a `]` right after the opening one is part of the set
+        if self.chars.get(i) == Some(&']') {
+            i += 1;
+        }
+        while let Some(&c) = self.chars.get(i) {
+            if is_boundary(c) {
+                return None;
+            }
+            if c == ']' {
+                return Some(WordPart::Glob {
+                    pattern: self.chars[at..=i].iter().collect(),
+                    span: at..i + 1,
+                });
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Drop the tokens lexed from inside a word ending at `end`. When one
+    /// of them runs past the end, as a token lexed from inside quotes can,
+    /// lex the rest of the input again from there.
+    fn resync(&mut self, end: usize) {
+        let start_of = |token: &Result<Token>| match token {
+            Ok(token) => Some(token.span.start),
+            Err(err) => err.span().map(|span| span.start),
+        };
+        while self
+            .tokens
+            .next_if(|token| start_of(token).is_some_and(|start| start < end))
+            .is_some()
+        {}
+        if self
+            .tokens
+            .peek()
+            .and_then(start_of)
+            .is_none_or(|start| start == end)
+        {
+            return;
+        }
+
+        let tokens: Vec<Result<Token>> = Lexer::range(self.text.chars(), end..self.chars.len()).collect();
+        self.source.retain(|token| token.span.end <= end);
+        self.source
+            .extend(tokens.iter().filter_map(|token| token.clone().ok()));
+        self.tokens = tokens.into_iter().peekable();
+    }
+}
@@ -1,7 +1,18 @@
-use std::fmt::Display;
+use std::ops::Range;
 
-use crate::Token;
+use crate::{ShellErr, Token};
 
+/// The result of parsing a whole input.
+#[derive(Debug, Clone, Default)]
+pub struct SyntaxTree {
+    pub stmts: Vec<AST>,
+    /// Every token of the input in order, whitespace and separators
+    /// included, so nothing of the source is lost.
+    pub tokens: Vec<Token>,
+    pub errors: Vec<ShellErr>,
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum AST {
     Function {
@@ -16,6 +27,29 @@ pub enum AST {
     Call {
         name: Token,
     },
+    /// Commands joined by `|`, each reading what the one before it prints.
+    Pipeline(Vec<AST>),
+    /// Input skipped while recovering from a syntax error.
+    Error(Range<usize>),
+}
+
+impl AST {
+    /// The input the node was parsed from, as char offsets.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            AST::Function { name, block, .. } => name.span.start..block.right.span.end,
+            AST::Command { name, args } => {
+                let end = args.last().map_or(name.span.end, |arg| arg.span().end);
+                name.span.start..end
+            }
+            AST::Call { name } => name.span.clone(),
+            AST::Pipeline(commands) => {
+                let span = |at: Option<&AST>| at.map(AST::span).unwrap_or_default();
+                span(commands.first()).start..span(commands.last()).end
+            }
+            AST::Error(span) => span.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -34,20 +68,103 @@ pub struct Block {
 
 #[derive(Debug, Clone)]
 pub enum Expression {
-    Variable(Token),
-    Ident(Token),
-    Str(Token),
-    Int(Token),
-    Path(Token),
+    /// One argument, like `foo$bar.txt`.
+    Word(Word),
+    /// An operator between arguments, like the `>` of a redirection.
     Symbol(Token),
 }
 
-impl Display for Expression {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use Expression::*;
+impl Expression {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Expression::Word(word) => word.span.clone(),
+            Expression::Symbol(token) => token.span.clone(),
+        }
+    }
+}
+
+/// Everything up to the next blank or operator, like `"$HOME"/*.rs`. The
+/// parts are expanded and joined into one argument, or several when a
+/// glob matches more than one file.
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+    pub span: Range<usize>,
+}
+
+impl Word {
+    /// The text of a word that expands to itself: only literal and quoted
+    /// text, and tildes, which are kept as written.
+    pub fn literal(&self) -> Option<String> {
+        fn literal(parts: &[WordPart], text: &mut String) -> Option<()> {
+            for part in parts {
+                match part {
+                    WordPart::Literal { text: literal, .. } | WordPart::SingleQuoted { text: literal, .. } => {
+                        text.push_str(literal)
+                    }
+                    WordPart::DoubleQuoted { parts, .. } => literal(parts, text)?,
+                    WordPart::Tilde { user, .. } => {
+                        text.push('~');
+                        text.push_str(user);
+                    }
+                    _ => return None,
+                }
+            }
+            Some(())
+        }
+
+        let mut text = String::new();
+        literal(&self.parts, &mut text).map(|_| text)
+    }
+
+    /// Visit every part, the ones inside double quotes included.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a WordPart)) {
+        fn walk<'a>(parts: &'a [WordPart], f: &mut impl FnMut(&'a WordPart)) {
+            for part in parts {
+                f(part);
+                if let WordPart::DoubleQuoted { parts, .. } = part {
+                    walk(parts, f);
+                }
+            }
+        }
+        walk(&self.parts, f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum WordPart {
+    /// Text taken as written, backslash escapes removed.
+    Literal { text: String, span: Range<usize> },
+    /// `'text'`, taken as written.
+    SingleQuoted { text: String, span: Range<usize> },
+    /// `"..."`, where only parameters, command substitutions and
+    /// arithmetic expand.
+    DoubleQuoted { parts: Vec<WordPart>, span: Range<usize> },
+    /// `$name`, `${name}`, or a special parameter like `$?`.
+    Parameter { name: String, span: Range<usize> },
+    /// `$(stmts)`, replaced by what the statements print.
+    CommandSubstitution { stmts: Vec<AST>, span: Range<usize> },
+    /// `$((expression))`, an integer expression.
+    Arithmetic { expression: String, span: Range<usize> },
+    /// `*`, `?` or a bracket expression like `[a-z]`.
+    Glob { pattern: String, span: Range<usize> },
+    /// `~` or `~user` at the start of a word.
+    Tilde { user: String, span: Range<usize> },
+}
+
+impl WordPart {
+    pub fn span(&self) -> &Range<usize> {
+        use WordPart::*;
 
-        write!(f, "{}", match self {
-            Variable(t) | Ident(t) | Str(t) | Int(t) | Path(t) | Symbol(t) => t.ty.to_string()    
-        })
+        match self {
+            Literal { span, .. }
+            | SingleQuoted { span, .. }
+            | DoubleQuoted { span, .. }
+            | Parameter { span, .. }
+            | CommandSubstitution { span, .. }
+            | Arithmetic { span, .. }
+            | Glob { span, .. }
+            | Tilde { span, .. } => span,
+        }
     }
 }
@@ -1,8 +1,9 @@
-use std::process::Stdio;
-use std::{fmt::Debug, path::PathBuf};
+use std::{fmt::{self, Debug}, io, path::{Path, PathBuf}, process::Child, thread};
 
-use crate::{Result, ShellState};
+use crate::io::{Input, Io};
+use crate::{io_message, Result, ShellErr, ShellState};
 use crate::example::Example;
+use x_util::{owns_terminal, Foreground};
 
 pub trait Command: Debug + CommandClone {
     fn get_name(&self) -> &str;
@@ -13,8 +14,13 @@ pub trait Command: Debug + CommandClone {
 
     fn get_usage(&self) -> &str;
 
-    fn run(&self, _: &mut ShellState, _: Vec<String>) -> Result<()> {
-        Ok(())
+    /// Replace the usage, for commands that learn it after they are
+    /// created. Others keep theirs.
+    fn edit_usage(&mut self, _: String) {}
+
+    /// Run the command with `args`, reading and writing through `io`.
+    fn run(&self, _: &mut ShellState, _: Vec<String>, _: &mut Io) -> Result<ExitStatus> {
+        Ok(ExitStatus::SUCCESS)
     }
 
     fn is_sub(&self) -> bool {
@@ -24,6 +30,11 @@ pub trait Command: Debug + CommandClone {
     fn is_builtin(&self) -> bool {
         false
     }
+
+    /// The program the command runs, for commands that are not builtins.
+    fn get_path(&self) -> Option<&Path> {
+        None
+    }
 }
 
 pub trait CommandClone {
@@ -53,18 +64,52 @@ pub struct EnvCommand {
 }
 
 impl Command for EnvCommand {
-    fn run(&self, _: &mut ShellState, args: Vec<String>) -> Result<()> {
-        std::process::Command::new(&self.path).args(&args).status().unwrap();
-        Ok(())
+    fn run(&self, state: &mut ShellState, args: Vec<String>, io: &mut Io) -> Result<ExitStatus> {
+        let mut command = self.command(state, args);
+        command
+            .stdin(io.stdin.stdio()?)
+            .stdout(io.stdout.stdio()?)
+            .stderr(io.stderr.stdio()?);
+        // Ctrl-C and the like go to the program, and to a script running it
+        let terminal = state.interactive && matches!(io.stdin, Input::Terminal) && owns_terminal();
+        let foreground = Foreground::new(terminal);
+        let mut child = self.spawn(command, &foreground)?;
+
+        // feed and drain the pipes at the same time, so that neither side
+        // blocks on a full one, and pass the output on as it comes
+        let Io { stdin, stdout, stderr } = io;
+        thread::scope(|scope| {
+            if let (Input::Buffer(_) | Input::Channel(_), Some(mut pipe)) = (&*stdin, child.stdin.take()) {
+                // the command may exit without reading it all
+                scope.spawn(move || drop(io::copy(stdin, &mut pipe)));
+            }
+            let errors = child.stderr.take().map(|mut pipe| scope.spawn(move || io::copy(&mut pipe, stderr)));
+            if let Some(mut pipe) = child.stdout.take() {
+                io::copy(&mut pipe, stdout)?;
+            }
+            if let Some(errors) = errors {
+                errors.join().unwrap_or(Ok(0))?;
+            }
+            Ok::<_, ShellErr>(())
+        })?;
+        Ok(foreground.wait(&mut child)?.into())
     }
 
     fn get_usage(&self) -> &str {
-        ""
+        &self.usage
+    }
+
+    fn edit_usage(&mut self, usage: String) {
+        self.usage = usage;
     }
 
     fn get_name(&self) -> &str {
         &self.name
     }
+
+    fn get_path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
 }
 
 impl EnvCommand {
@@ -72,7 +117,217 @@ impl EnvCommand {
         EnvCommand { name, path, usage: String::new() }
     }
 
-    pub fn edit_usage(&mut self, usage: String) {
-        self.usage = usage;
+    /// The program with `args`, run in the working directory of `state`.
+    /// Its streams are left for the caller to set.
+    pub fn command(&self, state: &ShellState, args: Vec<String>) -> std::process::Command {
+        let mut command = std::process::Command::new(&self.path);
+        command.args(&args);
+        if let Some(dir) = &state.path {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    /// Start `command`, made by [`EnvCommand::command`], without waiting
+    /// for it.
+    pub fn spawn(&self, mut command: std::process::Command, foreground: &Foreground) -> Result<Child> {
+        foreground.prepare(&mut command);
+        let child = command.spawn().map_err(|e| spawn_error(&self.name, e))?;
+        foreground.spawned(&child);
+        Ok(child)
+    }
+}
+
+/// `ENOEXEC`, which is the same on Linux and the BSDs.
+const ENOEXEC: i32 = 8;
+
+/// Why `name` could not be started.
+fn spawn_error(name: &str, e: io::Error) -> ShellErr {
+    match e.kind() {
+        io::ErrorKind::NotFound => ShellErr::NotFound(name.into()),
+        io::ErrorKind::PermissionDenied => ShellErr::PermissionDenied(name.into()),
+        _ if e.raw_os_error() == Some(ENOEXEC) => ShellErr::ExecFormat(name.into()),
+        _ => ShellErr::Spawn(name.into(), io_message(&e)),
+    }
+}
+
+/// How a command finished: with an exit code, or killed by a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitStatus {
+    /// The code passed to `exit`, `None` when a signal ended the command.
+    pub code: Option<i32>,
+    /// The signal that ended the command.
+    pub signal: Option<i32>,
+    pub core_dumped: bool,
+}
+
+impl ExitStatus {
+    pub const SUCCESS: ExitStatus = ExitStatus::code(0);
+    pub const FAILURE: ExitStatus = ExitStatus::code(1);
+
+    pub const fn code(code: i32) -> Self {
+        ExitStatus {
+            code: Some(code),
+            signal: None,
+            core_dumped: false,
+        }
+    }
+
+    pub fn signaled(signal: i32, core_dumped: bool) -> Self {
+        ExitStatus {
+            code: None,
+            signal: Some(signal),
+            core_dumped,
+        }
+    }
+
+    /// The status as `$?` shows it: the exit code, or 128 plus the signal.
+    pub fn status(&self) -> i32 {
+        match (self.code, self.signal) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => 1,
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.status() == 0
+    }
+}
+
+impl Default for ExitStatus {
+    fn default() -> Self {
+        ExitStatus::SUCCESS
+    }
+}
+
+/// `130`, or `130 SIGINT` when a signal is known by name, with
+/// `(core dumped)` after it when the command left one.
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.status())?;
+        if let Some(name) = self.signal.and_then(signal_name) {
+            write!(f, " {}", name)?;
+        }
+        if self.core_dumped {
+            write!(f, " (core dumped)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl From<std::process::ExitStatus> for ExitStatus {
+    fn from(status: std::process::ExitStatus) -> Self {
+        use std::os::unix::process::ExitStatusExt;
+        match status.signal() {
+            Some(signal) => ExitStatus::signaled(signal, status.core_dumped()),
+            None => ExitStatus::code(status.code().unwrap_or(1)),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl From<std::process::ExitStatus> for ExitStatus {
+    fn from(status: std::process::ExitStatus) -> Self {
+        ExitStatus::code(status.code().unwrap_or(1))
+    }
+}
+
+/// Name of the POSIX signal numbered `signal`, for the ones numbered
+/// the same everywhere.
+pub fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod command_test {
+    use std::path::PathBuf;
+
+    use std::io::Cursor;
+
+    use super::{Command, EnvCommand, ExitStatus};
+    use crate::io::{Input, Io};
+    use crate::{ShellErr, ShellState};
+
+    fn run(path: &str, args: &[&str]) -> crate::Result<ExitStatus> {
+        let command = EnvCommand::new("cmd".into(), PathBuf::from(path));
+        let args = args.iter().map(|arg| arg.to_string()).collect();
+        command.run(&mut ShellState::default(), args, &mut Io::terminal())
+    }
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(run("/bin/sh", &["-c", "exit 3"]).unwrap(), ExitStatus::code(3));
+        let killed = run("/bin/sh", &["-c", "kill -9 $$"]).unwrap();
+        assert_eq!(killed, ExitStatus::signaled(9, false));
+        assert_eq!(killed.to_string(), "137 SIGKILL");
+    }
+
+    #[test]
+    fn captured_streams() {
+        let command = EnvCommand::new("sh".into(), PathBuf::from("/bin/sh"));
+        let mut io = Io::capture();
+        io.stdin = Input::Buffer(Cursor::new(b"hello".to_vec()));
+        let args = vec!["-c".into(), "tr a-z A-Z; echo".into()];
+        command.run(&mut ShellState::default(), args, &mut io).unwrap();
+        assert_eq!(io.stdout.take().string, "HELLO\n");
+    }
+
+    #[test]
+    fn spawn_errors() {
+        assert!(matches!(run("/nonexistent/cmd", &[]), Err(ShellErr::NotFound(name)) if name == "cmd"));
+        let dir = env!("CARGO_MANIFEST_DIR");
+        assert!(matches!(run(dir, &[]), Err(ShellErr::PermissionDenied(_))));
+        let manifest = format!("{}/Cargo.toml", dir);
+        assert!(matches!(run(&manifest, &[]), Err(ShellErr::PermissionDenied(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn relative_program() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("xshell-relative-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("where"), "#!/bin/sh\npwd\n").unwrap();
+        std::fs::set_permissions(dir.join("where"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut state = ShellState::new(dir.clone(), "user".into());
+
+        // found in the shell's directory and run there, wherever the
+        // process itself is
+        let program = state.program_at("./where").unwrap();
+        let mut io = Io::capture();
+        program.run(&mut state, vec![], &mut io).unwrap();
+        let printed = PathBuf::from(io.stdout.take().string.trim_end());
+        assert_eq!(printed.canonicalize().unwrap(), dir.canonicalize().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exec_format_error() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("xshell-enoexec-{}", std::process::id()));
+        std::fs::write(&path, b"\x7fELF not really").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let result = run(path.to_str().unwrap(), &[]);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(ShellErr::ExecFormat(_))));
     }
 }
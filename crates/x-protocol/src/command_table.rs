@@ -0,0 +1,364 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::command::{Command, EnvCommand};
+
+/// Commands by name: the ones added up front, like builtins, and programs
+/// from `PATH`, which are looked up the first time they are wanted and
+/// remembered until `PATH` or the directory they are in changes.
+#[derive(Debug, Default)]
+pub struct CommandTable {
+    commands: HashMap<String, Box<dyn Command>>,
+    /// Programs found so far, by name.
+    hashed: RefCell<HashMap<String, Hashed>>,
+    /// Names no directory of `PATH` had, with when each of them last
+    /// changed as they were searched.
+    missing: RefCell<HashMap<String, Vec<Option<SystemTime>>>>,
+    /// Executables in each `PATH` directory, for listing every name. They
+    /// may be filled in by a scan in the background.
+    listings: Arc<Mutex<HashMap<PathBuf, Listing>>>,
+    /// Whether a scan is still listing directories.
+    scanning: Arc<AtomicBool>,
+    /// The `PATH` the programs were found with.
+    path: RefCell<String>,
+    /// Every command name, once listed with `path`, until `hash -r`.
+    names: RefCell<Option<Vec<String>>>,
+}
+
+#[derive(Debug, Clone)]
+struct Hashed {
+    command: EnvCommand,
+    /// When the directory the program is in last changed, or `None` for
+    /// a path given with `hash -p`, which is kept as it is.
+    mtime: Option<SystemTime>,
+}
+
+#[derive(Debug)]
+struct Listing {
+    mtime: Option<SystemTime>,
+    names: Vec<String>,
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Whether `path` is a file anyone may execute.
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The directories of `PATH`, where an empty entry is the working
+/// directory, which is left out.
+fn dirs(path: &str) -> impl Iterator<Item = &Path> {
+    path.split(':').filter(|dir| !dir.is_empty()).map(Path::new)
+}
+
+impl CommandTable {
+    /// Add `command`, in place of any other with its name.
+    pub fn add(&mut self, command: Box<dyn Command>) {
+        self.names.get_mut().take();
+        self.commands.insert(command.get_name().to_string(), command);
+    }
+
+    /// The commands that were added, builtins among them.
+    pub fn added(&self) -> impl Iterator<Item = &dyn Command> {
+        self.commands.values().map(|command| command.as_ref())
+    }
+
+    /// The command `name` runs, searching `path` for programs.
+    pub fn get(&self, name: &str, path: &str) -> Option<Box<dyn Command>> {
+        match self.commands.get(name) {
+            Some(command) => Some(command.clone()),
+            None => self.program(name, path).map(|program| Box::new(program) as Box<dyn Command>),
+        }
+    }
+
+    /// The program `name` runs, from the table when it is still current or
+    /// the first directory of `path` with an executable of that name. A
+    /// name none of them has is not searched for again until one changes.
+    pub fn program(&self, name: &str, path: &str) -> Option<EnvCommand> {
+        if name.contains('/') {
+            return None;
+        }
+        self.sync(path);
+        let mut hashed = self.hashed.borrow_mut();
+        if let Some(entry) = hashed.get(name) {
+            let dir = entry.command.get_path().and_then(Path::parent);
+            let current = entry.mtime.is_none() || dir.and_then(mtime) == entry.mtime;
+            if current {
+                return Some(entry.command.clone());
+            }
+            hashed.remove(name);
+        }
+
+        // searched again only once a directory changes
+        let mtimes: Vec<Option<SystemTime>> = dirs(path).map(mtime).collect();
+        let mut missing = self.missing.borrow_mut();
+        if missing.get(name) == Some(&mtimes) {
+            return None;
+        }
+        let Some(dir) = dirs(path).find(|dir| is_executable(&dir.join(name))) else {
+            missing.insert(name.into(), mtimes);
+            return None;
+        };
+        missing.remove(name);
+        let command = EnvCommand::new(name.into(), dir.join(name));
+        let entry = Hashed {
+            command: command.clone(),
+            mtime: mtime(dir),
+        };
+        hashed.insert(name.into(), entry);
+        Some(command)
+    }
+
+    /// Forget the programs found with a `PATH` other than `path`.
+    fn sync(&self, path: &str) {
+        let mut current = self.path.borrow_mut();
+        if *current != path {
+            self.hashed.borrow_mut().clear();
+            self.missing.borrow_mut().clear();
+            self.names.borrow_mut().take();
+            *current = path.into();
+        }
+    }
+
+    /// Run `program` for `name` until the table is cleared, as `hash -p`
+    /// does.
+    pub fn remember(&self, name: &str, program: PathBuf) {
+        let entry = Hashed {
+            command: EnvCommand::new(name.into(), program),
+            mtime: None,
+        };
+        self.hashed.borrow_mut().insert(name.into(), entry);
+    }
+
+    /// Forget every program found, as `hash -r` does.
+    pub fn forget(&self) {
+        self.hashed.borrow_mut().clear();
+        self.missing.borrow_mut().clear();
+        self.names.borrow_mut().take();
+    }
+
+    /// The programs found so far, by name, with their paths.
+    pub fn hashed(&self) -> Vec<(String, PathBuf)> {
+        let mut hashed: Vec<(String, PathBuf)> = self
+            .hashed
+            .borrow()
+            .iter()
+            .filter_map(|(name, entry)| Some((name.clone(), entry.command.get_path()?.to_path_buf())))
+            .collect();
+        hashed.sort();
+        hashed
+    }
+
+    /// List the directories of `path` on another thread, so that
+    /// [`names`](Self::names) has them without waiting.
+    pub fn scan(&self, path: &str) {
+        let dirs: Vec<PathBuf> = dirs(path).map(Path::to_path_buf).collect();
+        let listings = Arc::clone(&self.listings);
+        let scanning = Arc::clone(&self.scanning);
+        scanning.store(true, Ordering::SeqCst);
+        thread::spawn(move || {
+            for dir in dirs {
+                let listing = Listing {
+                    mtime: mtime(&dir),
+                    names: list(&dir),
+                };
+                listings.lock().unwrap_or_else(|e| e.into_inner()).insert(dir, listing);
+            }
+            scanning.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Whether a [`scan`](Self::scan) has yet to finish.
+    pub fn scanning(&self) -> bool {
+        self.scanning.load(Ordering::SeqCst)
+    }
+
+    /// Every command name, with the executables of each directory of
+    /// `path`. They are listed once for each `PATH`, and again after
+    /// [`forget`](Self::forget), as `hash -r` does. During a scan, what it
+    /// has listed so far is given, and not kept.
+    pub fn names(&self, path: &str) -> Vec<String> {
+        self.sync(path);
+        if let Some(names) = &*self.names.borrow() {
+            return names.clone();
+        }
+        let names = self.list_names(path);
+        if !self.scanning() {
+            *self.names.borrow_mut() = Some(names.clone());
+        }
+        names
+    }
+
+    /// Every command name, listing the directories of `path` that changed
+    /// since they were last listed.
+    fn list_names(&self, path: &str) -> Vec<String> {
+        let mut names: Vec<String> = self.commands.keys().cloned().collect();
+        let scanning = self.scanning();
+        let mut listings = self.listings.lock().unwrap_or_else(|e| e.into_inner());
+        for dir in dirs(path) {
+            if scanning {
+                names.extend(listings.get(dir).into_iter().flat_map(|listing| listing.names.iter().cloned()));
+                continue;
+            }
+            let mtime = mtime(dir);
+            let listing = listings.entry(dir.to_path_buf()).or_insert(Listing {
+                mtime: None,
+                names: vec![],
+            });
+            if listing.mtime != mtime || mtime.is_none() {
+                *listing = Listing {
+                    mtime,
+                    names: list(dir),
+                };
+            }
+            names.extend(listing.names.iter().cloned());
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Replace the usage of the command `name`, if it is known.
+    pub fn edit_usage(&mut self, name: &str, usage: String) {
+        if let Some(command) = self.commands.get_mut(name) {
+            command.edit_usage(usage);
+        } else if let Some(entry) = self.hashed.get_mut().get_mut(name) {
+            entry.command.edit_usage(usage);
+        }
+    }
+}
+
+/// Names of the executables in `dir`.
+fn list(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod command_table_test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::CommandTable;
+
+    /// A directory with an executable `run` and a plain file `data`.
+    fn bin(name: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("xshell-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("run"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(dir.join("run"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(dir.join("data"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn lookup() {
+        let (a, b) = (bin("table-a"), bin("table-b"));
+        let path = format!("{}:{}", a.display(), b.display());
+        let table = CommandTable::default();
+
+        let run = table.get("run", &path).unwrap();
+        assert_eq!(run.get_path(), Some(a.join("run").as_path()));
+        assert!(table.get("data", &path).is_none());
+        assert_eq!(table.names(&path), ["run"]);
+
+        // a new PATH looks again, and so does a change to the directory
+        let path = b.display().to_string();
+        assert_eq!(table.get("run", &path).unwrap().get_path(), Some(b.join("run").as_path()));
+        fs::remove_file(b.join("run")).unwrap();
+        assert!(table.get("run", &path).is_none());
+
+        table.remember("run", PathBuf::from("/bin/sh"));
+        assert_eq!(table.hashed(), [("run".to_string(), PathBuf::from("/bin/sh"))]);
+        table.forget();
+        assert!(table.hashed().is_empty());
+
+        fs::remove_dir_all(a).unwrap();
+        fs::remove_dir_all(b).unwrap();
+    }
+
+    #[test]
+    fn missing() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = bin("table-missing");
+        let path = dir.display().to_string();
+        let table = CommandTable::default();
+        assert!(table.program("new", &path).is_none());
+        assert_eq!(table.missing.borrow().len(), 1);
+
+        // adding it changes the directory
+        fs::write(dir.join("new"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(dir.join("new"), fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(table.program("new", &path).is_some());
+        assert!(table.missing.borrow().is_empty());
+
+        assert!(table.program("other", &path).is_none());
+        table.forget();
+        assert!(table.missing.borrow().is_empty());
+        assert!(table.program("other", &path).is_none());
+        assert!(table.program("other", "/nonexistent").is_none());
+        assert_eq!(table.missing.borrow().get("other"), Some(&vec![None]));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn names() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = bin("table-names");
+        let path = dir.display().to_string();
+        let table = CommandTable::default();
+        assert_eq!(table.names(&path), ["run"]);
+
+        // kept until `hash -r`
+        fs::write(dir.join("new"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(dir.join("new"), fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(table.names(&path), ["run"]);
+        table.forget();
+        assert_eq!(table.names(&path), ["new", "run"]);
+        assert_eq!(table.names(""), Vec::<String>::new());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn scan() {
+        let dir = bin("table-scan");
+        let path = dir.display().to_string();
+        let table = CommandTable::default();
+
+        table.scan(&path);
+        while table.scanning() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(table.names(&path), ["run"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+use std::{fmt::Display, ops::Range};
+
+use crate::ShellErr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A message attached to a span of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+/// A problem in the input, with everything needed to explain it.
+/// Spans are char offsets, like the ones the lexer produces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the kind of problem, e.g. `XS101`, used to
+    /// silence it.
+    pub code: Option<String>,
+    pub message: String,
+    /// Where the problem is.
+    pub primary: Label,
+    /// Related places, e.g. where an unterminated block was opened.
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    /// Suggested fixes, e.g. "did you mean `ls`?".
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: &str, span: Range<usize>, label: &str) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            primary: Label {
+                span,
+                message: label.into(),
+            },
+            secondary: vec![],
+            notes: vec![],
+            help: vec![],
+        }
+    }
+
+    pub fn error(message: &str, span: Range<usize>, label: &str) -> Self {
+        Diagnostic::new(Severity::Error, message, span, label)
+    }
+
+    pub fn warning(message: &str, span: Range<usize>, label: &str) -> Self {
+        Diagnostic::new(Severity::Warning, message, span, label)
+    }
+
+    pub fn code(mut self, code: &str) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn label(mut self, span: Range<usize>, message: &str) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn note(mut self, note: &str) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn help(mut self, help: &str) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Explain `err`, which was found in `source`. Syntax errors get the
+    /// code `XS001`, unknown commands `XS002`.
+    pub fn from_err(err: &ShellErr, source: &str) -> Self {
+        let end = source.chars().count();
+        let diagnostic = match err {
+            ShellErr::Syntax(span, message) => {
+                let message = if message.is_empty() { "Syntax error." } else { message };
+                Diagnostic::error(message, span.clone(), "")
+            }
+            ShellErr::Unterminated(span, message) => {
+                let opened = match source.chars().nth(span.start) {
+                    Some('[') => "parameter list opened here",
+                    _ => "block opened here",
+                };
+                Diagnostic::error(message, end..end, "input ends here").label(span.clone(), opened)
+            }
+            ShellErr::UnterminatedStr(span) => {
+                let quote = source.chars().nth(span.start).unwrap_or('"');
+                Diagnostic::error("Unterminated string.", span.clone(), "string starts here")
+                    .help(&format!("close it with `{}`", quote))
+            }
+            ShellErr::UnknownCommand(span, name) => {
+                return Diagnostic::error(
+                    &format!("Unknown command `{}`.", name),
+                    span.clone(),
+                    "not a function, builtin or executable in PATH",
+                )
+                .code("XS002")
+            }
+            ShellErr::EOF => Diagnostic::error("Unexpected end of input.", end..end, ""),
+            // errors of running commands, which have no place in the source
+            ShellErr::NotFound(_) | ShellErr::PermissionDenied(_) | ShellErr::ExecFormat(_) | ShellErr::Spawn(..) | ShellErr::Io(_) => {
+                Diagnostic::error(&err.to_string(), end..end, "")
+            }
+        };
+        diagnostic.code("XS001")
+    }
+}
+
+/// Zero-based line and column, in chars, of char `offset` in `source`.
+pub fn position(source: &str, offset: usize) -> (usize, usize) {
+    let mut position = (0, 0);
+    for c in source.chars().take(offset) {
+        position = match c {
+            '\n' => (position.0 + 1, 0),
+            _ => (position.0, position.1 + 1),
+        };
+    }
+    position
+}
+
+#[cfg(test)]
+mod diagnostic_test {
+    use crate::ShellErr;
+
+    use super::{position, Diagnostic};
+
+    #[test]
+    fn unterminated() {
+        let err = ShellErr::Unterminated(9..10, "Missing right brackets.".into());
+        let diagnostic = Diagnostic::from_err(&err, "def f [] { ls;");
+
+        assert_eq!(diagnostic.primary.span, 14..14);
+        assert_eq!(diagnostic.secondary[0].span, 9..10);
+        assert_eq!(diagnostic.secondary[0].message, "block opened here");
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(position("ls\necho 你好", 8), (1, 5));
+        assert_eq!(position("ls", 2), (0, 2));
+    }
+}
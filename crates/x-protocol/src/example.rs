@@ -1,3 +1,4 @@
+#[derive(Debug, Clone)]
 pub struct Example {
     pub example: &'static str,
     pub description: &'static str,
@@ -0,0 +1,40 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// How long a lookup is trusted before the filesystem is asked again.
+const TTL: Duration = Duration::from_secs(2);
+/// Entries kept before the cache starts over.
+const CAPACITY: usize = 1024;
+
+/// Remembers whether paths exist, so highlighting does not stat the same
+/// paths again on every key press.
+#[derive(Debug, Default)]
+pub struct FsCache {
+    entries: RefCell<HashMap<PathBuf, (Instant, bool)>>,
+}
+
+impl FsCache {
+    pub fn exists(&self, path: &Path) -> bool {
+        let mut entries = self.entries.borrow_mut();
+        if let Some((time, exists)) = entries.get(path) {
+            if time.elapsed() < TTL {
+                return *exists;
+            }
+        }
+        if entries.len() >= CAPACITY {
+            entries.clear();
+        }
+        let exists = path.exists();
+        entries.insert(path.to_path_buf(), (Instant::now(), exists));
+        exists
+    }
+
+    /// Forget everything, e.g. after a command may have touched the disk.
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs::File;
+use std::io::{self, Cursor, IsTerminal, Read, Write};
+use std::process::Stdio;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::Output;
+
+/// The standard streams of a command.
+#[derive(Debug)]
+pub struct Io {
+    pub stdin: Input,
+    pub stdout: Sink,
+    pub stderr: Sink,
+}
+
+impl Io {
+    /// The streams of the shell itself.
+    pub fn terminal() -> Self {
+        Io {
+            stdin: Input::Terminal,
+            stdout: Sink::Stdout,
+            stderr: Sink::Stderr,
+        }
+    }
+
+    /// Like [`Io::terminal`], with stdout collected in memory.
+    pub fn capture() -> Self {
+        Io {
+            stdout: Sink::Capture(Output::default()),
+            ..Io::terminal()
+        }
+    }
+}
+
+/// Where a command reads from.
+#[derive(Debug)]
+pub enum Input {
+    Terminal,
+    File(File),
+    /// Bytes already in memory, like the output of an earlier command.
+    Buffer(Cursor<Vec<u8>>),
+    /// What a command running at the same time writes to a [`channel`].
+    Channel(ChannelReader),
+}
+
+impl Input {
+    /// The stream for a child process. A buffer has to be written to the
+    /// pipe this opens.
+    pub fn stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Input::Terminal => Stdio::inherit(),
+            Input::File(file) => file.try_clone()?.into(),
+            Input::Buffer(_) | Input::Channel(_) => Stdio::piped(),
+        })
+    }
+
+    /// Whether the command reads from a terminal.
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Input::Terminal => io::stdin().is_terminal(),
+            Input::File(file) => file.is_terminal(),
+            Input::Buffer(_) | Input::Channel(_) => false,
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::Terminal => io::stdin().read(buf),
+            Input::File(file) => file.read(buf),
+            Input::Buffer(buffer) => buffer.read(buf),
+            Input::Channel(channel) => channel.read(buf),
+        }
+    }
+}
+
+/// Where a command writes to.
+#[derive(Debug)]
+pub enum Sink {
+    Stdout,
+    Stderr,
+    File(File),
+    Capture(Output),
+    Channel(ChannelWriter),
+}
+
+impl Sink {
+    /// The stream for a child process. What it writes to a capture has to
+    /// be read from the pipe this opens.
+    pub fn stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Sink::Stdout => Stdio::inherit(),
+            Sink::Stderr => io::stderr().into(),
+            Sink::File(file) => file.try_clone()?.into(),
+            Sink::Capture(_) | Sink::Channel(_) => Stdio::piped(),
+        })
+    }
+
+    /// Whether the command writes to a terminal.
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Sink::Stdout => io::stdout().is_terminal(),
+            Sink::Stderr => io::stderr().is_terminal(),
+            Sink::File(file) => file.is_terminal(),
+            Sink::Capture(_) | Sink::Channel(_) => false,
+        }
+    }
+
+    /// What was captured, empty for any other sink.
+    pub fn take(&mut self) -> Output {
+        match self {
+            Sink::Capture(output) => std::mem::take(output),
+            _ => Output::default(),
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Stdout => io::stdout().write(buf),
+            Sink::Stderr => io::stderr().write(buf),
+            Sink::File(file) => file.write(buf),
+            Sink::Capture(output) => output.write(buf),
+            Sink::Channel(channel) => channel.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Stdout => io::stdout().flush(),
+            Sink::Stderr => io::stderr().flush(),
+            Sink::File(file) => file.flush(),
+            Sink::Capture(output) => output.flush(),
+            Sink::Channel(channel) => channel.flush(),
+        }
+    }
+}
+
+/// A pipe in memory, between commands of a pipeline that the shell runs
+/// itself. Writes never block, so a builtin can write all it has before
+/// the command after it runs.
+pub fn channel() -> (ChannelWriter, ChannelReader) {
+    let (sender, receiver) = mpsc::channel();
+    let reader = ChannelReader {
+        chunks: receiver,
+        chunk: Cursor::default(),
+    };
+    (ChannelWriter(sender), reader)
+}
+
+#[derive(Debug)]
+pub struct ChannelWriter(Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    /// Fails with `BrokenPipe` once the reader is gone.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.0.send(buf.to_vec()).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads what was written to the [`ChannelWriter`], and the end of it once
+/// the writer is dropped.
+#[derive(Debug)]
+pub struct ChannelReader {
+    chunks: Receiver<Vec<u8>>,
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.position() as usize == self.chunk.get_ref().len() {
+            match self.chunks.recv() {
+                Ok(chunk) => self.chunk = Cursor::new(chunk),
+                Err(_) => return Ok(0),
+            }
+        }
+        self.chunk.read(buf)
+    }
+}
@@ -1,9 +1,15 @@
 pub mod ast;
 pub mod command;
+pub mod command_table;
+pub mod diagnostic;
 pub mod example;
+pub mod fs_cache;
+pub mod io;
 pub mod output;
 pub mod shell_err;
 pub mod state;
+pub mod style;
+pub mod theme;
 pub mod tokens;
 
 pub use crossterm;
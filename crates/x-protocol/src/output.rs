@@ -1,3 +1,6 @@
+use std::io::{self, Write};
+
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Output {
     pub string: String,
 }
@@ -8,10 +11,15 @@ impl Output {
     }
 }
 
-impl Default for Output {
-    fn default() -> Self {
-        Output {
-            string: String::new(),
-        }
+/// Collects what is written, as text. Bytes that are not UTF-8 become
+/// `U+FFFD`, so writers should not split characters across writes.
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.string.push_str(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
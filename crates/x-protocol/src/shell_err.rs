@@ -1,3 +1,5 @@
+use std::fmt;
+use std::io;
 use std::ops::Range;
 
 pub type Result<T> = std::result::Result<T, ShellErr>;
@@ -5,8 +7,68 @@ pub type Result<T> = std::result::Result<T, ShellErr>;
 #[derive(Debug, Clone)]
 pub enum ShellErr {
     Syntax(Range<usize>, String),
-    Unterminated(Range<usize>, usize, String),
+    Unterminated(Range<usize>, String),
     UnterminatedStr(Range<usize>),
-    UnknownCommand(usize, String), 
+    UnknownCommand(Range<usize>, String),
     EOF,
+    /// The program of a command does not exist.
+    NotFound(String),
+    /// The program of a command may not be executed.
+    PermissionDenied(String),
+    /// The program of a command is not in a format the system can run.
+    ExecFormat(String),
+    /// Any other reason a command could not be started, with the system's
+    /// message.
+    Spawn(String, String),
+    /// Reading or writing a stream of a command failed.
+    Io(String),
+}
+
+impl ShellErr {
+    /// The part of the input the error points at.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            ShellErr::Syntax(span, _)
+            | ShellErr::Unterminated(span, _)
+            | ShellErr::UnterminatedStr(span)
+            | ShellErr::UnknownCommand(span, _) => Some(span.clone()),
+            ShellErr::EOF
+            | ShellErr::NotFound(_)
+            | ShellErr::PermissionDenied(_)
+            | ShellErr::ExecFormat(_)
+            | ShellErr::Spawn(..)
+            | ShellErr::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ShellErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShellErr::Syntax(_, message) | ShellErr::Unterminated(_, message) => write!(f, "{}", message),
+            ShellErr::UnterminatedStr(_) => write!(f, "Unterminated string."),
+            ShellErr::UnknownCommand(_, name) => write!(f, "{}: command not found", name),
+            ShellErr::EOF => write!(f, "Unexpected end of input."),
+            ShellErr::NotFound(name) => write!(f, "{}: No such file or directory", name),
+            ShellErr::PermissionDenied(name) => write!(f, "{}: Permission denied", name),
+            ShellErr::ExecFormat(name) => write!(f, "{}: cannot execute: Exec format error", name),
+            ShellErr::Spawn(name, message) => write!(f, "{}: {}", name, message),
+            ShellErr::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for ShellErr {
+    fn from(e: io::Error) -> Self {
+        ShellErr::Io(io_message(&e))
+    }
+}
+
+/// The message of `e` without the ` (os error N)` Rust adds to it.
+pub fn io_message(e: &io::Error) -> String {
+    let message = e.to_string();
+    match message.find(" (os error ") {
+        Some(i) => message[..i].to_string(),
+        None => message,
+    }
 }
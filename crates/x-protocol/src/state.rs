@@ -1,8 +1,12 @@
-use std::{collections::HashMap, path::PathBuf, fs};
+use std::{collections::HashMap, fs, path::PathBuf};
 use x_util::{home_dir, whoami};
 
-use crate::Result;
-use crate::command::{Command, EnvCommand};
+use crate::ast::AST;
+use crate::command::{Command, EnvCommand, ExitStatus};
+use crate::command_table::{is_executable, CommandTable};
+use crate::fs_cache::FsCache;
+use crate::theme::Theme;
+use crate::ShellErr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputState {
@@ -20,8 +24,28 @@ pub struct ShellState {
     pub path: Option<PathBuf>,
     pub login: String,
     pub envs: HashMap<String, String>,
-    pub commands: Vec<Box<dyn Command>>,
+    pub commands: CommandTable,
+    /// Functions declared with `def`, by name.
+    pub functions: HashMap<String, AST>,
     pub variables: HashMap<String, String>,
+    /// Aliases set with `alias`, by name.
+    pub aliases: HashMap<String, String>,
+    /// Commands set with `trap`, by signal number, where 0 is `EXIT`.
+    pub traps: HashMap<i32, String>,
+    /// Exit status of the last command.
+    pub status: i32,
+    /// How each command of the last pipeline finished, for `PIPESTATUS`.
+    pub pipestatus: Vec<ExitStatus>,
+    /// Number of function calls under way.
+    pub depth: usize,
+    /// Number of background jobs.
+    pub jobs: usize,
+    /// Highlighting theme of the line editor.
+    pub theme: Theme,
+    pub fs_cache: FsCache,
+    /// Whether commands are read from the line editor, which gives each
+    /// program the terminal to itself.
+    pub interactive: bool,
     pub is_exit: bool,
 }
 
@@ -32,39 +56,112 @@ impl ShellState {
             login,
             envs: HashMap::new(),
             variables: HashMap::new(),
-            commands: vec![],
+            aliases: HashMap::new(),
+            traps: HashMap::new(),
+            commands: CommandTable::default(),
+            functions: HashMap::new(),
+            status: 0,
+            pipestatus: vec![],
+            depth: 0,
+            jobs: 0,
+            theme: Theme::default(),
+            fs_cache: FsCache::default(),
+            interactive: false,
             is_exit: false,
         }
     }
 
     pub fn init_commands(&mut self, commands: Vec<Box<dyn Command>>) {
         for command in commands {
-            self.commands.push(command);
+            self.commands.add(command);
         }
     }
 
+    /// The command `name` runs, a builtin or a program from `PATH`, or the
+    /// program at `name` when it is a path.
+    pub fn command(&self, name: &str) -> Option<Box<dyn Command>> {
+        if name.contains('/') {
+            return self.program_at(name).ok().map(|program| Box::new(program) as Box<dyn Command>);
+        }
+        self.commands.get(name, &self.search_path())
+    }
+
+    /// The program at the path `name`, relative to the working directory,
+    /// or why it cannot be run.
+    pub fn program_at(&self, name: &str) -> Result<EnvCommand, ShellErr> {
+        let path = self.resolve_path(name);
+        let meta = fs::metadata(&path).map_err(|_| ShellErr::NotFound(name.into()))?;
+        if meta.is_dir() {
+            return Err(ShellErr::Spawn(name.into(), "Is a directory".into()));
+        }
+        if !is_executable(&path) {
+            return Err(ShellErr::PermissionDenied(name.into()));
+        }
+        Ok(EnvCommand::new(name.into(), path))
+    }
+
+    /// Every name a command can be run by.
+    pub fn command_names(&self) -> Vec<String> {
+        self.commands.names(&self.search_path())
+    }
+
+    /// Set `$0` to `name`, and `$1`, `$2`, ..., `$#`, `$@` and `$*` to
+    /// `args`.
+    pub fn set_arguments(&mut self, name: &str, args: &[String]) {
+        self.variables.insert("0".into(), name.into());
+        for (i, arg) in args.iter().enumerate() {
+            self.variables.insert((i + 1).to_string(), arg.clone());
+        }
+        self.variables.insert("#".into(), args.len().to_string());
+        self.variables.insert("@".into(), args.join(" "));
+        self.variables.insert("*".into(), args.join(" "));
+    }
+
+    /// `PATH`, where programs are searched.
+    pub fn search_path(&self) -> String {
+        self.get_variable("PATH").cloned().unwrap_or_default()
+    }
+
     pub fn exit(&mut self) {
         self.is_exit = true;
     }
 
+    /// Record how the commands of a pipeline finished. `$?` is the status
+    /// of the last one.
+    pub fn set_status(&mut self, pipeline: Vec<ExitStatus>) {
+        self.status = pipeline.last().map_or(0, ExitStatus::status);
+        self.pipestatus = pipeline;
+    }
+
+    /// How the last command finished.
+    pub fn last_status(&self) -> ExitStatus {
+        match self.pipestatus.last() {
+            Some(status) if status.status() == self.status => *status,
+            _ => ExitStatus::code(self.status),
+        }
+    }
+
     pub fn add_env(&mut self, key: String, value: String) {
         self.envs.insert(key, value);
     }
 
-    pub fn updata(&mut self) {
-        if let Some(path) = self.envs.get("PATH") {
-            path.split(":").for_each(|path| {
-                if let Ok(dir) = fs::read_dir(path) {
-                    for file in dir {
-                        let file = file.unwrap();
-                        let name = file.file_name().into_string().unwrap();
-                        if self.commands.iter().find(|command| { command.get_name() == name }).is_none() {
-                            let command = EnvCommand::new(name, file.path());
-                            self.commands.push(Box::new(command));
-                        };
-                    }
-                }
-            })
+    /// Look up a shell variable, falling back to the environment.
+    pub fn get_variable(&self, key: &str) -> Option<&String> {
+        self.variables.get(key).or_else(|| self.envs.get(key))
+    }
+
+    /// Resolve `path` against the working directory, expanding a leading `~`.
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = match path.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => match home_dir() {
+                Some(home) => home.join(rest.trim_start_matches('/')),
+                None => PathBuf::from(path),
+            },
+            _ => PathBuf::from(path),
+        };
+        match &self.path {
+            Some(cwd) => cwd.join(path),
+            None => path,
         }
     }
 }
@@ -72,11 +169,22 @@ impl ShellState {
 impl Default for ShellState {
     fn default() -> Self {
         ShellState {
-            path: home_dir(),
+            // where the shell was started, as programs see it
+            path: std::env::current_dir().ok().or_else(home_dir),
             login: whoami().into(),
             envs: HashMap::new(),
             variables: HashMap::new(),
-            commands: vec![],
+            aliases: HashMap::new(),
+            traps: HashMap::new(),
+            commands: CommandTable::default(),
+            functions: HashMap::new(),
+            status: 0,
+            pipestatus: vec![],
+            depth: 0,
+            jobs: 0,
+            theme: Theme::default(),
+            fs_cache: FsCache::default(),
+            interactive: false,
             is_exit: false,
         }
     }
@@ -87,3 +195,24 @@ fn test() {
     let state = ShellState::default();
     println!("{:?}", state);
 }
+
+#[test]
+#[cfg(unix)]
+fn program_at() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("xshell-program-at-{}", std::process::id()));
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("run"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(dir.join("run"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(dir.join("data"), "").unwrap();
+    let state = ShellState::new(dir.clone(), "user".into());
+
+    assert_eq!(state.program_at("./run").unwrap().get_path(), Some(dir.join("./run").as_path()));
+    assert!(matches!(state.program_at("./sub"), Err(ShellErr::Spawn(_, message)) if message == "Is a directory"));
+    assert!(matches!(state.program_at("./data"), Err(ShellErr::PermissionDenied(_))));
+    assert!(matches!(state.program_at("./none"), Err(ShellErr::NotFound(_))));
+    assert!(state.command("sub/../run").is_some());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,73 @@
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+/// Parse a single colour: a crossterm colour name such as `dark_green`
+/// or a `#rrggbb` hex triple.
+pub fn parse_color(s: &str) -> Option<Color> {
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    } else {
+        Color::try_from(s).ok()
+    }
+}
+
+/// Parse a style spec made of words separated by spaces or commas.
+///
+/// A word is a foreground colour, `on_<colour>` for the background, one of
+/// `bold`, `dim`, `italic`, `underline`, `reverse`, or `reset`.
+/// ## Example
+/// ```
+/// use x_protocol::style::parse_style;
+///
+/// assert!(parse_style("bold #f28500 on_black").is_some());
+/// assert!(parse_style("bold sparkly").is_none());
+/// ```
+pub fn parse_style(spec: &str) -> Option<ContentStyle> {
+    let mut style = ContentStyle::new();
+
+    for word in spec.split(|c: char| c == ',' || c.is_whitespace()) {
+        match word {
+            "" => {}
+            "reset" => style = ContentStyle::new(),
+            "bold" => style.attributes.set(Attribute::Bold),
+            "dim" => style.attributes.set(Attribute::Dim),
+            "italic" => style.attributes.set(Attribute::Italic),
+            "underline" => style.attributes.set(Attribute::Underlined),
+            "reverse" => style.attributes.set(Attribute::Reverse),
+            word => match word.strip_prefix("on_") {
+                Some(color) => style.background_color = Some(parse_color(color)?),
+                None => style.foreground_color = Some(parse_color(word)?),
+            },
+        }
+    }
+
+    Some(style)
+}
+
+#[cfg(test)]
+mod style_test {
+    use crossterm::style::{Attribute, Color};
+
+    use super::parse_style;
+
+    #[test]
+    fn parse() {
+        let style = parse_style("bold,dark_blue on_#000000").unwrap();
+        assert_eq!(style.foreground_color, Some(Color::DarkBlue));
+        assert_eq!(style.background_color, Some(Color::Rgb { r: 0, g: 0, b: 0 }));
+        assert!(style.attributes.has(Attribute::Bold));
+    }
+
+    #[test]
+    fn unknown_word() {
+        assert!(parse_style("blurple").is_none());
+        assert!(parse_style("#12345").is_none());
+    }
+}
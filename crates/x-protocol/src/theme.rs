@@ -0,0 +1,249 @@
+use std::{collections::HashMap, fmt::Display, fs, path::Path};
+
+use crossterm::style::{ContentStyle, StyledContent};
+
+use crate::style::parse_style;
+
+/// What a piece of highlighted input is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    Command,
+    Builtin,
+    Function,
+    UnknownCommand,
+    String,
+    Variable,
+    Path,
+    Keyword,
+    Operator,
+    Number,
+    Argument,
+    Error,
+    Comment,
+    /// History suggestion shown after the cursor.
+    Suggestion,
+}
+
+impl Role {
+    pub const ALL: [Role; 14] = [
+        Role::Command,
+        Role::Builtin,
+        Role::Function,
+        Role::UnknownCommand,
+        Role::String,
+        Role::Variable,
+        Role::Path,
+        Role::Keyword,
+        Role::Operator,
+        Role::Number,
+        Role::Argument,
+        Role::Error,
+        Role::Comment,
+        Role::Suggestion,
+    ];
+
+    /// The key used for this role in theme files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Command => "command",
+            Role::Builtin => "builtin",
+            Role::Function => "function",
+            Role::UnknownCommand => "unknown_command",
+            Role::String => "string",
+            Role::Variable => "variable",
+            Role::Path => "path",
+            Role::Keyword => "keyword",
+            Role::Operator => "operator",
+            Role::Number => "number",
+            Role::Argument => "argument",
+            Role::Error => "error",
+            Role::Comment => "comment",
+            Role::Suggestion => "suggestion",
+        }
+    }
+
+    pub fn new(name: &str) -> Option<Self> {
+        Role::ALL.into_iter().find(|role| role.name() == name)
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Styles for every [`Role`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    styles: HashMap<Role, ContentStyle>,
+}
+
+impl Theme {
+    /// Names accepted by [`Theme::builtin`].
+    pub const BUILTIN: [&'static str; 2] = ["dark", "light"];
+
+    fn from_specs(name: &str, specs: [(Role, &str); 14]) -> Self {
+        Theme {
+            name: name.into(),
+            styles: specs
+                .into_iter()
+                .map(|(role, spec)| (role, parse_style(spec).unwrap()))
+                .collect(),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Theme::from_specs(
+            "dark",
+            [
+                (Role::Command, "dark_blue"),
+                (Role::Builtin, "bold dark_cyan"),
+                (Role::Function, "bold blue"),
+                (Role::UnknownCommand, "red"),
+                (Role::String, "dark_green"),
+                (Role::Variable, "magenta"),
+                (Role::Path, "cyan"),
+                (Role::Keyword, "bold dark_green"),
+                (Role::Operator, "#f28500"),
+                (Role::Number, "dark_blue"),
+                (Role::Argument, "yellow"),
+                (Role::Error, "red"),
+                (Role::Comment, "italic dark_grey"),
+                (Role::Suggestion, "dark_grey"),
+            ],
+        )
+    }
+
+    pub fn light() -> Self {
+        Theme::from_specs(
+            "light",
+            [
+                (Role::Command, "blue"),
+                (Role::Builtin, "bold dark_blue"),
+                (Role::Function, "bold dark_cyan"),
+                (Role::UnknownCommand, "red"),
+                (Role::String, "dark_green"),
+                (Role::Variable, "dark_magenta"),
+                (Role::Path, "dark_cyan"),
+                (Role::Keyword, "bold dark_magenta"),
+                (Role::Operator, "dark_yellow"),
+                (Role::Number, "dark_blue"),
+                (Role::Argument, "dark_grey"),
+                (Role::Error, "red underline"),
+                (Role::Comment, "italic grey"),
+                (Role::Suggestion, "grey"),
+            ],
+        )
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            _ => None,
+        }
+    }
+
+    /// Parse a theme file. Keys are role names with style specs as values,
+    /// `extends` picks the built-in theme that unset roles fall back to.
+    /// ## Example
+    /// ```
+    /// use x_protocol::theme::Theme;
+    ///
+    /// let theme = Theme::from_toml("mine", r##"
+    /// extends = "light"
+    /// command = "bold #268bd2"
+    /// "##).unwrap();
+    /// assert_eq!(theme.name, "mine");
+    /// ```
+    pub fn from_toml(name: &str, source: &str) -> Result<Self, String> {
+        let table = source
+            .parse::<toml::Table>()
+            .map_err(|e| format!("{}: {}", name, e.message()))?;
+        let string = |key: &str, value: &toml::Value| {
+            value
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| format!("{}: `{}` must be a string", name, key))
+        };
+
+        let mut theme = match table.get("extends") {
+            Some(base) => {
+                let base = string("extends", base)?;
+                Theme::builtin(&base)
+                    .ok_or_else(|| format!("{}: unknown base theme `{}`", name, base))?
+            }
+            None => Theme::default(),
+        };
+        theme.name = name.into();
+
+        for (key, value) in table.iter().filter(|(key, _)| *key != "extends") {
+            let role = Role::new(key).ok_or_else(|| format!("{}: unknown role `{}`", name, key))?;
+            let spec = string(key, value)?;
+            let style =
+                parse_style(&spec).ok_or_else(|| format!("{}: bad style `{}` for `{}`", name, spec, key))?;
+            theme.styles.insert(role, style);
+        }
+
+        Ok(theme)
+    }
+
+    /// Load a theme file, named after the file stem.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Theme::from_toml(&name, &source)
+    }
+
+    /// A built-in theme by name, otherwise a theme file.
+    pub fn find(name: &str) -> Result<Self, String> {
+        match Theme::builtin(name) {
+            Some(theme) => Ok(theme),
+            None => Theme::load(Path::new(name)),
+        }
+    }
+
+    pub fn style(&self, role: Role) -> ContentStyle {
+        self.styles.get(&role).copied().unwrap_or_default()
+    }
+
+    pub fn paint(&self, role: Role, text: String) -> StyledContent<String> {
+        self.style(role).apply(text)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+#[cfg(test)]
+mod theme_test {
+    use crossterm::style::Color;
+
+    use super::{Role, Theme};
+
+    #[test]
+    fn extends() {
+        let theme = Theme::from_toml("t", "extends = \"light\"\nstring = \"#ffffff\"").unwrap();
+        assert_eq!(theme.style(Role::Command), Theme::light().style(Role::Command));
+        assert_eq!(
+            theme.style(Role::String).foreground_color,
+            Some(Color::Rgb { r: 255, g: 255, b: 255 })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Theme::from_toml("t", "cmd = \"red\"").is_err());
+        assert!(Theme::from_toml("t", "command = \"sparkly\"").is_err());
+        assert!(Theme::from_toml("t", "command = 1").is_err());
+        assert!(Theme::from_toml("t", "extends = \"nope\"").is_err());
+    }
+}
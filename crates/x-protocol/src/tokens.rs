@@ -1,6 +1,6 @@
 use std::{fmt::Display, ops::Range};
 
-use crossterm::style::{StyledContent, Stylize};
+use crate::theme::Role;
 
 macro_rules! Gen {
     ($name: ident, $($kwd: ident => $str: expr),*) => {
@@ -44,6 +44,8 @@ pub enum Tokens {
     Int(String),
     Space(char),
     Arg(String),
+    /// `#` up to the end of the line.
+    Comment(String),
     And,
     Or,
     PipeLine,
@@ -69,6 +71,7 @@ impl Token {
         Token { ty, span, index }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn eq(&self, ty: Tokens) -> bool {
         self.ty == ty
     }
@@ -82,7 +85,7 @@ impl Display for Tokens {
             f,
             "{}",
             match self {
-                Path(s) | Ident(s) | Int(s) | Arg(s) => s.to_string(),
+                Path(s) | Ident(s) | Int(s) | Arg(s) | Comment(s) => s.to_string(),
                 Str(s) => s[1..s.len() - 1].to_string(),
                 Keyword(k) => k.to_string(),
                 Space(c) | Symbol(c) => c.to_string(),
@@ -99,28 +102,27 @@ impl Display for Tokens {
 }
 
 impl Tokens {
-    pub fn default_highlighter(&self) -> StyledContent<String> {
+    /// The role a token has on its own, without knowing its position.
+    pub fn role(&self) -> Option<Role> {
         use Tokens::*;
 
-        match self {
-            Ident(s) | Int(s) => s.clone().dark_blue(),
-            Str(s) => s.clone().dark_green(),
-            Keyword(k) => k.to_string().dark_green().bold(),
-            Space(c) => c.to_string().reset(),
-            Symbol(c) => c.to_string().with(crossterm::style::Color::Rgb {
-                r: 242,
-                g: 133,
-                b: 0,
-            }),
-            Arg(s) => s.clone().yellow(),
-            _ => self.to_string().reset(),
-        }
+        Some(match self {
+            Ident(_) | Arg(_) => Role::Argument,
+            Int(_) => Role::Number,
+            Str(_) => Role::String,
+            Keyword(_) => Role::Keyword,
+            Path(_) => Role::Path,
+            Symbol(_) | And | Or | PipeLine | Background => Role::Operator,
+            Comment(_) => Role::Comment,
+            Space(_) | NewLine | EOF => return None,
+        })
     }
 
-    pub fn highlighter<F>(&self, highlighter: F) -> StyledContent<String>
-    where
-        F: FnOnce(String) -> StyledContent<String>,
-    {
-        highlighter(self.to_string())
+    /// The source text of the token, quotes included.
+    pub fn source(&self) -> String {
+        match self {
+            Tokens::Str(s) => s.clone(),
+            _ => self.to_string(),
+        }
     }
 }
@@ -0,0 +1,191 @@
+use std::fmt::Write;
+
+use x_protocol::crossterm::style::{Color, Stylize};
+use x_protocol::diagnostic::{Diagnostic, Severity};
+
+use crate::layout::char_width;
+
+/// Columns a tab takes up in a snippet.
+const TAB_WIDTH: usize = 4;
+
+/// Format `diagnostic` found in `source` as a caret-underlined snippet:
+///
+/// ```text
+/// error[XS001]: Missing right brackets.
+///  --> <stdin>:1:15
+///   |
+/// 1 | def f [] { ls;
+///   |          - block opened here
+///   |               ^ input ends here
+/// ```
+///
+/// `origin` names the input, `color` adds terminal styling.
+pub fn report(diagnostic: &Diagnostic, source: &str, origin: &str, color: bool) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let lines = lines(&chars);
+    let paint = |text: &str, fg: Color| {
+        if color {
+            text.with(fg).bold().to_string()
+        } else {
+            text.to_string()
+        }
+    };
+    let severity_color = match diagnostic.severity {
+        Severity::Error => Color::Red,
+        Severity::Warning => Color::Yellow,
+    };
+
+    // (line, column, width, marker, message) of every label, in input order
+    let mut labels = vec![];
+    let primary = std::iter::once((&diagnostic.primary, true));
+    for (label, is_primary) in primary.chain(diagnostic.secondary.iter().map(|l| (l, false))) {
+        let line = line_of(&lines, label.span.start);
+        let (start, end) = lines[line];
+        let col = columns(&chars[start..label.span.start.clamp(start, end)]);
+        let width = columns(&chars[label.span.start.clamp(start, end)..label.span.end.clamp(start, end)]).max(1);
+        let marker = if is_primary { '^' } else { '-' };
+        labels.push((line, col, width, marker, label.message.as_str(), is_primary));
+    }
+    labels.sort_by_key(|&(line, col, ..)| (line, col));
+
+    let (first_line, first_col) = {
+        let line = line_of(&lines, diagnostic.primary.span.start);
+        let start = lines[line].0;
+        (line, diagnostic.primary.span.start.saturating_sub(start))
+    };
+    let gutter = (labels.iter().map(|l| l.0).max().unwrap_or(0) + 1).to_string().len();
+    let bar = paint("|", Color::Blue);
+    let empty = format!("{:gutter$} {}", "", bar);
+
+    let mut out = String::new();
+    let severity = match &diagnostic.code {
+        Some(code) => format!("{}[{}]", diagnostic.severity, code),
+        None => diagnostic.severity.to_string(),
+    };
+    let _ = writeln!(
+        out,
+        "{}{}",
+        paint(&severity, severity_color),
+        paint(&format!(": {}", diagnostic.message), Color::Reset)
+    );
+    let _ = writeln!(
+        out,
+        "{:gutter$}{} {}:{}:{}",
+        "",
+        paint("-->", Color::Blue),
+        origin,
+        first_line + 1,
+        first_col + 1
+    );
+    let _ = writeln!(out, "{}", empty);
+
+    let mut previous: Option<usize> = None;
+    for (i, &(line, col, width, marker, message, is_primary)) in labels.iter().enumerate() {
+        if previous != Some(line) {
+            if previous.is_some_and(|previous| line > previous + 1) {
+                let _ = writeln!(out, "{}", paint("...", Color::Blue));
+            }
+            let (start, end) = lines[line];
+            let text: String = chars[start..end].iter().map(expand_tab).collect();
+            let number = paint(&format!("{:>gutter$}", line + 1), Color::Blue);
+            let _ = writeln!(out, "{} {} {}", number, bar, text.trim_end());
+            previous = Some(line);
+        }
+        let markers = marker.to_string().repeat(width);
+        let markers = paint(&markers, if is_primary { severity_color } else { Color::Blue });
+        let message = if message.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", paint(message, if is_primary { severity_color } else { Color::Blue }))
+        };
+        let _ = writeln!(out, "{} {:col$}{}{}", empty, "", markers, message);
+        if i + 1 == labels.len() && !(diagnostic.notes.is_empty() && diagnostic.help.is_empty()) {
+            let _ = writeln!(out, "{}", empty);
+        }
+    }
+
+    for note in &diagnostic.notes {
+        let _ = writeln!(out, "{:gutter$} {} note: {}", "", paint("=", Color::Blue), note);
+    }
+    for help in &diagnostic.help {
+        let _ = writeln!(out, "{:gutter$} {} help: {}", "", paint("=", Color::Blue), help);
+    }
+    out
+}
+
+/// `(start, end)` char offsets of every line, without the newline.
+fn lines(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut lines = vec![];
+    let mut start = 0;
+    for (i, c) in chars.iter().enumerate() {
+        if *c == '\n' {
+            lines.push((start, i));
+            start = i + 1;
+        }
+    }
+    lines.push((start, chars.len()));
+    lines
+}
+
+fn line_of(lines: &[(usize, usize)], offset: usize) -> usize {
+    lines
+        .iter()
+        .position(|&(_, end)| offset <= end)
+        .unwrap_or(lines.len() - 1)
+}
+
+fn expand_tab(c: &char) -> String {
+    match c {
+        '\t' => " ".repeat(TAB_WIDTH),
+        c => c.to_string(),
+    }
+}
+
+fn columns(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .map(|&c| if c == '\t' { TAB_WIDTH } else { char_width(c) })
+        .sum()
+}
+
+#[cfg(test)]
+mod diagnostic_test {
+    use x_protocol::diagnostic::Diagnostic;
+    use x_protocol::ShellErr;
+
+    use super::report;
+
+    #[test]
+    fn labels() {
+        let source = "def f [] { ls;";
+        let err = ShellErr::Unterminated(9..10, "Missing right brackets.".into());
+        let diagnostic = Diagnostic::from_err(&err, source);
+
+        assert_eq!(
+            report(&diagnostic, source, "<stdin>", false),
+            "error[XS001]: Missing right brackets.\n \
+             --> <stdin>:1:15\n  \
+               |\n\
+             1 | def f [] { ls;\n  \
+               |          - block opened here\n  \
+               |               ^ input ends here\n"
+        );
+    }
+
+    #[test]
+    fn lines_and_help() {
+        let source = "ls\necho 你 'a\nls";
+        let diagnostic = Diagnostic::from_err(&ShellErr::UnterminatedStr(10..11), source);
+
+        assert_eq!(
+            report(&diagnostic, source, "a.xsh", false),
+            "error[XS001]: Unterminated string.\n \
+             --> a.xsh:2:8\n  \
+               |\n\
+             2 | echo 你 'a\n  \
+               |         ^ string starts here\n  \
+               |\n  \
+               = help: close it with `'`\n"
+        );
+    }
+}
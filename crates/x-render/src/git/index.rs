@@ -0,0 +1,147 @@
+use std::fs;
+use std::path::Path;
+
+use sha1_smol::Sha1;
+
+use super::object::Id;
+
+/// The part of an index entry needed to spot changes.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: String,
+    pub id: Id,
+    pub mode: u32,
+    pub mtime: (u32, u32),
+    pub size: u32,
+    pub stage: u16,
+    pub skip_worktree: bool,
+}
+
+/// Parse `.git/index` (versions 2 to 4). Extensions are ignored.
+pub fn read_index(git_dir: &Path) -> Option<Vec<Entry>> {
+    let data = fs::read(git_dir.join("index")).ok()?;
+    if data.get(..4)? != b"DIRC" {
+        return None;
+    }
+    let version = be32(&data, 4)?;
+    let count = be32(&data, 8)? as usize;
+    let mut entries = Vec::with_capacity(count);
+    let mut at = 12;
+    let mut previous = String::new();
+
+    for _ in 0..count {
+        let start = at;
+        let mtime = (be32(&data, at + 8)?, be32(&data, at + 12)?);
+        let mode = be32(&data, at + 24)?;
+        let size = be32(&data, at + 36)?;
+        let id: Id = data.get(at + 40..at + 60)?.try_into().ok()?;
+        let flags = u16::from_be_bytes(data.get(at + 60..at + 62)?.try_into().ok()?);
+        at += 62;
+
+        let mut skip_worktree = false;
+        if version >= 3 && flags & 0x4000 != 0 {
+            let extended = u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?);
+            skip_worktree = extended & 0x4000 != 0;
+            at += 2;
+        }
+
+        let path = if version >= 4 {
+            // the previous path minus `strip` bytes, plus a new suffix
+            let mut strip = 0usize;
+            loop {
+                let byte = *data.get(at)?;
+                at += 1;
+                strip = (strip << 7) | (byte & 0x7f) as usize;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                strip += 1;
+            }
+            let nul = at + data.get(at..)?.iter().position(|&b| b == 0)?;
+            let mut path = previous.get(..previous.len().checked_sub(strip)?)?.to_string();
+            path.push_str(&String::from_utf8_lossy(&data[at..nul]));
+            at = nul + 1;
+            path
+        } else {
+            let nul = at + data.get(at..)?.iter().position(|&b| b == 0)?;
+            let path = String::from_utf8_lossy(&data[at..nul]).into_owned();
+            // entries are NUL padded to a multiple of eight bytes
+            at = start + (nul - start + 8) / 8 * 8;
+            path
+        };
+
+        previous = path.clone();
+        entries.push(Entry {
+            path,
+            id,
+            mode,
+            mtime,
+            size,
+            stage: (flags >> 12) & 0x3,
+            skip_worktree,
+        });
+    }
+
+    Some(entries)
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Whether a tracked file in `work_tree` differs from its index entry.
+/// As in git's own check, one of another size changed, and one with the
+/// same size but another modification time is hashed to tell.
+pub fn worktree_changed(work_tree: &Path, entries: &[Entry]) -> bool {
+    entries
+        .iter()
+        .filter(|entry| !entry.skip_worktree)
+        .any(|entry| {
+            if entry.stage != 0 {
+                return true;
+            }
+            // gitlinks (submodules) are directories, not files
+            if entry.mode & 0o170000 == 0o160000 {
+                return false;
+            }
+            let Ok(meta) = fs::symlink_metadata(work_tree.join(&entry.path)) else {
+                return true;
+            };
+            if meta.len() as u32 != entry.size {
+                return true;
+            }
+            mtime(&meta).is_some_and(|time| !same_time(time, entry.mtime))
+                && blob_id(&work_tree.join(&entry.path), &meta) != Some(entry.id)
+        })
+}
+
+/// The id `path` would have as a blob: of its content, or of its target
+/// for a symbolic link.
+fn blob_id(path: &Path, meta: &fs::Metadata) -> Option<Id> {
+    let data = if meta.file_type().is_symlink() {
+        fs::read_link(path).ok()?.to_string_lossy().into_owned().into_bytes()
+    } else {
+        fs::read(path).ok()?
+    };
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", data.len()).as_bytes());
+    hasher.update(&data);
+    Some(hasher.digest().bytes())
+}
+
+fn same_time(file: (u32, u32), index: (u32, u32)) -> bool {
+    // nanoseconds are zero when git was built without them
+    file.0 == index.0 && (index.1 == 0 || file.1 == index.1)
+}
+
+#[cfg(unix)]
+fn mtime(meta: &fs::Metadata) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.mtime() as u32, meta.mtime_nsec() as u32))
+}
+
+#[cfg(not(unix))]
+fn mtime(meta: &fs::Metadata) -> Option<(u32, u32)> {
+    let time = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some((time.as_secs() as u32, time.subsec_nanos()))
+}
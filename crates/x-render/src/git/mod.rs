@@ -0,0 +1,352 @@
+mod index;
+mod object;
+mod refs;
+
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use index::{read_index, worktree_changed};
+use object::{parse_id, parse_tree, Commit, Id, Kind, ObjectStore};
+use refs::{read_head, resolve, upstream, Head};
+
+/// How long `status` waits for a fresh result before serving the cache.
+const TIMEOUT: Duration = Duration::from_millis(50);
+/// How long a cached result is served without recomputing it.
+const TTL: Duration = Duration::from_secs(2);
+/// Commits walked at most when counting ahead / behind.
+const MAX_WALK: usize = 10_000;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitStatus {
+    pub branch: String,
+    pub ahead: usize,
+    pub behind: usize,
+    pub dirty: bool,
+}
+
+impl Display for GitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.branch)?;
+        if self.ahead > 0 {
+            write!(f, " ↑{}", self.ahead)?;
+        }
+        if self.behind > 0 {
+            write!(f, " ↓{}", self.behind)?;
+        }
+        if self.dirty {
+            write!(f, " *")?;
+        }
+        Ok(())
+    }
+}
+
+/// A repository found above some directory.
+struct Repo {
+    work_tree: PathBuf,
+    git_dir: PathBuf,
+    /// Where refs and objects live, differs from `git_dir` for worktrees.
+    common_dir: PathBuf,
+}
+
+impl Repo {
+    fn find(dir: &Path) -> Option<Self> {
+        let (work_tree, git_dir) = dir.ancestors().find_map(|dir| {
+            let git = dir.join(".git");
+            if git.is_dir() {
+                Some((dir.to_path_buf(), git))
+            } else if git.is_file() {
+                // worktrees and submodules: `gitdir: <path>`
+                let content = fs::read_to_string(&git).ok()?;
+                let path = content.strip_prefix("gitdir:")?.trim();
+                Some((dir.to_path_buf(), dir.join(path)))
+            } else {
+                None
+            }
+        })?;
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common) => git_dir.join(common.trim()),
+            Err(_) => git_dir.clone(),
+        };
+
+        Some(Repo {
+            work_tree,
+            git_dir,
+            common_dir,
+        })
+    }
+
+    fn branch(&self) -> Option<String> {
+        Some(match read_head(&self.git_dir)? {
+            Head::Branch(reference) => reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(&reference)
+                .to_string(),
+            Head::Detached(id) => id.chars().take(7).collect(),
+        })
+    }
+
+    fn status(&self) -> Option<GitStatus> {
+        let head = read_head(&self.git_dir)?;
+        let branch = self.branch()?;
+        let store = ObjectStore::open(&self.common_dir);
+        let head_id = match &head {
+            Head::Branch(reference) => resolve(&self.git_dir, &self.common_dir, reference),
+            Head::Detached(id) => Some(id.clone()),
+        }
+        .and_then(|id| parse_id(&id));
+
+        let (ahead, behind) = match (&head, head_id) {
+            (Head::Branch(reference), Some(head_id)) => upstream(&self.common_dir, reference)
+                .and_then(|upstream| resolve(&self.git_dir, &self.common_dir, &upstream))
+                .and_then(|id| parse_id(&id))
+                .map(|upstream| ahead_behind(&store, head_id, upstream))
+                .unwrap_or((0, 0)),
+            _ => (0, 0),
+        };
+
+        let dirty = match read_index(&self.git_dir) {
+            Some(entries) => {
+                worktree_changed(&self.work_tree, &entries)
+                    || head_id.map_or(!entries.is_empty(), |id| staged(&store, id, &entries))
+            }
+            None => false,
+        };
+
+        Some(GitStatus {
+            branch,
+            ahead,
+            behind,
+            dirty,
+        })
+    }
+}
+
+/// Count commits only reachable from `left` and only reachable from
+/// `right`, walking both histories newest first until they meet.
+fn ahead_behind(store: &ObjectStore, left: Id, right: Id) -> (usize, usize) {
+    const LEFT: u8 = 1;
+    const RIGHT: u8 = 2;
+    const BOTH: u8 = LEFT | RIGHT;
+
+    let mut flags: HashMap<Id, u8> = HashMap::new();
+    let mut commits: HashMap<Id, Commit> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    let push = |queue: &mut BinaryHeap<(i64, Id)>, commits: &mut HashMap<Id, Commit>, id: Id| {
+        let time = match commits.entry(id) {
+            Entry::Occupied(commit) => commit.get().time,
+            Entry::Vacant(slot) => match store.read(&id) {
+                Some((Kind::Commit, data)) => slot.insert(Commit::parse(&data)).time,
+                _ => return,
+            },
+        };
+        queue.push((time, id));
+    };
+
+    for (id, flag) in [(left, LEFT), (right, RIGHT)] {
+        *flags.entry(id).or_default() |= flag;
+        push(&mut queue, &mut commits, id);
+    }
+
+    let mut walked = 0;
+    while queue.iter().any(|(_, id)| flags[id] != BOTH) && walked < MAX_WALK {
+        let Some((_, id)) = queue.pop() else {
+            break;
+        };
+        walked += 1;
+        let flag = flags[&id];
+        let parents = commits[&id].parents.clone();
+        for parent in parents {
+            let entry = flags.entry(parent).or_default();
+            if *entry | flag != *entry {
+                *entry |= flag;
+                push(&mut queue, &mut commits, parent);
+            }
+        }
+    }
+
+    // a commit seen from one side first may be reached from the other
+    // later, so only the final flags count
+    flags.values().fold((0, 0), |(ahead, behind), flag| match *flag {
+        LEFT => (ahead + 1, behind),
+        RIGHT => (ahead, behind + 1),
+        _ => (ahead, behind),
+    })
+}
+
+/// Whether the index differs from the tree of commit `head`.
+fn staged(store: &ObjectStore, head: Id, entries: &[index::Entry]) -> bool {
+    let Some(tree) = store
+        .read(&head)
+        .and_then(|(_, data)| Commit::parse(&data).tree)
+    else {
+        return true;
+    };
+    let mut files = HashMap::new();
+    if !flatten_tree(store, tree, String::new(), &mut files) {
+        return true;
+    }
+
+    files.len() != entries.len()
+        || entries
+            .iter()
+            .any(|entry| files.get(&entry.path) != Some(&entry.id))
+}
+
+fn flatten_tree(store: &ObjectStore, tree: Id, prefix: String, files: &mut HashMap<String, Id>) -> bool {
+    let Some((Kind::Tree, data)) = store.read(&tree) else {
+        return false;
+    };
+    let Some(entries) = parse_tree(&data) else {
+        return false;
+    };
+
+    entries.into_iter().all(|(mode, name, id)| {
+        let path = format!("{}{}", prefix, name);
+        if mode == 0o40000 {
+            flatten_tree(store, id, path + "/", files)
+        } else {
+            files.insert(path, id);
+            true
+        }
+    })
+}
+
+struct CacheEntry {
+    status: Option<GitStatus>,
+    updated: Instant,
+    pending: bool,
+}
+
+fn cache() -> &'static Mutex<HashMap<PathBuf, CacheEntry>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, CacheEntry>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Current branch name, or the short commit hash on a detached head.
+pub fn branch(dir: &Path) -> Option<String> {
+    Repo::find(dir)?.branch()
+}
+
+/// Branch, ahead / behind counts and dirty state of the repository
+/// containing `dir`, or `None` outside a repository.
+///
+/// Results are cached per directory. A refresh runs on its own thread and
+/// is waited for at most [`TIMEOUT`]; after that the previous result (or
+/// just the branch) is returned and the refresh lands in the cache.
+pub fn status(dir: &Path) -> Option<GitStatus> {
+    let repo = Repo::find(dir)?;
+    let stale = {
+        let mut cache = cache().lock().ok()?;
+        match cache.get_mut(dir) {
+            Some(entry) if entry.pending || entry.updated.elapsed() < TTL => {
+                return entry.status.clone();
+            }
+            Some(entry) => {
+                entry.pending = true;
+                entry.status.clone()
+            }
+            None => {
+                cache.insert(
+                    dir.to_path_buf(),
+                    CacheEntry {
+                        status: None,
+                        updated: Instant::now(),
+                        pending: true,
+                    },
+                );
+                None
+            }
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let key = dir.to_path_buf();
+    let fallback = repo.branch();
+    thread::spawn(move || {
+        let status = repo.status();
+        if let Ok(mut cache) = cache().lock() {
+            cache.insert(
+                key,
+                CacheEntry {
+                    status: status.clone(),
+                    updated: Instant::now(),
+                    pending: false,
+                },
+            );
+        }
+        let _ = sender.send(status);
+    });
+
+    match receiver.recv_timeout(TIMEOUT) {
+        Ok(status) => status,
+        Err(_) => stale.or_else(|| {
+            fallback.map(|branch| GitStatus {
+                branch,
+                ..Default::default()
+            })
+        }),
+    }
+}
+
+#[cfg(test)]
+mod git_test {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    use super::Repo;
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .args(["-c", "user.name=x", "-c", "user.email=x@x", "-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    #[test]
+    fn status() {
+        let dir = std::env::temp_dir().join(format!("xshell-git-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // the repository is built with git itself, skip where it is missing
+        if !git(&dir, &["init", "-q", "-b", "main"]) {
+            return;
+        }
+        fs::write(dir.join("a"), "a").unwrap();
+        assert!(git(&dir, &["add", "a"]));
+        assert!(git(&dir, &["commit", "-qm", "a"]));
+        // both commits usually share a timestamp, which the walk must handle
+        assert!(git(&dir, &["update-ref", "refs/remotes/origin/main", "HEAD"]));
+        assert!(git(&dir, &["config", "branch.main.remote", "origin"]));
+        assert!(git(&dir, &["config", "branch.main.merge", "refs/heads/main"]));
+        fs::write(dir.join("b"), "b").unwrap();
+        assert!(git(&dir, &["add", "b"]));
+        assert!(git(&dir, &["commit", "-qm", "b"]));
+        assert!(git(&dir, &["gc", "-q"]));
+
+        let repo = Repo::find(&dir.join("sub/dir")).unwrap();
+        let status = repo.status().unwrap();
+        assert_eq!(status.branch, "main");
+        assert_eq!((status.ahead, status.behind), (1, 0));
+        assert!(!status.dirty);
+
+        // touched but the same, which only its content tells
+        let a = fs::File::options().append(true).open(dir.join("a")).unwrap();
+        a.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5)).unwrap();
+        assert!(!repo.status().unwrap().dirty);
+        fs::write(dir.join("a"), "b").unwrap();
+        assert!(repo.status().unwrap().dirty);
+        fs::write(dir.join("a"), "changed").unwrap();
+        assert!(repo.status().unwrap().dirty);
+        assert_eq!(status.to_string(), "main ↑1");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+/// Deepest delta chain followed before giving up.
+const MAX_DELTA_DEPTH: usize = 64;
+
+pub type Id = [u8; 20];
+
+pub fn parse_id(hex: &str) -> Option<Id> {
+    let hex = hex.trim();
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut id = [0; 20];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(id)
+}
+
+pub fn to_hex(id: &Id) -> String {
+    id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl Kind {
+    fn from_pack(ty: u8) -> Option<Self> {
+        Some(match ty {
+            1 => Kind::Commit,
+            2 => Kind::Tree,
+            3 => Kind::Blob,
+            4 => Kind::Tag,
+            _ => return None,
+        })
+    }
+
+    fn from_name(name: &[u8]) -> Option<Self> {
+        Some(match name {
+            b"commit" => Kind::Commit,
+            b"tree" => Kind::Tree,
+            b"blob" => Kind::Blob,
+            b"tag" => Kind::Tag,
+            _ => return None,
+        })
+    }
+}
+
+/// A pack index (`.idx`, version 2) and the path of its pack.
+struct Pack {
+    pack: PathBuf,
+    ids: Vec<Id>,
+    offsets: Vec<u64>,
+}
+
+impl Pack {
+    fn open(idx: &Path) -> Option<Self> {
+        let data = fs::read(idx).ok()?;
+        if data.get(..8)? != b"\xfftOc\x00\x00\x00\x02" {
+            return None;
+        }
+        let count = u32_at(&data, 8 + 255 * 4)? as usize;
+        let ids_start = 8 + 256 * 4;
+        let offsets_start = ids_start + count * 24;
+        let large_start = offsets_start + count * 4;
+
+        let ids = (0..count)
+            .map(|i| data.get(ids_start + i * 20..ids_start + i * 20 + 20)?.try_into().ok())
+            .collect::<Option<Vec<Id>>>()?;
+        let offsets = (0..count)
+            .map(|i| {
+                let offset = u32_at(&data, offsets_start + i * 4)?;
+                if offset & 0x8000_0000 == 0 {
+                    Some(offset as u64)
+                } else {
+                    let at = large_start + (offset & 0x7fff_ffff) as usize * 8;
+                    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+                }
+            })
+            .collect::<Option<Vec<u64>>>()?;
+
+        Some(Pack {
+            pack: idx.with_extension("pack"),
+            ids,
+            offsets,
+        })
+    }
+
+    fn find(&self, id: &Id) -> Option<u64> {
+        let i = self.ids.binary_search(id).ok()?;
+        Some(self.offsets[i])
+    }
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Read-only access to loose and packed objects of a repository.
+pub struct ObjectStore {
+    objects: PathBuf,
+    packs: Vec<Pack>,
+}
+
+impl ObjectStore {
+    pub fn open(common_dir: &Path) -> Self {
+        let objects = common_dir.join("objects");
+        let packs = fs::read_dir(objects.join("pack"))
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+                    .filter_map(|path| Pack::open(&path))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        ObjectStore { objects, packs }
+    }
+
+    pub fn read(&self, id: &Id) -> Option<(Kind, Vec<u8>)> {
+        self.read_depth(id, 0)
+    }
+
+    fn read_depth(&self, id: &Id, depth: usize) -> Option<(Kind, Vec<u8>)> {
+        if let Some(object) = self.read_loose(id) {
+            return Some(object);
+        }
+        self.packs.iter().find_map(|pack| {
+            let offset = pack.find(id)?;
+            self.read_packed(pack, offset, depth)
+        })
+    }
+
+    fn read_loose(&self, id: &Id) -> Option<(Kind, Vec<u8>)> {
+        let hex = to_hex(id);
+        let file = File::open(self.objects.join(&hex[..2]).join(&hex[2..])).ok()?;
+        let mut data = vec![];
+        ZlibDecoder::new(file).read_to_end(&mut data).ok()?;
+
+        let space = data.iter().position(|&b| b == b' ')?;
+        let nul = data.iter().position(|&b| b == 0)?;
+        let kind = Kind::from_name(&data[..space])?;
+        Some((kind, data.split_off(nul + 1)))
+    }
+
+    fn read_packed(&self, pack: &Pack, offset: u64, depth: usize) -> Option<(Kind, Vec<u8>)> {
+        if depth > MAX_DELTA_DEPTH {
+            return None;
+        }
+        let mut file = BufReader::new(File::open(&pack.pack).ok()?);
+        file.seek(SeekFrom::Start(offset)).ok()?;
+
+        let mut byte = read_byte(&mut file)?;
+        let ty = (byte >> 4) & 0x7;
+        let mut size = (byte & 0x0f) as usize;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = read_byte(&mut file)?;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+        }
+
+        let (kind, base) = match ty {
+            OBJ_OFS_DELTA => {
+                let mut byte = read_byte(&mut file)?;
+                let mut distance = (byte & 0x7f) as u64;
+                while byte & 0x80 != 0 {
+                    byte = read_byte(&mut file)?;
+                    distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+                }
+                self.read_packed(pack, offset.checked_sub(distance)?, depth + 1)?
+            }
+            OBJ_REF_DELTA => {
+                let mut base = [0; 20];
+                file.read_exact(&mut base).ok()?;
+                self.read_depth(&base, depth + 1)?
+            }
+            ty => {
+                let data = inflate(file, size)?;
+                return Some((Kind::from_pack(ty)?, data));
+            }
+        };
+
+        let delta = inflate(file, size)?;
+        Some((kind, apply_delta(&base, &delta)?))
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> Option<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte).ok()?;
+    Some(byte[0])
+}
+
+fn inflate<R: Read>(reader: R, size: usize) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(size);
+    ZlibDecoder::new(reader)
+        .take(size as u64)
+        .read_to_end(&mut data)
+        .ok()?;
+    (data.len() == size).then_some(data)
+}
+
+fn delta_size(delta: &[u8], at: &mut usize) -> Option<usize> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *delta.get(*at)?;
+        *at += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break Some(size);
+        }
+    }
+}
+
+/// Rebuild an object from its `base` and a git delta.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut at = 0;
+    if delta_size(delta, &mut at)? != base.len() {
+        return None;
+    }
+    let size = delta_size(delta, &mut at)?;
+    let mut out = Vec::with_capacity(size);
+
+    while at < delta.len() {
+        let op = delta[at];
+        at += 1;
+        if op & 0x80 != 0 {
+            // copy from base
+            let mut offset = 0;
+            let mut len = 0;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(at)? as usize) << (i * 8);
+                    at += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    len |= (*delta.get(at)? as usize) << (i * 8);
+                    at += 1;
+                }
+            }
+            if len == 0 {
+                len = 0x10000;
+            }
+            out.extend_from_slice(base.get(offset..offset + len)?);
+        } else if op != 0 {
+            // insert literal bytes
+            let len = op as usize;
+            out.extend_from_slice(delta.get(at..at + len)?);
+            at += len;
+        } else {
+            return None;
+        }
+    }
+
+    (out.len() == size).then_some(out)
+}
+
+/// The parents and committer timestamp of a commit.
+pub struct Commit {
+    pub parents: Vec<Id>,
+    pub time: i64,
+    pub tree: Option<Id>,
+}
+
+impl Commit {
+    pub fn parse(data: &[u8]) -> Self {
+        let mut commit = Commit {
+            parents: vec![],
+            time: 0,
+            tree: None,
+        };
+        let text = String::from_utf8_lossy(data);
+
+        for line in text.lines() {
+            if line.is_empty() {
+                break;
+            } else if let Some(id) = line.strip_prefix("parent ") {
+                commit.parents.extend(parse_id(id));
+            } else if let Some(id) = line.strip_prefix("tree ") {
+                commit.tree = parse_id(id);
+            } else if let Some(committer) = line.strip_prefix("committer ") {
+                commit.time = committer
+                    .rsplit(' ')
+                    .nth(1)
+                    .and_then(|time| time.parse().ok())
+                    .unwrap_or(0);
+            }
+        }
+
+        commit
+    }
+}
+
+/// Entries of a tree object as `(mode, name, id)`.
+pub fn parse_tree(data: &[u8]) -> Option<Vec<(u32, String, Id)>> {
+    let mut entries = vec![];
+    let mut at = 0;
+
+    while at < data.len() {
+        let space = at + data[at..].iter().position(|&b| b == b' ')?;
+        let nul = space + data[space..].iter().position(|&b| b == 0)?;
+        let mode = u32::from_str_radix(std::str::from_utf8(&data[at..space]).ok()?, 8).ok()?;
+        let name = String::from_utf8_lossy(&data[space + 1..nul]).into_owned();
+        let id = data.get(nul + 1..nul + 21)?.try_into().ok()?;
+        entries.push((mode, name, id));
+        at = nul + 21;
+    }
+
+    Some(entries)
+}
+
+#[cfg(test)]
+mod object_test {
+    use super::{apply_delta, parse_id, to_hex, Commit};
+
+    #[test]
+    fn hex_round_trip() {
+        let hex = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(to_hex(&parse_id(hex).unwrap()), hex);
+        assert!(parse_id("xyz").is_none());
+    }
+
+    #[test]
+    fn delta() {
+        let base = b"hello world";
+        // base size 11, result size 11: copy "hello " then insert "there"
+        let delta = [11, 11, 0x80 | 0x01 | 0x10, 0, 6, 5, b't', b'h', b'e', b'r', b'e'];
+        assert_eq!(apply_delta(base, &delta).unwrap(), b"hello there");
+    }
+
+    #[test]
+    fn commit() {
+        let data = b"tree 0123456789abcdef0123456789abcdef01234567\n\
+            parent 89abcdef0123456789abcdef0123456789abcdef\n\
+            author A <a@b> 1600000000 +0000\n\
+            committer A <a@b> 1600000042 +0000\n\nmessage\n";
+        let commit = Commit::parse(data);
+        assert_eq!(commit.parents.len(), 1);
+        assert_eq!(commit.time, 1600000042);
+        assert!(commit.tree.is_some());
+    }
+}
@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::Path;
+
+/// What `HEAD` points at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Head {
+    /// A symbolic ref such as `refs/heads/main`.
+    Branch(String),
+    /// A detached commit id.
+    Detached(String),
+}
+
+pub fn read_head(git_dir: &Path) -> Option<Head> {
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    Some(match head.strip_prefix("ref: ") {
+        Some(reference) => Head::Branch(reference.to_string()),
+        None => Head::Detached(head.to_string()),
+    })
+}
+
+/// Resolve `name` to a commit id, looking at loose refs first and
+/// `packed-refs` after that.
+pub fn resolve(git_dir: &Path, common_dir: &Path, name: &str) -> Option<String> {
+    for dir in [git_dir, common_dir] {
+        if let Ok(content) = fs::read_to_string(dir.join(name)) {
+            let content = content.trim();
+            return match content.strip_prefix("ref: ") {
+                Some(target) => resolve(git_dir, common_dir, target),
+                None => Some(content.to_string()),
+            };
+        }
+    }
+
+    let packed = fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .find_map(|line| {
+            let (id, reference) = line.split_once(' ')?;
+            (reference == name).then(|| id.to_string())
+        })
+}
+
+/// The remote tracking ref of `branch` (a full `refs/heads/` name), read
+/// from the `[branch "..."]` section of the repository config.
+pub fn upstream(common_dir: &Path, branch: &str) -> Option<String> {
+    let short = branch.strip_prefix("refs/heads/")?;
+    let config = fs::read_to_string(common_dir.join("config")).ok()?;
+    let section = format!("[branch \"{}\"]", short);
+    let mut in_section = false;
+    let mut remote = None;
+    let mut merge = None;
+
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == section;
+        } else if in_section {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "remote" => remote = Some(value.trim().to_string()),
+                "merge" => merge = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    let (remote, merge) = (remote?, merge?);
+    if remote == "." {
+        Some(merge)
+    } else {
+        let merge = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+        Some(format!("refs/remotes/{}/{}", remote, merge))
+    }
+}
+
+#[cfg(test)]
+mod refs_test {
+    use std::fs;
+
+    use super::{resolve, upstream};
+
+    #[test]
+    fn packed_and_upstream() {
+        let dir = std::env::temp_dir().join(format!("xshell-refs-{}", std::process::id()));
+        fs::create_dir_all(dir.join("refs/heads")).unwrap();
+        fs::write(dir.join("refs/heads/main"), "1111111111111111111111111111111111111111\n").unwrap();
+        fs::write(
+            dir.join("packed-refs"),
+            "# pack-refs with: peeled\n2222222222222222222222222222222222222222 refs/remotes/origin/main\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("config"),
+            "[core]\n\tbare = false\n[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n",
+        )
+        .unwrap();
+
+        let upstream = upstream(&dir, "refs/heads/main").unwrap();
+        assert_eq!(upstream, "refs/remotes/origin/main");
+        assert_eq!(resolve(&dir, &dir, "refs/heads/main").unwrap(), "1".repeat(40));
+        assert_eq!(resolve(&dir, &dir, &upstream).unwrap(), "2".repeat(40));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
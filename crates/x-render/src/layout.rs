@@ -0,0 +1,85 @@
+use unicode_width::UnicodeWidthChar;
+
+/// A screen cell, relative to the first row of the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Position {
+    pub fn new(row: usize, col: usize) -> Self {
+        Position { row, col }
+    }
+
+    /// Move a cursor sitting past the last column onto the next row,
+    /// where the terminal will put the next character.
+    pub fn wrapped(self, width: usize) -> Self {
+        if self.col >= width.max(1) {
+            Position::new(self.row + 1, 0)
+        } else {
+            self
+        }
+    }
+}
+
+/// Display width of `c` in terminal columns.
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// Display width of `s` in terminal columns, ignoring wrapping.
+pub fn str_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+/// Walk `s` from `pos` the way a terminal `width` columns wide prints it.
+///
+/// The returned column may equal `width` when the last character filled the
+/// row, since terminals defer the wrap until the next character arrives.
+pub fn advance(mut pos: Position, s: &str, width: usize) -> Position {
+    let width = width.max(1);
+
+    for c in s.chars() {
+        if c == '\n' {
+            pos = Position::new(pos.row + 1, 0);
+            continue;
+        }
+        let w = char_width(c);
+        if pos.col + w > width {
+            pos = Position::new(pos.row + 1, 0);
+        }
+        pos.col += w;
+    }
+
+    pos
+}
+
+#[cfg(test)]
+mod layout_test {
+    use super::{advance, Position};
+
+    #[test]
+    fn no_wrap() {
+        assert_eq!(advance(Position::default(), "abc", 10), Position::new(0, 3));
+    }
+
+    #[test]
+    fn exact_fill_defers_wrap() {
+        let end = advance(Position::default(), "abcd", 4);
+        assert_eq!(end, Position::new(0, 4));
+        assert_eq!(end.wrapped(4), Position::new(1, 0));
+    }
+
+    #[test]
+    fn wrap_multiple_rows() {
+        let start = advance(Position::default(), "$ ", 4);
+        assert_eq!(advance(start, "abcdefg", 4), Position::new(2, 1));
+    }
+
+    #[test]
+    fn wide_char_at_boundary() {
+        // `你` does not fit in the last column and moves to the next row.
+        assert_eq!(advance(Position::new(0, 3), "你", 4), Position::new(1, 2));
+    }
+}
@@ -1,60 +1,194 @@
+mod diagnostic;
+mod git;
+mod layout;
+mod prompt;
+
 use std::fmt::Display;
-use std::io::{stdout, Stdout};
-use x_protocol::crossterm::cursor::{MoveLeft, MoveToColumn, RestorePosition, SavePosition};
-use x_protocol::crossterm::execute;
-use x_protocol::crossterm::style::{Print, Stylize};
-use x_protocol::crossterm::terminal::{Clear, ClearType};
+use std::io::{stdout, Stdout, Write};
+use x_protocol::crossterm::cursor::{MoveDown, MoveToColumn, MoveUp};
+use x_protocol::crossterm::style::{Print, StyledContent};
+use x_protocol::crossterm::terminal::{self, Clear, ClearType};
 use x_protocol::crossterm::Result;
+use x_protocol::crossterm::{execute, queue};
 use x_protocol::ShellState;
 
+pub use diagnostic::*;
+pub use layout::*;
+pub use prompt::*;
+
 pub struct Render {
     stdout: Stdout,
+    /// Terminal width in columns.
+    width: u16,
+    /// Prompt redrawn in front of the input on every render.
+    prompt: Prompt,
+    /// Prompt drawn at the right edge while the input leaves room for it.
+    rprompt: Prompt,
+    /// Last rendered input and the byte index of the cursor in it.
+    line: String,
+    cursor: usize,
+    /// Rows between the first prompt row and the cursor / the end of input.
+    cursor_row: usize,
+    cursor_col: usize,
+    end_row: usize,
 }
 
 impl Render {
     pub fn new_line(&mut self, shell_state: &ShellState) -> Result<()> {
+        self.leave()?;
         execute!(&self.stdout, Print("\n"), MoveToColumn(0))?;
         self.output_state(shell_state)
     }
 
-    pub fn clear_line(&self) -> Result<()> {
+    /// Move back to the first prompt row and clear everything below it.
+    pub fn clear_line(&mut self) -> Result<()> {
+        if self.cursor_row > 0 {
+            queue!(&self.stdout, MoveUp(self.cursor_row as u16))?;
+        }
+        self.cursor_row = 0;
+        self.end_row = 0;
         execute!(
             &self.stdout,
-            RestorePosition,
+            MoveToColumn(0),
             Clear(ClearType::FromCursorDown)
         )
     }
 
-    pub fn render<T: Display>(&self, input: T, cursor: usize) -> Result<()> {
-        execute!(&self.stdout, Print(input), MoveLeft(cursor as u16))
+    /// Draw the prompt followed by `input` and the `hint` ghost text, then
+    /// place the cursor at byte index `cursor` of `raw`, the unstyled text
+    /// of `input`.
+    pub fn render<T: Display>(
+        &mut self,
+        input: T,
+        raw: &str,
+        cursor: usize,
+        hint: &StyledContent<String>,
+    ) -> Result<()> {
+        let width = self.width as usize;
+        let line = format!("{}{}", raw, hint.content());
+        let start = advance(Position::default(), &self.prompt.plain, width);
+        let end = advance(start, &line, width);
+        let before = raw.get(..cursor).unwrap_or(raw);
+        let target = advance(start, before, width).wrapped(width);
+
+        queue!(&self.stdout, Print(&self.prompt.styled), Print(input), Print(hint))?;
+        let rwidth = str_width(&self.rprompt.plain);
+        if !self.rprompt.is_empty() && end.row == start.row && end.col + 1 + rwidth <= width {
+            queue!(
+                &self.stdout,
+                MoveToColumn((width - rwidth) as u16),
+                Print(&self.rprompt.styled)
+            )?;
+        }
+        let end = if end.col >= width {
+            // Force the pending wrap so the cursor can sit on the new row.
+            queue!(&self.stdout, Print("\n"), MoveToColumn(0))?;
+            end.wrapped(width)
+        } else {
+            end
+        };
+        if end.row > target.row {
+            queue!(&self.stdout, MoveUp((end.row - target.row) as u16))?;
+        }
+        queue!(&self.stdout, MoveToColumn(target.col as u16))?;
+
+        self.line = line;
+        self.cursor = before.len();
+        self.cursor_row = target.row;
+        self.cursor_col = target.col;
+        self.end_row = end.row;
+        self.stdout.flush()
     }
 
-    pub fn output_state(&self, state: &ShellState) -> Result<()> {
-        execute!(
+    /// Show `text` under the input until the next render, leaving the cursor
+    /// where it is. `plain` is `text` without styling.
+    pub fn below(&mut self, text: &str, plain: &str) -> Result<()> {
+        let plain = plain.trim_end_matches('\n');
+        let rows = 1 + advance(Position::default(), plain, self.width as usize).row;
+        let end_row = self.end_row + rows;
+
+        if self.end_row > self.cursor_row {
+            queue!(&self.stdout, MoveDown((self.end_row - self.cursor_row) as u16))?;
+        }
+        queue!(
             &self.stdout,
-            Print(format!(
-                "{}@{}: ",
-                state.login.clone().green(),
-                state
-                    .path
-                    .clone()
-                    .unwrap()
-                    .as_path()
-                    .to_str()
-                    .unwrap()
-                    .blue()
-            )),
-            SavePosition
-        )
+            Print("\r\n"),
+            Print(text.trim_end_matches('\n').replace('\n', "\r\n")),
+            MoveUp((end_row - self.cursor_row) as u16),
+            MoveToColumn(self.cursor_col as u16)
+        )?;
+        self.end_row = end_row;
+        self.stdout.flush()
+    }
+
+    /// Recompute the layout after the terminal changed to `width` columns.
+    ///
+    /// Most terminals reflow wrapped lines on resize, so the cursor is
+    /// assumed to sit where the last frame would land at the new width.
+    pub fn resize(&mut self, width: u16) {
+        self.width = width.max(1);
+        let width = self.width as usize;
+        let start = advance(Position::default(), &self.prompt.plain, width);
+        let target = advance(start, &self.line[..self.cursor], width).wrapped(width);
+        self.cursor_row = target.row;
+        self.cursor_col = target.col;
+        self.end_row = advance(start, &self.line, width).wrapped(width).row;
+    }
+
+    pub fn output_state(&mut self, state: &ShellState) -> Result<()> {
+        self.prompt = Prompt::left(state);
+        self.rprompt = Prompt::right(state);
+        self.line.clear();
+        self.cursor = 0;
+
+        let width = self.width as usize;
+        let end = advance(Position::default(), &self.prompt.plain, width);
+        queue!(&self.stdout, Print(&self.prompt.styled))?;
+        if end.col >= width {
+            queue!(&self.stdout, Print("\n"), MoveToColumn(0))?;
+        }
+        self.cursor_row = end.wrapped(width).row;
+        self.cursor_col = end.wrapped(width).col;
+        self.end_row = self.cursor_row;
+        self.stdout.flush()
     }
 
-    pub fn debug<T: Display>(&self, s: T) -> Result<()> {
+    pub fn debug<T: Display>(&mut self, s: T) -> Result<()> {
+        self.leave()?;
         execute!(&self.stdout, Print('\n'), MoveToColumn(0), Print(s), Print('\n'), MoveToColumn(0))
     }
+
+    /// Start a line below the input, for output that comes while it is
+    /// being edited. The prompt is drawn again with [`output_state`](Self::output_state).
+    pub fn break_line(&mut self) -> Result<()> {
+        self.leave()?;
+        execute!(&self.stdout, Print("\n"), MoveToColumn(0))
+    }
+
+    /// Move the cursor below the input so output does not overwrite it.
+    fn leave(&mut self) -> Result<()> {
+        if self.end_row > self.cursor_row {
+            queue!(&self.stdout, MoveDown((self.end_row - self.cursor_row) as u16))?;
+        }
+        self.cursor_row = 0;
+        self.end_row = 0;
+        Ok(())
+    }
 }
 
 impl Default for Render {
     fn default() -> Self {
-        Render { stdout: stdout() }
+        let (width, _) = terminal::size().unwrap_or((80, 24));
+        Render {
+            stdout: stdout(),
+            width: width.max(1),
+            prompt: Prompt::default(),
+            rprompt: Prompt::default(),
+            line: String::new(),
+            cursor: 0,
+            cursor_row: 0,
+            cursor_col: 0,
+            end_row: 0,
+        }
     }
 }
@@ -0,0 +1,215 @@
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+use x_protocol::crossterm::style::{ContentStyle, Stylize};
+use x_protocol::style::parse_style;
+use x_protocol::ShellState;
+use x_util::{home_dir, hostname, local_time};
+
+use crate::git;
+
+/// Prompt used when `PS1` is not set, `login@cwd: `, with the status of
+/// the last command when it failed.
+pub const DEFAULT_PS1: &str = r"{green}\u{reset}@{blue}\w{reset}\S: ";
+
+/// A prompt expanded from a `PS1` style template.
+///
+/// Escapes:
+/// - `\u` user, `\h` short host name, `\H` full host name
+/// - `\w` working directory with `~` for home, `\W` its last component
+/// - `\?` last exit status, `\j` number of jobs
+/// - `\S` ` [status]` in red when the last command failed, with the
+///   signal that killed it, empty otherwise
+/// - `\t` time as `HH:MM:SS`, `\A` time as `HH:MM`
+/// - `\b` git branch, `\g` branch with ahead / behind counts and `*` when
+///   dirty, `\$` `#` for root and `$` otherwise
+/// - `\n` newline, `\\` backslash
+///
+/// `{spec}` switches the style for the following text, where spec is
+/// anything [`parse_style`] accepts, e.g. `{bold red}` or `{reset}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prompt {
+    pub styled: String,
+    pub plain: String,
+}
+
+impl Prompt {
+    pub fn new(template: &str, state: &ShellState) -> Self {
+        let mut prompt = Prompt::default();
+        let mut style = ContentStyle::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('S') => {
+                        let status = state.last_status();
+                        if !status.success() {
+                            prompt.push(style.red(), &format!(" [{}]", status));
+                        }
+                    }
+                    Some(escape) => {
+                        let text = expand(escape, state);
+                        prompt.push(style, &text);
+                    }
+                    None => prompt.push(style, "\\"),
+                },
+                '{' => match style_markup(&mut chars) {
+                    Ok(new_style) => style = new_style,
+                    Err(text) => prompt.push(style, &text),
+                },
+                c => prompt.push(style, &c.to_string()),
+            }
+        }
+
+        prompt
+    }
+
+    /// The left prompt from `PS1`.
+    pub fn left(state: &ShellState) -> Self {
+        let template = state.get_variable("PS1").map(|s| s.as_str());
+        Prompt::new(template.unwrap_or(DEFAULT_PS1), state)
+    }
+
+    /// The right prompt from `RPROMPT`, empty when unset.
+    pub fn right(state: &ShellState) -> Self {
+        match state.get_variable("RPROMPT") {
+            Some(template) => Prompt::new(template, state),
+            None => Prompt::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plain.is_empty()
+    }
+
+    fn push(&mut self, style: ContentStyle, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.plain.push_str(text);
+        // raw mode does not translate `\n`, so return the carriage as well
+        let text = text.replace('\n', "\r\n");
+        self.styled.push_str(&style.apply(text).to_string());
+    }
+}
+
+/// Read the rest of a `{spec}`. On failure the consumed text is returned
+/// so it can be printed literally.
+fn style_markup(chars: &mut Peekable<Chars>) -> Result<ContentStyle, String> {
+    let mut spec = String::new();
+
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => spec.push(c),
+            None => return Err(format!("{{{}", spec)),
+        }
+    }
+
+    parse_style(&spec).ok_or_else(|| format!("{{{}}}", spec))
+}
+
+fn expand(escape: char, state: &ShellState) -> String {
+    match escape {
+        'u' => state.login.clone(),
+        'h' => hostname().split('.').next().unwrap_or_default().to_string(),
+        'H' => hostname(),
+        'w' => state.path.as_deref().map(abbreviate_home).unwrap_or_default(),
+        'W' => state
+            .path
+            .as_deref()
+            .map(|path| match path.file_name() {
+                Some(name) if Some(path) != home_dir().as_deref() => {
+                    name.to_string_lossy().into_owned()
+                }
+                _ => abbreviate_home(path),
+            })
+            .unwrap_or_default(),
+        '?' => state.status.to_string(),
+        'j' => state.jobs.to_string(),
+        't' => {
+            let (h, m, s) = local_time();
+            format!("{:02}:{:02}:{:02}", h, m, s)
+        }
+        'A' => {
+            let (h, m, _) = local_time();
+            format!("{:02}:{:02}", h, m)
+        }
+        'b' => state
+            .path
+            .as_deref()
+            .and_then(git::branch)
+            .unwrap_or_default(),
+        'g' => state
+            .path
+            .as_deref()
+            .and_then(git::status)
+            .map(|status| status.to_string())
+            .unwrap_or_default(),
+        '$' => if state.login == "root" { "#" } else { "$" }.into(),
+        'n' => "\n".into(),
+        '\\' => "\\".into(),
+        c => format!("\\{}", c),
+    }
+}
+
+/// Replace the home directory prefix of `path` with `~`.
+fn abbreviate_home(path: &Path) -> String {
+    if let Some(home) = home_dir() {
+        if let Ok(rest) = path.strip_prefix(&home) {
+            return if rest.as_os_str().is_empty() {
+                "~".into()
+            } else {
+                format!("~/{}", rest.display())
+            };
+        }
+    }
+    path.display().to_string()
+}
+
+#[cfg(test)]
+mod prompt_test {
+    use std::path::PathBuf;
+
+    use x_protocol::command::ExitStatus;
+    use x_protocol::ShellState;
+
+    use super::Prompt;
+
+    fn state() -> ShellState {
+        ShellState::new(PathBuf::from("/tmp/project"), "alice".into())
+    }
+
+    #[test]
+    fn escapes() {
+        let mut state = state();
+        state.status = 127;
+        let prompt = Prompt::new(r"\u:\W [\?] \$ ", &state);
+        assert_eq!(prompt.plain, "alice:project [127] $ ");
+    }
+
+    #[test]
+    fn failed_status() {
+        let mut state = state();
+        assert_eq!(Prompt::new(r"\S$ ", &state).plain, "$ ");
+        state.set_status(vec![ExitStatus::SUCCESS, ExitStatus::signaled(11, true)]);
+        let prompt = Prompt::new(r"\S$ ", &state);
+        assert_eq!(prompt.plain, " [139 SIGSEGV (core dumped)]$ ");
+        assert_ne!(prompt.styled, prompt.plain);
+    }
+
+    #[test]
+    fn style_markup() {
+        let prompt = Prompt::new("{bold red}x{reset}y", &state());
+        assert_eq!(prompt.plain, "xy");
+        assert_ne!(prompt.styled, prompt.plain);
+    }
+
+    #[test]
+    fn unknown_markup_is_literal() {
+        let prompt = Prompt::new("{nope}{unclosed", &state());
+        assert_eq!(prompt.plain, "{nope}{unclosed");
+    }
+}
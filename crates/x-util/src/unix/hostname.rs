@@ -0,0 +1,11 @@
+use std::ffi::CStr;
+
+pub fn hostname() -> String {
+    let mut buf = [0 as libc::c_char; 256];
+    unsafe {
+        if libc::gethostname(buf.as_mut_ptr(), buf.len()) != 0 {
+            return String::new();
+        }
+        CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned()
+    }
+}
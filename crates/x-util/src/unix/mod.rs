@@ -1,3 +1,11 @@
+mod access;
+mod hostname;
+mod signal;
+mod time;
 mod whoami;
 
+pub use access::*;
+pub use hostname::*;
+pub use signal::*;
+pub use time::*;
 pub use whoami::*;
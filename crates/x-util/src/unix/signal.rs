@@ -0,0 +1,192 @@
+use std::io;
+use std::mem::zeroed;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus};
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Signals by name, without the `SIG` prefix.
+pub const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// The signals typed at a terminal, which the shell leaves to the program
+/// it waits for.
+const TERMINAL_SIGNALS: [i32; 3] = [libc::SIGINT, libc::SIGQUIT, libc::SIGTSTP];
+
+/// Caught signals that have yet to be handled, one bit per number.
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn record(signal: libc::c_int) {
+    PENDING.fetch_or(1 << signal, Ordering::SeqCst);
+}
+
+/// Does nothing, which ignores a signal without the program started next
+/// ignoring it too, as `exec` sets caught signals back to their default.
+extern "C" fn disregard(_: libc::c_int) {}
+
+/// Install `handler` for `signal`, returning what it replaced.
+fn install(signal: i32, handler: libc::sighandler_t) -> io::Result<libc::sigaction> {
+    unsafe {
+        let mut action: libc::sigaction = zeroed();
+        action.sa_sigaction = handler;
+        // reads of a program's output go on when a caught signal arrives
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut old: libc::sigaction = zeroed();
+        if libc::sigaction(signal, &action, &mut old) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(old)
+    }
+}
+
+fn restore(signal: i32, old: &libc::sigaction) {
+    unsafe {
+        libc::sigaction(signal, old, null_mut());
+    }
+}
+
+/// What happens when a signal arrives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Disposition {
+    Default,
+    Ignore,
+    /// Remember it for [`take_pending`].
+    Catch,
+}
+
+/// Handle `signal` as `disposition` says.
+pub fn set_disposition(signal: i32, disposition: Disposition) -> io::Result<()> {
+    let handler = match disposition {
+        Disposition::Default => libc::SIG_DFL,
+        Disposition::Ignore => libc::SIG_IGN,
+        Disposition::Catch => record as extern "C" fn(libc::c_int) as libc::sighandler_t,
+    };
+    if !(1..64).contains(&signal) {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+    install(signal, handler).map(drop)
+}
+
+/// The caught signals that arrived since the last call, in order of
+/// number.
+pub fn take_pending() -> Vec<i32> {
+    let pending = PENDING.swap(0, Ordering::SeqCst);
+    (1..64).filter(|signal| pending & (1 << signal) != 0).collect()
+}
+
+/// Whether the shell's stdin is a terminal it has the foreground of.
+pub fn owns_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() }
+}
+
+/// A program the shell waits for. With `terminal`, it runs in a process
+/// group of its own that has the terminal, so what is typed there, like
+/// Ctrl-C, goes to it alone. Until this is dropped the shell disregards the
+/// terminal's signals, except the ones a trap catches.
+pub struct Foreground {
+    terminal: bool,
+    /// The signals disregarded, with what they did before.
+    disregarded: Vec<(i32, libc::sigaction)>,
+}
+
+impl Foreground {
+    pub fn new(terminal: bool) -> Foreground {
+        let mut disregarded = vec![];
+        for signal in TERMINAL_SIGNALS {
+            let Ok(old) = install(signal, disregard as extern "C" fn(libc::c_int) as libc::sighandler_t) else {
+                continue;
+            };
+            if old.sa_sigaction == libc::SIG_DFL {
+                disregarded.push((signal, old));
+            } else {
+                // ignored or trapped already
+                restore(signal, &old);
+            }
+        }
+        Foreground { terminal, disregarded }
+    }
+
+    /// Have `command` start in a process group of its own, when it gets the
+    /// terminal.
+    pub fn prepare(&self, command: &mut Command) {
+        if self.terminal {
+            command.process_group(0);
+        }
+    }
+
+    /// Hand the terminal to `child`.
+    pub fn spawned(&self, child: &Child) {
+        if self.terminal {
+            give_terminal(child.id() as libc::pid_t);
+        }
+    }
+
+    /// Wait for `child` to exit. A program that stops, as one does on
+    /// Ctrl-Z or when it used the terminal before it was handed over, is
+    /// continued: there is no job control to resume it later.
+    pub fn wait(&self, child: &mut Child) -> io::Result<ExitStatus> {
+        let pid = child.id() as libc::pid_t;
+        loop {
+            let mut status = 0;
+            if unsafe { libc::waitpid(pid, &mut status, libc::WUNTRACED) } < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e);
+            }
+            if !libc::WIFSTOPPED(status) {
+                return Ok(ExitStatus::from_raw(status));
+            }
+            unsafe {
+                libc::kill(pid, libc::SIGCONT);
+            }
+        }
+    }
+}
+
+impl Drop for Foreground {
+    fn drop(&mut self) {
+        if self.terminal {
+            give_terminal(unsafe { libc::getpgrp() });
+        }
+        for (signal, old) in &self.disregarded {
+            restore(*signal, old);
+        }
+    }
+}
+
+/// Make `group` the foreground of the terminal. `SIGTTOU`, which doing so
+/// from the background raises, is ignored meanwhile.
+fn give_terminal(group: libc::pid_t) {
+    let old = install(libc::SIGTTOU, libc::SIG_IGN);
+    unsafe {
+        libc::tcsetpgrp(libc::STDIN_FILENO, group);
+    }
+    if let Ok(old) = old {
+        restore(libc::SIGTTOU, &old);
+    }
+}
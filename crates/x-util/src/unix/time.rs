@@ -0,0 +1,12 @@
+use std::mem::MaybeUninit;
+
+/// Local wall clock time as `(hour, minute, second)`.
+pub fn local_time() -> (u32, u32, u32) {
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm = MaybeUninit::<libc::tm>::zeroed();
+        libc::localtime_r(&now, tm.as_mut_ptr());
+        let tm = tm.assume_init();
+        (tm.tm_hour as u32, tm.tm_min as u32, tm.tm_sec as u32)
+    }
+}
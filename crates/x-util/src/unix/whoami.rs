@@ -9,3 +9,16 @@ pub fn whoami() -> &'static str {
         CStr::from_ptr(pwd.pw_name).to_str().unwrap()
     }
 }
+
+/// The home directory of `user`, from the password database.
+pub fn user_home(user: &str) -> Option<std::path::PathBuf> {
+    let name = std::ffi::CString::new(user).ok()?;
+    unsafe {
+        let pwd_pointer: *mut passwd = libc::getpwnam(name.as_ptr());
+        if pwd_pointer.is_null() {
+            return None;
+        }
+        let dir = CStr::from_ptr((*pwd_pointer).pw_dir).to_str().ok()?;
+        Some(dir.into())
+    }
+}
@@ -0,0 +1,16 @@
+use std::fs;
+use std::path::Path;
+
+/// What a file may be opened for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+/// Whether `path` may be used for `access`, which only a read-only file
+/// refuses here, and only writing.
+pub fn accessible(path: &Path, access: Access) -> bool {
+    fs::metadata(path).is_ok_and(|meta| access != Access::Write || !meta.permissions().readonly())
+}
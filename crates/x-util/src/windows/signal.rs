@@ -0,0 +1,42 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+
+/// Signals by name, without the `SIG` prefix. There are none to trap here.
+pub const SIGNALS: &[(&str, i32)] = &[];
+
+/// What happens when a signal arrives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Disposition {
+    Default,
+    Ignore,
+    Catch,
+}
+
+pub fn set_disposition(_: i32, _: Disposition) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+pub fn take_pending() -> Vec<i32> {
+    vec![]
+}
+
+pub fn owns_terminal() -> bool {
+    false
+}
+
+/// A program the shell waits for, which the console signals reach anyway.
+pub struct Foreground;
+
+impl Foreground {
+    pub fn new(_: bool) -> Foreground {
+        Foreground
+    }
+
+    pub fn prepare(&self, _: &mut Command) {}
+
+    pub fn spawned(&self, _: &Child) {}
+
+    pub fn wait(&self, child: &mut Child) -> io::Result<ExitStatus> {
+        child.wait()
+    }
+}
@@ -0,0 +1,14 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Wall clock time as `(hour, minute, second)`, in UTC.
+pub fn local_time() -> (u32, u32, u32) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    (
+        (secs / 3600 % 24) as u32,
+        (secs / 60 % 60) as u32,
+        (secs % 60) as u32,
+    )
+}
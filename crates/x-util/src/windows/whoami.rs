@@ -1 +1,6 @@
 
+/// The home directory of `user`, which only the current user's is known
+/// of here.
+pub fn user_home(_: &str) -> Option<std::path::PathBuf> {
+    None
+}
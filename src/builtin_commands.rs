@@ -1,18 +1,37 @@
+mod help;
+mod printf;
+mod test;
+mod trap;
+
 use std::fmt::Debug;
+use std::fs;
+use std::io::Write;
 
 use x_engine::ShellState;
 use x_engine::Result;
 use x_engine::Command;
+use x_engine::ExitStatus;
+use x_engine::Io;
+use x_engine::Theme;
+use x_engine::run_script_with;
+use x_parser::{Lexer, Parser};
+use x_protocol::example::Example;
+use x_protocol::io_message;
+
+use printf::{escape, printf};
+use test::test;
+use trap::trap;
 
 #[derive(Clone)]
 pub struct BuiltinCommand<'a, F> {
     name: &'a str,
     usage: &'a str,
+    examples: Vec<Example>,
     func: Box<F>
 }
 
 impl<F> Command for BuiltinCommand<'static, F>
-where F: Fn(Vec<String>, &mut ShellState) -> Result<()> + Clone + 'static 
+where F: Fn(Vec<String>, &mut ShellState, &mut Io) -> Result<ExitStatus> + Clone + 'static 
 {
     fn get_name(&self) -> &str {
         self.name
@@ -22,8 +41,12 @@ where F: Fn(Vec<String>, &mut ShellState) -> Result<()> + Clone + 'static
         self.usage
     }
 
-    fn run(&self, state: &mut ShellState, args: Vec<String>) -> Result<()> {
-        (self.func)(args, state)
+    fn get_example(&self) -> Vec<Example> {
+        self.examples.clone()
+    }
+
+    fn run(&self, state: &mut ShellState, args: Vec<String>, io: &mut Io) -> Result<ExitStatus> {
+        (self.func)(args, state, io)
     }
 
     fn is_builtin(&self) -> bool {
@@ -32,7 +55,7 @@ where F: Fn(Vec<String>, &mut ShellState) -> Result<()> + Clone + 'static
 }
 
 impl<F> Debug for BuiltinCommand<'static, F>
-where F: Fn(Vec<String>, &mut ShellState) -> Result<()>
+where F: Fn(Vec<String>, &mut ShellState, &mut Io) -> Result<ExitStatus>
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Command name: {}\nUsage: {}", self.name, self.usage)
@@ -40,19 +63,23 @@ where F: Fn(Vec<String>, &mut ShellState) -> Result<()>
 }
 
 impl<'a, F> BuiltinCommand<'a, F>
-where F: Fn(Vec<String>, &mut ShellState) -> Result<()> + Clone + 'static
+where F: Fn(Vec<String>, &mut ShellState, &mut Io) -> Result<ExitStatus> + Clone + 'static
 {
-    pub fn new(name: &'a str, usage: &'a str, func: Box<F>) -> Self
+    pub fn new(name: &'a str, usage: &'a str, examples: Vec<Example>, func: Box<F>) -> Self
     {
-        BuiltinCommand { name, usage, func }
+        BuiltinCommand { name, usage, examples, func }
     }
 }
 
 macro_rules! create_command {
-    ($commands: ident, $name: literal, $usage: literal, $func: expr) => {
-        let command = BuiltinCommand::new($name, $usage, Box::new($func));
+    ($commands: ident, $name: literal, $usage: literal, [$(($example: literal, $description: literal)),* $(,)?], $func: expr) => {
+        let examples = vec![$(Example { example: $example, description: $description }),*];
+        let command = BuiltinCommand::new($name, $usage, examples, Box::new($func));
         $commands.push(Box::new(command));
     };
+    ($commands: ident, $name: literal, $usage: literal, $func: expr) => {
+        create_command!($commands, $name, $usage, [], $func);
+    };
 }
 
 pub fn get_commands() -> Vec<Box<dyn Command>> {
@@ -61,12 +88,640 @@ pub fn get_commands() -> Vec<Box<dyn Command>> {
     create_command!(
         commands,
         "exit",
-        "",
-        |_, state: &mut ShellState| {
+        "exit [N]",
+        [("exit 1", "exit the shell, failing with status 1")],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            // the status of the last command, unless one is given
+            let code = match args.as_slice() {
+                [] => state.status,
+                [code] => match code.parse::<i32>() {
+                    // only the low byte reaches the parent, as with `$?`
+                    Ok(code) => code.rem_euclid(256),
+                    Err(_) => {
+                        writeln!(io.stderr, "exit: {}: numeric argument required", code)?;
+                        2
+                    }
+                },
+                _ => {
+                    writeln!(io.stderr, "exit: too many arguments")?;
+                    return Ok(ExitStatus::FAILURE);
+                }
+            };
             state.exit();
-            Ok(())
+            Ok(ExitStatus::code(code))
+        }
+    );
+    create_command!(
+        commands,
+        "theme",
+        "theme [dark | light | <file.toml>]",
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            match args.first() {
+                Some(name) => match Theme::find(name) {
+                    Ok(theme) => state.theme = theme,
+                    Err(e) => {
+                        writeln!(io.stderr, "theme: {}", e)?;
+                        return Ok(ExitStatus::FAILURE);
+                    }
+                },
+                None => {
+                    writeln!(io.stdout, "current: {}", state.theme.name)?;
+                    writeln!(io.stdout, "builtin: {}", Theme::BUILTIN.join(", "))?;
+                }
+            }
+            Ok(ExitStatus::SUCCESS)
+        }
+    );
+    create_command!(
+        commands,
+        "echo",
+        "echo [-neE] [STRING]...",
+        [
+            ("echo hello world", "print `hello world` and a newline"),
+            ("echo -n ready", "print without the trailing newline"),
+            ("echo -e 'a\\tb'", "interpret backslash escapes"),
+        ],
+        |args: Vec<String>, _: &mut ShellState, io: &mut Io| {
+            let (mut newline, mut escapes) = (true, false);
+            // leading arguments made only of option letters are options
+            let options = args
+                .iter()
+                .take_while(|arg| arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| "neE".contains(c)))
+                .count();
+            for arg in &args[..options] {
+                for option in arg[1..].chars() {
+                    match option {
+                        'n' => newline = false,
+                        'e' => escapes = true,
+                        _ => escapes = false,
+                    }
+                }
+            }
+
+            let mut text = args[options..].join(" ");
+            if escapes {
+                let chars: Vec<char> = text.chars().collect();
+                let (mut escaped, mut i) = (String::new(), 0);
+                while i < chars.len() {
+                    if chars[i] != '\\' {
+                        escaped.push(chars[i]);
+                        i += 1;
+                        continue;
+                    }
+                    let (piece, stop) = escape(&chars, &mut i, true);
+                    escaped.push_str(&piece);
+                    if stop {
+                        newline = false;
+                        break;
+                    }
+                }
+                text = escaped;
+            }
+            if newline {
+                text.push('\n');
+            }
+            io.stdout.write_all(text.as_bytes())?;
+            Ok(ExitStatus::SUCCESS)
+        }
+    );
+    create_command!(
+        commands,
+        "printf",
+        "printf FORMAT [ARGUMENT]...",
+        [
+            ("printf '%s\\n' a b", "print each argument on its own line"),
+            ("printf '%5.2f|%-4d|\\n' 3.14159 7", "pad and round numbers"),
+            ("printf '%x %o\\n' 255 8", "print numbers in hex and octal"),
+        ],
+        |args: Vec<String>, _: &mut ShellState, io: &mut Io| {
+            let Some((format, args)) = args.split_first() else {
+                writeln!(io.stderr, "printf: usage: printf FORMAT [ARGUMENT]...")?;
+                return Ok(ExitStatus::code(2));
+            };
+            let printed = printf(format, args);
+            io.stdout.write_all(printed.output.as_bytes())?;
+            for error in &printed.errors {
+                writeln!(io.stderr, "printf: {}", error)?;
+            }
+            Ok(if printed.errors.is_empty() { ExitStatus::SUCCESS } else { ExitStatus::FAILURE })
+        }
+    );
+    create_command!(
+        commands,
+        "test",
+        "test EXPRESSION",
+        [
+            ("test -f Cargo.toml", "succeed when the file exists"),
+            ("test \"$a\" = yes -o $n -gt 3", "compare strings and numbers"),
+            ("test ! -d build", "succeed when there is no `build` directory"),
+        ],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            condition("test", &args, state, io)
+        }
+    );
+    create_command!(
+        commands,
+        "[",
+        "[ EXPRESSION ]",
+        [("[ -n \"$name\" ]", "succeed when `name` is not empty")],
+        |mut args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            if args.pop().as_deref() != Some("]") {
+                writeln!(io.stderr, "[: missing `]`")?;
+                return Ok(ExitStatus::code(2));
+            }
+            condition("[", &args, state, io)
+        }
+    );
+    create_command!(
+        commands,
+        "true",
+        "true",
+        |_, _: &mut ShellState, _: &mut Io| Ok(ExitStatus::SUCCESS)
+    );
+    create_command!(
+        commands,
+        "false",
+        "false",
+        |_, _: &mut ShellState, _: &mut Io| Ok(ExitStatus::FAILURE)
+    );
+    create_command!(
+        commands,
+        "type",
+        "type NAME...",
+        [("type ls ll", "tell whether each name is an alias, function, builtin or program")],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            let mut status = ExitStatus::SUCCESS;
+            for name in &args {
+                match resolve(state, name) {
+                    Some(Resolved::Alias(value)) => writeln!(io.stdout, "{} is aliased to `{}`", name, value)?,
+                    Some(Resolved::Function) => writeln!(io.stdout, "{} is a function", name)?,
+                    Some(Resolved::Builtin) => writeln!(io.stdout, "{} is a shell builtin", name)?,
+                    Some(Resolved::Program(path)) => writeln!(io.stdout, "{} is {}", name, path)?,
+                    None => {
+                        writeln!(io.stderr, "type: {}: not found", name)?;
+                        status = ExitStatus::FAILURE;
+                    }
+                }
+            }
+            Ok(status)
+        }
+    );
+    create_command!(
+        commands,
+        "which",
+        "which NAME...",
+        [("which git", "print the path of the program `git` runs")],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            let mut status = ExitStatus::SUCCESS;
+            for name in &args {
+                match resolve(state, name) {
+                    Some(Resolved::Alias(value)) => writeln!(io.stdout, "{}: aliased to {}", name, value)?,
+                    Some(Resolved::Function) => writeln!(io.stdout, "{}: function", name)?,
+                    Some(Resolved::Builtin) => writeln!(io.stdout, "{}: shell builtin", name)?,
+                    Some(Resolved::Program(path)) => writeln!(io.stdout, "{}", path)?,
+                    None => {
+                        writeln!(io.stderr, "{} not found", name)?;
+                        status = ExitStatus::FAILURE;
+                    }
+                }
+            }
+            Ok(status)
+        }
+    );
+    create_command!(
+        commands,
+        "source",
+        "source FILE",
+        [("source ~/.xshrc", "run a script in the current shell, keeping its functions")],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            source("source", &args, state, io)
+        }
+    );
+    create_command!(
+        commands,
+        ".",
+        ". FILE",
+        [(". ./env.xsh", "run a script in the current shell")],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            source(".", &args, state, io)
+        }
+    );
+    create_command!(
+        commands,
+        "alias",
+        "alias [NAME[=VALUE]]...",
+        [
+            ("alias ll='ls -la'", "make `ll` run `ls -la`"),
+            ("alias sudo='sudo '", "also expand the alias after `sudo`"),
+            ("alias", "list the aliases"),
+        ],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            if args.is_empty() {
+                let mut aliases: Vec<(&String, &String)> = state.aliases.iter().collect();
+                aliases.sort();
+                for (name, value) in aliases {
+                    writeln!(io.stdout, "alias {}={}", name, single_quote(value))?;
+                }
+                return Ok(ExitStatus::SUCCESS);
+            }
+            let mut status = ExitStatus::SUCCESS;
+            for arg in &args {
+                let Some((name, value)) = arg.split_once('=') else {
+                    match state.aliases.get(arg) {
+                        Some(value) => writeln!(io.stdout, "alias {}={}", arg, single_quote(value))?,
+                        None => {
+                            writeln!(io.stderr, "alias: {}: not found", arg)?;
+                            status = ExitStatus::FAILURE;
+                        }
+                    }
+                    continue;
+                };
+                if name.is_empty() || name.contains(|c: char| c.is_whitespace() || "/$`'\"\\;|&<>()".contains(c)) {
+                    writeln!(io.stderr, "alias: `{}`: invalid alias name", name)?;
+                    status = ExitStatus::FAILURE;
+                    continue;
+                }
+                if !Parser::new(Lexer::new(value.chars())).parse_tree().errors.is_empty() {
+                    writeln!(io.stderr, "alias: {}: syntax error in `{}`", name, value)?;
+                    status = ExitStatus::FAILURE;
+                    continue;
+                }
+                state.aliases.insert(name.into(), value.into());
+            }
+            Ok(status)
+        }
+    );
+    create_command!(
+        commands,
+        "unalias",
+        "unalias -a | NAME...",
+        [("unalias ll", "remove the alias `ll`"), ("unalias -a", "remove every alias")],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            if args.first().is_some_and(|arg| arg == "-a") {
+                state.aliases.clear();
+                return Ok(ExitStatus::SUCCESS);
+            }
+            let mut status = ExitStatus::SUCCESS;
+            for name in &args {
+                if state.aliases.remove(name).is_none() {
+                    writeln!(io.stderr, "unalias: {}: not found", name)?;
+                    status = ExitStatus::FAILURE;
+                }
+            }
+            Ok(status)
+        }
+    );
+    create_command!(
+        commands,
+        "help",
+        "help [NAME]",
+        [
+            ("help", "list the builtin commands"),
+            ("help printf", "show the usage and examples of `printf`"),
+            ("help grep", "summarize a program from its man page and `--help`"),
+        ],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            let Some(name) = args.first() else {
+                let text = help::list(state.commands.added(), io);
+                io.stdout.write_all(text.as_bytes())?;
+                return Ok(ExitStatus::SUCCESS);
+            };
+            if state.functions.contains_key(name) {
+                writeln!(io.stdout, "{}: function defined with `def`", name)?;
+                return Ok(ExitStatus::SUCCESS);
+            }
+            let Some(mut command) = state.command(name) else {
+                writeln!(io.stderr, "help: no command named `{}`", name)?;
+                return Ok(ExitStatus::FAILURE);
+            };
+            // programs are asked once, when their help is first wanted
+            if command.get_usage().is_empty() {
+                let usage = command.get_path().and_then(|path| help::external_usage(name, path));
+                if let Some(usage) = usage {
+                    state.commands.edit_usage(name, usage.clone());
+                    command.edit_usage(usage);
+                }
+            }
+            let text = help::describe(command.as_ref(), io);
+            io.stdout.write_all(text.as_bytes())?;
+            Ok(ExitStatus::SUCCESS)
+        }
+    );
+    create_command!(
+        commands,
+        "hash",
+        "hash [-lr] [-p PATH NAME] [NAME]...",
+        [
+            ("hash", "list the programs found so far"),
+            ("hash -r", "forget them, to search `PATH` again"),
+            ("hash -p /opt/bin/python3 python", "run `/opt/bin/python3` for `python`"),
+        ],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| {
+            let mut args = args.as_slice();
+            let (mut list, mut cleared, mut program) = (false, false, None);
+            while let Some(option) = args.first().filter(|arg| arg.starts_with('-') && arg.len() > 1) {
+                match option.as_str() {
+                    "-r" => {
+                        state.commands.forget();
+                        cleared = true;
+                    }
+                    "-l" => list = true,
+                    "-p" if args.len() > 1 => {
+                        program = Some(args[1].clone());
+                        args = &args[1..];
+                    }
+                    "--" => {
+                        args = &args[1..];
+                        break;
+                    }
+                    option => {
+                        writeln!(io.stderr, "hash: {}: invalid option", option)?;
+                        writeln!(io.stderr, "hash: usage: hash [-lr] [-p PATH NAME] [NAME]...")?;
+                        return Ok(ExitStatus::code(2));
+                    }
+                }
+                args = &args[1..];
+            }
+
+            if let Some(program) = program {
+                let Some(name) = args.first() else {
+                    writeln!(io.stderr, "hash: -p: NAME expected")?;
+                    return Ok(ExitStatus::code(2));
+                };
+                state.commands.remember(name, state.resolve_path(&program));
+                return Ok(ExitStatus::SUCCESS);
+            }
+            if args.is_empty() && cleared {
+                return Ok(ExitStatus::SUCCESS);
+            }
+            if args.is_empty() {
+                let hashed = state.commands.hashed();
+                if hashed.is_empty() && !list {
+                    writeln!(io.stdout, "hash: table empty")?;
+                }
+                for (name, path) in hashed {
+                    if list {
+                        writeln!(io.stdout, "hash -p {} {}", single_quote(&path.display().to_string()), name)?;
+                    } else {
+                        writeln!(io.stdout, "{}\t{}", name, path.display())?;
+                    }
+                }
+                return Ok(ExitStatus::SUCCESS);
+            }
+
+            let mut status = ExitStatus::SUCCESS;
+            let path = state.search_path();
+            for name in args {
+                // builtins and functions are not in the table
+                let known = state.functions.contains_key(name.as_str())
+                    || state.commands.added().any(|command| command.get_name() == name);
+                if !known && state.commands.program(name, &path).is_none() {
+                    writeln!(io.stderr, "hash: {}: not found", name)?;
+                    status = ExitStatus::FAILURE;
+                }
+            }
+            Ok(status)
         }
     );
+    create_command!(
+        commands,
+        "trap",
+        "trap [-lp] [[ACTION] SIGNAL...]",
+        [
+            ("trap 'rm -f /tmp/lock' EXIT", "remove the lock file when the shell exits"),
+            ("trap 'echo interrupted' INT", "run a command on Ctrl-C instead of stopping"),
+            ("trap '' TERM", "ignore SIGTERM"),
+            ("trap - INT", "do what SIGINT does by default again"),
+        ],
+        |args: Vec<String>, state: &mut ShellState, io: &mut Io| trap(&args, state, io)
+    );
     commands
 }
 
+/// `text` in single quotes, as the shell reads it back.
+fn single_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Run `test` or `[`: 0 when the expression holds, 1 when not, 2 when it
+/// is not one.
+fn condition(name: &str, args: &[String], state: &ShellState, io: &mut Io) -> Result<ExitStatus> {
+    match test(state, io, args) {
+        Ok(true) => Ok(ExitStatus::SUCCESS),
+        Ok(false) => Ok(ExitStatus::FAILURE),
+        Err(e) => {
+            writeln!(io.stderr, "{}: {}", name, e)?;
+            Ok(ExitStatus::code(2))
+        }
+    }
+}
+
+/// What a command name runs, looked up in the order the shell does.
+enum Resolved {
+    Alias(String),
+    Function,
+    Builtin,
+    Program(String),
+}
+
+fn resolve(state: &ShellState, name: &str) -> Option<Resolved> {
+    if let Some(value) = state.aliases.get(name) {
+        return Some(Resolved::Alias(value.clone()));
+    }
+    if state.functions.contains_key(name) {
+        return Some(Resolved::Function);
+    }
+    let command = state.command(name)?;
+    match command.get_path() {
+        Some(path) if !command.is_builtin() => Some(Resolved::Program(path.display().to_string())),
+        _ => Some(Resolved::Builtin),
+    }
+}
+
+/// Run the script `args[0]` in the current shell.
+fn source(name: &str, args: &[String], state: &mut ShellState, io: &mut Io) -> Result<ExitStatus> {
+    let Some(file) = args.first() else {
+        writeln!(io.stderr, "{}: usage: {} FILE", name, name)?;
+        return Ok(ExitStatus::code(2));
+    };
+    match fs::read_to_string(state.resolve_path(file)) {
+        Ok(script) => Ok(ExitStatus::code(run_script_with(state, &script, file, io))),
+        Err(e) => {
+            writeln!(io.stderr, "{}: {}: {}", name, file, io_message(&e))?;
+            Ok(ExitStatus::FAILURE)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod builtin_commands_test {
+    use std::path::PathBuf;
+
+    use x_engine::{run_script_with, Io, ShellState};
+    use x_protocol::io::Sink;
+    use x_protocol::output::Output;
+
+    use super::get_commands;
+
+    fn state() -> ShellState {
+        let mut state = ShellState::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")), "user".into());
+        state.init_commands(get_commands());
+        state.add_env("PATH".into(), "/bin:/usr/bin".into());
+        state
+    }
+
+    /// Run `source` in `state`, giving what it printed on stdout and
+    /// stderr, and its status.
+    fn run_in(state: &mut ShellState, source: &str) -> (String, String, i32) {
+        let mut io = Io {
+            stderr: Sink::Capture(Output::default()),
+            ..Io::capture()
+        };
+        let status = run_script_with(state, source, "-c", &mut io);
+        (io.stdout.take().string, io.stderr.take().string, status)
+    }
+
+    fn run(source: &str) -> (String, String, i32) {
+        run_in(&mut state(), source)
+    }
+
+    #[test]
+    fn echo() {
+        assert_eq!(run("echo a  b").0, "a b\n");
+        assert_eq!(run("echo -n a; echo -e 'b\\tc\\cd'; echo -x -nx").0, "ab\tc-x -nx\n");
+        assert_eq!(run("echo -nE 'a\\tb'").0, "a\\tb");
+    }
+
+    #[test]
+    fn printf_status() {
+        assert_eq!(run("printf '%s\\n' a b"), ("a\nb\n".into(), String::new(), 0));
+        assert_eq!(run("printf '%d' 99999999999999999999").1, "printf: 99999999999999999999: Result too large\n");
+        assert_eq!(run("printf '%d' 99999999999999999999").2, 1);
+        assert_eq!(run("printf").2, 2);
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(run("test -f Cargo.toml").2, 0);
+        assert_eq!(run("test -d Cargo.toml").2, 1);
+        assert_eq!(run("[ a = a ]").2, 0);
+        assert_eq!(run("[ a = b ]").2, 1);
+        assert_eq!(run("test 1 -eq x"), (String::new(), "test: `x`: integer expected\n".into(), 2));
+        assert_eq!(run("[ a = a"), (String::new(), "[: missing `]`\n".into(), 2));
+        // a pipe, whatever the shell writes to
+        assert_eq!(run("test -t 1 | cat; echo $PIPESTATUS").0, "1 0\n");
+    }
+
+    #[test]
+    fn type_and_which() {
+        let source = "alias ll='ls -l'; def f [] { true; } type ll f echo ls nope";
+        let (stdout, stderr, status) = run(source);
+        let ls = run("which ls").0;
+        assert!(ls.ends_with("/ls\n"));
+        assert_eq!(
+            stdout,
+            format!("ll is aliased to `ls -l`\nf is a function\necho is a shell builtin\nls is {}", ls)
+        );
+        assert_eq!((stderr.as_str(), status), ("type: nope: not found\n", 1));
+
+        let (stdout, stderr, status) = run("def f [] { true; } which f echo nope");
+        assert_eq!(stdout, "f: function\necho: shell builtin\n");
+        assert_eq!((stderr.as_str(), status), ("nope not found\n", 1));
+    }
+
+    #[test]
+    fn source() {
+        let dir = std::env::temp_dir().join(format!("xshell-source-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.xsh"), "def hello [] { echo hello; }\n").unwrap();
+        let mut state = state();
+        state.path = Some(dir.clone());
+
+        assert_eq!(run_in(&mut state, "source lib.xsh; hello").0, "hello\n");
+        assert_eq!(run_in(&mut state, ". ./lib.xsh; echo $?").0, "0\n");
+        let (_, stderr, status) = run_in(&mut state, "source missing.xsh");
+        assert_eq!((stderr.as_str(), status), ("source: missing.xsh: No such file or directory\n", 1));
+        assert_eq!(run_in(&mut state, ".").2, 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn exit_stops() {
+        assert_eq!(run("exit; echo after").0, "");
+        assert_eq!(run("def f [] { exit; echo in; } f; echo after").0, "");
+        // it only ends the substitution
+        assert_eq!(run("echo x$(exit; echo in); echo after").0, "x\nafter\n");
+
+
+        assert_eq!(run("exit 3; echo after"), (String::new(), String::new(), 3));
+        assert_eq!(run("false; exit").2, 1);
+        assert_eq!(run("exit 257").2, 1);
+        assert_eq!(run("exit -1").2, 255);
+        assert_eq!(run("exit abc"), (String::new(), "exit: abc: numeric argument required\n".into(), 2));
+        assert_eq!(run("exit 1 2; echo after"), ("after\n".into(), "exit: too many arguments\n".into(), 0));
+
+        let dir = std::env::temp_dir().join(format!("xshell-exit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.xsh"), "exit\necho in\n").unwrap();
+        let mut state = state();
+        state.path = Some(dir.clone());
+        assert_eq!(run_in(&mut state, "source lib.xsh; echo after").0, "");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn hash_options() {
+        assert_eq!(run("hash"), ("hash: table empty\n".into(), String::new(), 0));
+        assert_eq!(run("hash ls; hash").0, "ls\t/bin/ls\n");
+        assert_eq!(run("hash ls; hash -l").0, "hash -p '/bin/ls' ls\n");
+        assert_eq!(run("hash ls; hash -r; hash").0, "hash: table empty\n");
+        assert_eq!(run("hash -r ls; hash").0, "ls\t/bin/ls\n");
+        assert_eq!(run("hash -p /opt/bin/python3 python; hash").0, "python\t/opt/bin/python3\n");
+        assert_eq!(run("hash -l").0, "");
+
+        assert_eq!(run("hash -- -r"), (String::new(), "hash: -r: not found\n".into(), 1));
+        assert_eq!(run("hash -p /opt/bin/python3"), (String::new(), "hash: -p: NAME expected\n".into(), 2));
+        let (stdout, stderr, status) = run("hash -x ls");
+        assert_eq!(stdout, "");
+        assert_eq!(stderr, "hash: -x: invalid option\nhash: usage: hash [-lr] [-p PATH NAME] [NAME]...\n");
+        assert_eq!(status, 2);
+    }
+
+    #[test]
+    fn alias_and_unalias() {
+        let source = "alias ll='ls -l' la='ls -a'; alias ll; alias";
+        assert_eq!(run(source).0, "alias ll='ls -l'\nalias la='ls -a'\nalias ll='ls -l'\n");
+        assert_eq!(run("alias nope"), (String::new(), "alias: nope: not found\n".into(), 1));
+        assert_eq!(run("alias 'a b=ls'"), (String::new(), "alias: `a b`: invalid alias name\n".into(), 1));
+        assert_eq!(run("alias x='ls |'"), (String::new(), "alias: x: syntax error in `ls |`\n".into(), 1));
+        // the valid ones are defined anyway
+        assert_eq!(run("alias =ls ll=ls; alias").0, "alias ll='ls'\n");
+
+        let mut state = state();
+        run_in(&mut state, "alias ll='ls -l' la='ls -a'");
+        assert_eq!(run_in(&mut state, "unalias ll; alias"), ("alias la='ls -a'\n".into(), String::new(), 0));
+        assert_eq!(run_in(&mut state, "unalias ll"), (String::new(), "unalias: ll: not found\n".into(), 1));
+        assert_eq!(run_in(&mut state, "unalias -a; alias"), (String::new(), String::new(), 0));
+    }
+
+    #[test]
+    fn trap_arguments() {
+        let mut state = state();
+        let set = "trap 'echo bye' EXIT; trap -- 'echo usr1' SIGUSR1";
+        assert_eq!(run_in(&mut state, set), (String::new(), String::new(), 0));
+        let traps = "trap -- 'echo bye' EXIT\ntrap -- 'echo usr1' SIGUSR1\n";
+        assert_eq!(run_in(&mut state, "trap").0, traps);
+        assert_eq!(run_in(&mut state, "trap -p usr1").0, "trap -- 'echo usr1' SIGUSR1\n");
+        // a lone signal or a number first resets
+        assert_eq!(run_in(&mut state, "trap USR1; trap 0; trap"), (String::new(), String::new(), 0));
+        assert_eq!(run_in(&mut state, "trap 'echo x' 0; trap - EXIT; trap").0, "");
+
+        assert!(run("trap -l").0.contains(" 2) SIGINT\n"));
+        let invalid = "trap: NOPE: invalid signal specification\n";
+        assert_eq!(run("trap 'echo x' NOPE"), (String::new(), invalid.into(), 1));
+        assert_eq!(run("trap -p NOPE"), (String::new(), invalid.into(), 1));
+        assert_eq!(run("trap 'echo x' 99").1, "trap: 99: invalid signal specification\n");
+    }
+}
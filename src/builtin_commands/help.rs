@@ -0,0 +1,119 @@
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use std::process::{Command as Process, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use x_engine::{Command, Io};
+use x_protocol::crossterm::style::Stylize;
+use x_protocol::io::Sink;
+
+/// How long a program gets to print its `--help`, which some ignore.
+const HELP_TIMEOUT: Duration = Duration::from_secs(1);
+/// Lines of `--help` output kept when no usage paragraph stands out.
+const HELP_LINES: usize = 12;
+
+/// `text` in bold when `io` writes to a terminal.
+fn bold(io: &Io, text: &str) -> String {
+    if matches!(io.stdout, Sink::Stdout) && std::io::stdout().is_terminal() {
+        text.bold().to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// The builtins, one per line with their usage.
+pub fn list<'a>(commands: impl Iterator<Item = &'a dyn Command>, io: &Io) -> String {
+    let mut builtins: Vec<&dyn Command> = commands.filter(|command| command.is_builtin()).collect();
+    builtins.sort_by_key(|command| command.get_name());
+    let width = builtins.iter().map(|command| command.get_name().chars().count()).max().unwrap_or(0);
+
+    let mut text = format!("{}\n", bold(io, "Builtin commands:"));
+    for command in builtins {
+        let name = command.get_name();
+        let padding = " ".repeat(width - name.chars().count());
+        text.push_str(&format!("  {}{}  {}\n", bold(io, name), padding, command.get_usage()));
+    }
+    text.push_str("\nRun `help NAME` for the usage and examples of a command.\n");
+    text
+}
+
+/// Usage and examples of `command`.
+pub fn describe(command: &dyn Command, io: &Io) -> String {
+    let mut text = match command.get_usage() {
+        "" => format!("{}: no usage known\n", command.get_name()),
+        usage => format!("{}\n", usage.trim_end()),
+    };
+    let examples = command.get_example();
+    if !examples.is_empty() {
+        text.push_str(&format!("\n{}\n", bold(io, "Examples:")));
+        for example in examples {
+            text.push_str(&format!("  {}\n      {}\n", bold(io, example.example), example.description));
+        }
+    }
+    text
+}
+
+/// What the system says about the program at `path`: the summary line of
+/// its man page and the usage it prints for `--help`.
+pub fn external_usage(name: &str, path: &Path) -> Option<String> {
+    let summary = output(Path::new("man"), &["-f", name]).and_then(|whatis| {
+        let line = whatis.lines().next()?;
+        let (_, summary) = line.split_once(" - ")?;
+        Some(format!("{} - {}", name, summary.trim()))
+    });
+    let usage = output(path, &["--help"]).map(|help| usage_paragraph(&help)).filter(|usage| !usage.is_empty());
+    match (summary, usage) {
+        (Some(summary), Some(usage)) => Some(format!("{}\n\n{}", summary, usage)),
+        (summary, usage) => summary.or(usage),
+    }
+}
+
+/// The paragraph of `help` starting at its `usage:` line, or its first
+/// lines when there is none.
+fn usage_paragraph(help: &str) -> String {
+    let lines: Vec<&str> = help.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.trim_start().to_lowercase().starts_with("usage"));
+    let paragraph: Vec<&str> = match start {
+        Some(start) => lines[start..].iter().take_while(|line| !line.trim().is_empty()).copied().collect(),
+        None => lines.iter().skip_while(|line| line.trim().is_empty()).take(HELP_LINES).copied().collect(),
+    };
+    paragraph.join("\n").trim_end().to_string()
+}
+
+/// What `program` prints on stdout and stderr, if it exits in time.
+fn output(program: &Path, args: &[&str]) -> Option<String> {
+    let mut child = Process::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let read = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut bytes = vec![];
+            let _ = pipe.read_to_end(&mut bytes);
+            String::from_utf8_lossy(&bytes).into_owned()
+        })
+    };
+    let stdout = read(Box::new(child.stdout.take()?));
+    let stderr = read(Box::new(child.stderr.take()?));
+
+    let deadline = Instant::now() + HELP_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let text = stdout.join().ok()? + &stderr.join().ok()?;
+    (!text.trim().is_empty()).then_some(text)
+}
@@ -0,0 +1,476 @@
+/// What `printf` printed, with a message for each argument that was not a
+/// number where the format wanted one.
+pub struct Printed {
+    pub output: String,
+    pub errors: Vec<String>,
+}
+
+/// Format `args` the way POSIX `printf` does: the format is reused while
+/// arguments remain, and missing ones count as empty or zero.
+pub fn printf(format: &str, args: &[String]) -> Printed {
+    let format: Vec<char> = format.chars().collect();
+    let mut printer = Printer {
+        args,
+        next: 0,
+        printed: Printed {
+            output: String::new(),
+            errors: vec![],
+        },
+    };
+    loop {
+        let start = printer.next;
+        if printer.format(&format).is_err() || printer.next == start || printer.next >= args.len() {
+            break;
+        }
+    }
+    printer.printed
+}
+
+/// Reached `\c`, or an error, which ends all output.
+struct Stop;
+
+/// The widest a field may be padded to, or the most digits it may have,
+/// far more than any terminal shows and short of running out of memory.
+const MAX_FIELD: usize = 1 << 20;
+
+struct Printer<'a> {
+    args: &'a [String],
+    next: usize,
+    printed: Printed,
+}
+
+impl Printer<'_> {
+    fn format(&mut self, format: &[char]) -> Result<(), Stop> {
+        let mut i = 0;
+        while i < format.len() {
+            match format[i] {
+                '\\' => {
+                    let (text, stop) = escape(format, &mut i, false);
+                    self.printed.output.push_str(&text);
+                    if stop {
+                        return Err(Stop);
+                    }
+                }
+                '%' if format.get(i + 1) == Some(&'%') => {
+                    self.printed.output.push('%');
+                    i += 2;
+                }
+                '%' => {
+                    i += 1;
+                    self.conversion(format, &mut i)?;
+                }
+                c => {
+                    self.printed.output.push(c);
+                    i += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn arg(&mut self) -> &str {
+        let arg = self.args.get(self.next).map_or("", |arg| arg.as_str());
+        self.next += 1;
+        arg
+    }
+
+    /// A `*` width or precision, or digits written in the format. One
+    /// wider than [`MAX_FIELD`] is an error, which ends the output.
+    fn number(&mut self, format: &[char], i: &mut usize) -> Result<Option<usize>, Stop> {
+        let (text, number) = if format.get(*i) == Some(&'*') {
+            *i += 1;
+            let arg = self.arg().to_string();
+            let number = self.integer(&arg).max(0) as u64;
+            (arg, Some(number))
+        } else {
+            let start = *i;
+            while format.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+                *i += 1;
+            }
+            let digits: String = format[start..*i].iter().collect();
+            let number = (!digits.is_empty()).then(|| digits.parse().unwrap_or(u64::MAX));
+            (digits, number)
+        };
+        match number {
+            Some(number) if number > MAX_FIELD as u64 => {
+                // one out of range of any integer is reported already
+                let message = format!("{}: Result too large", text);
+                if self.printed.errors.last() != Some(&message) {
+                    self.printed.errors.push(message);
+                }
+                Err(Stop)
+            }
+            number => Ok(number.map(|number| number as usize)),
+        }
+    }
+
+    fn conversion(&mut self, format: &[char], i: &mut usize) -> Result<(), Stop> {
+        let mut spec = Spec::default();
+        while let Some(flag @ ('-' | '+' | ' ' | '#' | '0')) = format.get(*i).copied() {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alternate = true,
+                _ => spec.zero = true,
+            }
+            *i += 1;
+        }
+        spec.width = self.number(format, i)?.unwrap_or(0);
+        if format.get(*i) == Some(&'.') {
+            *i += 1;
+            spec.precision = Some(self.number(format, i)?.unwrap_or(0));
+        }
+
+        let Some(&conversion) = format.get(*i) else {
+            self.printed.errors.push("missing format character".into());
+            return Err(Stop);
+        };
+        *i += 1;
+        let text = match conversion {
+            's' => {
+                let arg = self.arg();
+                match spec.precision {
+                    Some(precision) => arg.chars().take(precision).collect(),
+                    None => arg.to_string(),
+                }
+            }
+            'b' => {
+                let arg: Vec<char> = self.arg().chars().collect();
+                let (mut text, mut stop, mut at) = (String::new(), false, 0);
+                while at < arg.len() && !stop {
+                    if arg[at] == '\\' {
+                        let (escaped, stopped) = escape(&arg, &mut at, true);
+                        text.push_str(&escaped);
+                        stop = stopped;
+                    } else {
+                        text.push(arg[at]);
+                        at += 1;
+                    }
+                }
+                let text = spec.pad(String::new(), text, false);
+                self.printed.output.push_str(&text);
+                return if stop { Err(Stop) } else { Ok(()) };
+            }
+            'c' => self.arg().chars().next().map(String::from).unwrap_or_default(),
+            'd' | 'i' => {
+                let arg = self.arg().to_string();
+                let value = self.integer(&arg);
+                self.push_integer(&spec, value.unsigned_abs().to_string(), value < 0, "");
+                return Ok(());
+            }
+            'u' | 'o' | 'x' | 'X' => {
+                let arg = self.arg().to_string();
+                let value = self.integer(&arg) as u64;
+                let (digits, prefix) = match conversion {
+                    'u' => (value.to_string(), ""),
+                    // `#o` makes the first digit a zero
+                    'o' => (format!("{:o}", value), "0"),
+                    'x' => (format!("{:x}", value), "0x"),
+                    _ => (format!("{:X}", value), "0X"),
+                };
+                let prefix = if spec.alternate && value != 0 { prefix } else { "" };
+                self.push_integer(&spec, digits, false, prefix);
+                return Ok(());
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let arg = self.arg().to_string();
+                let value = self.float(&arg);
+                let body = float(value.abs(), conversion, spec.precision.unwrap_or(6), spec.alternate);
+                let sign = spec.sign(value.is_sign_negative() && value != 0.0);
+                let text = spec.pad(sign, body, value.is_finite());
+                self.printed.output.push_str(&text);
+                return Ok(());
+            }
+            c => {
+                self.printed.errors.push(format!("%{}: invalid conversion", c));
+                return Err(Stop);
+            }
+        };
+        let text = spec.pad(String::new(), text, false);
+        self.printed.output.push_str(&text);
+        Ok(())
+    }
+
+    fn push_integer(&mut self, spec: &Spec, mut digits: String, negative: bool, prefix: &str) {
+        if let Some(precision) = spec.precision {
+            if digits == "0" && precision == 0 {
+                digits.clear();
+            }
+            while digits.len() < precision {
+                digits.insert(0, '0');
+            }
+        }
+        // a precision may have put the zero of `#o` there already
+        let prefix = if prefix == "0" && digits.starts_with('0') { "" } else { prefix };
+        let text = spec.pad(format!("{}{}", spec.sign(negative), prefix), digits, spec.precision.is_none());
+        self.printed.output.push_str(&text);
+    }
+
+    /// `arg` as an integer, which may be written in octal or hex, or as
+    /// `'c` for the code of a character.
+    fn integer(&mut self, arg: &str) -> i64 {
+        if let Some(c) = arg.strip_prefix(['\'', '"']) {
+            return c.chars().next().map_or(0, |c| c as i64);
+        }
+        let trimmed = arg.trim_start();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (base, digits) = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            (16, hex)
+        } else if digits.len() > 1 && digits.starts_with('0') {
+            (8, &digits[1..])
+        } else {
+            (10, digits)
+        };
+        let end = digits.find(|c: char| !c.is_digit(base)).unwrap_or(digits.len());
+        if arg.is_empty() {
+            return 0;
+        }
+        // the most that fits, which is one more below zero
+        let limit = if negative { i64::MIN.unsigned_abs() } else { i64::MAX as u64 };
+        let magnitude = match u64::from_str_radix(&digits[..end], base) {
+            Ok(magnitude) if magnitude <= limit => magnitude,
+            Ok(_) | Err(_) if end > 0 => {
+                self.printed.errors.push(format!("{}: Result too large", arg));
+                limit
+            }
+            _ => 0,
+        };
+        if end == 0 || end < digits.len() {
+            let message = if end == 0 { "expected a numeric value" } else { "not completely converted" };
+            self.printed.errors.push(format!("{}: {}", arg, message));
+        }
+        if negative {
+            (-(magnitude as i128)) as i64
+        } else {
+            magnitude as i64
+        }
+    }
+
+    fn float(&mut self, arg: &str) -> f64 {
+        if arg.starts_with(['\'', '"']) {
+            return self.integer(arg) as f64;
+        }
+        if arg.is_empty() {
+            return 0.0;
+        }
+        match arg.trim().parse() {
+            Ok(value) => value,
+            Err(_) => {
+                self.printed.errors.push(format!("{}: expected a numeric value", arg));
+                0.0
+            }
+        }
+    }
+}
+
+/// Flags, width and precision of a conversion.
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn sign(&self, negative: bool) -> String {
+        match (negative, self.plus, self.space) {
+            (true, ..) => "-".into(),
+            (false, true, _) => "+".into(),
+            (false, false, true) => " ".into(),
+            _ => String::new(),
+        }
+    }
+
+    /// `prefix` then `body`, padded to the width. Zeros go between them
+    /// when the `0` flag applies to the conversion.
+    fn pad(&self, prefix: String, body: String, numeric: bool) -> String {
+        let len = prefix.chars().count() + body.chars().count();
+        let fill = self.width.saturating_sub(len);
+        if self.left {
+            format!("{}{}{}", prefix, body, " ".repeat(fill))
+        } else if self.zero && numeric {
+            format!("{}{}{}", prefix, "0".repeat(fill), body)
+        } else {
+            format!("{}{}{}", " ".repeat(fill), prefix, body)
+        }
+    }
+}
+
+/// A non-negative `value` written by the `f`, `e` or `g` conversions.
+fn float(value: f64, conversion: char, precision: usize, alternate: bool) -> String {
+    let upper = conversion.is_ascii_uppercase();
+    let text = if value.is_infinite() {
+        "inf".to_string()
+    } else if value.is_nan() {
+        "nan".to_string()
+    } else {
+        match conversion.to_ascii_lowercase() {
+            'f' => format!("{:.*}", precision, value),
+            'e' => exponent(value, precision),
+            _ => {
+                let precision = precision.max(1);
+                let exp = if value == 0.0 { 0 } else { value.abs().log10().floor() as i64 };
+                // rounding may carry into the next power of ten
+                let exp = match exponent(value, precision - 1).split_once('e') {
+                    Some((_, written)) => written.parse().unwrap_or(exp),
+                    None => exp,
+                };
+                let mut text = if exp < -4 || exp >= precision as i64 {
+                    exponent(value, precision - 1)
+                } else {
+                    format!("{:.*}", (precision as i64 - 1 - exp) as usize, value)
+                };
+                if !alternate {
+                    text = trim_zeros(&text);
+                }
+                text
+            }
+        }
+    };
+    if upper {
+        text.to_uppercase()
+    } else {
+        text
+    }
+}
+
+/// `value` as `d.ddde+XX`.
+fn exponent(value: f64, precision: usize) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let (mantissa, exp) = text.split_once('e').unwrap_or((&text, "0"));
+    let exp: i64 = exp.parse().unwrap_or(0);
+    format!("{}e{}{:02}", mantissa, if exp < 0 { '-' } else { '+' }, exp.abs())
+}
+
+/// Drop the trailing zeros of the fraction, and the point when nothing is
+/// left after it.
+fn trim_zeros(text: &str) -> String {
+    let (number, exp) = match text.find('e') {
+        Some(i) => text.split_at(i),
+        None => (text, ""),
+    };
+    let number = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+    format!("{}{}", number, exp)
+}
+
+/// The escape sequence at `chars[*i]`, which is a `\`, moving `i` past it.
+/// Returns whether it was `\c`. In `%b` arguments octal escapes are
+/// written `\0nnn`, in formats `\nnn`.
+pub fn escape(chars: &[char], i: &mut usize, argument: bool) -> (String, bool) {
+    *i += 1;
+    let Some(&c) = chars.get(*i) else {
+        return ("\\".into(), false);
+    };
+    *i += 1;
+    let text = match c {
+        '\\' => '\\',
+        'a' => '\x07',
+        'b' => '\x08',
+        'f' => '\x0c',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'v' => '\x0b',
+        'c' => return (String::new(), true),
+        '0'..='7' if !argument || c == '0' => {
+            // the digits after `\0`, or after the `\` itself
+            let start = if argument { *i } else { *i - 1 };
+            let mut end = start;
+            while end < start + 3 && chars.get(end).is_some_and(|c| ('0'..='7').contains(c)) {
+                end += 1;
+            }
+            *i = end;
+            let digits: String = chars[start..end].iter().collect();
+            let value = u32::from_str_radix(&digits, 8).unwrap_or(0);
+            // only bytes fit in an octal escape
+            char::from_u32(value & 0xff).unwrap_or('\0')
+        }
+        c => return (format!("\\{}", c), false),
+    };
+    (text.to_string(), false)
+}
+
+#[cfg(test)]
+mod printf_test {
+    use super::printf;
+
+    fn output(format: &str, args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        printf(format, &args).output
+    }
+
+    fn errors(format: &str, args: &[&str]) -> Vec<String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        printf(format, &args).errors
+    }
+
+    #[test]
+    fn flags_width_precision() {
+        assert_eq!(output("%5d|%-5d|%05d|%+d|% d", &["42"; 5]), "   42|42   |00042|+42| 42");
+        assert_eq!(output("%.3s|%8.3f|%.0f", &["abcdef", "3.14159", "2.5"]), "abc|   3.142|2");
+        assert_eq!(output("%#x %#X %#o %#o %.3d", &["255", "255", "8", "0", "7"]), "0xff 0XFF 010 0 007");
+        assert_eq!(output("%*d|%-*.*f|", &["5", "42", "8", "2", "2.5"]), "   42|2.50    |");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(output("%b|", &["a\\tb\\cignored"]), "a\tb");
+        assert_eq!(output("a\\cb", &[]), "a");
+        // `\nnn` in formats, `\0nnn` in `%b` arguments
+        assert_eq!(output("\\101\\0101", &[]), "A\x081");
+        assert_eq!(output("%b %b", &["\\0101", "\\101"]), "A \\101");
+        assert_eq!(output("100%%\\n", &[]), "100%\n");
+    }
+
+    #[test]
+    fn format_reuse() {
+        assert_eq!(output("%s-", &["a", "b", "c"]), "a-b-c-");
+        assert_eq!(output("%s %s\\n", &["a", "b", "c"]), "a b\nc \n");
+        assert_eq!(output("%s|%d\\n", &[]), "|0\n");
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(
+            output("%g %g %g %g %.3g %#g", &["0.0001", "123456789", "9.9999999", "999999.5", "1234", "1"]),
+            "0.0001 1.23457e+08 10 1e+06 1.23e+03 1.00000"
+        );
+        assert_eq!(output("%e %E", &["1234.5", "0.00012"]), "1.234500e+03 1.200000E-04");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(output("%d %d %d %x", &["'A", "010", "-0x10", "-1"]), "65 8 -16 ffffffffffffffff");
+        assert_eq!(output("%d", &["-9223372036854775808"]), "-9223372036854775808");
+        assert!(errors("%d", &["-9223372036854775808"]).is_empty());
+
+        assert_eq!(output("%d|%d", &["abc", "12abc"]), "0|12");
+        assert_eq!(errors("%d|%d", &["abc", "12abc"]), ["abc: expected a numeric value", "12abc: not completely converted"]);
+        assert_eq!(output("%d", &["99999999999999999999"]), "9223372036854775807");
+        assert_eq!(errors("%d", &["99999999999999999999"]), ["99999999999999999999: Result too large"]);
+        assert_eq!(errors("%q", &[]), ["%q: invalid conversion"]);
+    }
+
+    #[test]
+    fn huge_fields() {
+        let too_large = ["9223372036854775807: Result too large"];
+        assert_eq!(errors("%*d", &["9223372036854775807", "1"]), too_large);
+        assert_eq!(output("a%*d", &["9223372036854775807", "1"]), "a");
+        assert_eq!(errors("%.*f", &["9223372036854775807", "1"]), too_large);
+        assert_eq!(errors("%99999999999999999999d", &["1"]), ["99999999999999999999: Result too large"]);
+        assert_eq!(errors("%*d", &["99999999999999999999", "1"]), ["99999999999999999999: Result too large"]);
+        assert_eq!(output("%*d", &["3", "1"]), "  1");
+    }
+}
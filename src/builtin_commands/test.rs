@@ -0,0 +1,243 @@
+use std::fs;
+
+use x_engine::{Io, ShellState};
+use x_util::{accessible, Access};
+
+const UNARY: [&str; 12] = ["-e", "-f", "-d", "-r", "-w", "-x", "-s", "-L", "-h", "-n", "-z", "-t"];
+const BINARY: [&str; 8] = ["=", "!=", "-eq", "-ne", "-lt", "-le", "-gt", "-ge"];
+
+/// Evaluate the expression of `test`, or explain why it is not one.
+pub fn test(state: &ShellState, io: &Io, args: &[String]) -> Result<bool, String> {
+    let mut test = Test { state, io, args, at: 0 };
+    let result = match args.len() {
+        0 => false,
+        1 => !args[0].is_empty(),
+        _ => test.or()?,
+    };
+    match args.get(test.at) {
+        Some(extra) if args.len() > 1 => Err(format!("`{}`: unexpected argument", extra)),
+        _ => Ok(result),
+    }
+}
+
+struct Test<'a> {
+    state: &'a ShellState,
+    /// The streams of `test` itself, which `-t` is about.
+    io: &'a Io,
+    args: &'a [String],
+    at: usize,
+}
+
+impl Test<'_> {
+    fn peek(&self, offset: usize) -> Option<&str> {
+        self.args.get(self.at + offset).map(|arg| arg.as_str())
+    }
+
+    fn next(&mut self) -> Result<&str, String> {
+        let arg = self.args.get(self.at).ok_or("argument expected")?;
+        self.at += 1;
+        Ok(arg)
+    }
+
+    fn or(&mut self) -> Result<bool, String> {
+        let mut value = self.and()?;
+        while self.peek(0) == Some("-o") {
+            self.at += 1;
+            // both sides are evaluated, as they are parsed
+            value |= self.and()?;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let mut value = self.not()?;
+        while self.peek(0) == Some("-a") {
+            self.at += 1;
+            value &= self.not()?;
+        }
+        Ok(value)
+    }
+
+    fn not(&mut self) -> Result<bool, String> {
+        // `!` followed by nothing is just a string
+        if self.peek(0) == Some("!") && self.peek(1).is_some() {
+            self.at += 1;
+            return Ok(!self.not()?);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<bool, String> {
+        // a binary operator second wins, as in `test -n = -n`
+        if let Some(op) = self.peek(1).filter(|op| BINARY.contains(op)) {
+            if self.peek(2).is_some() {
+                let op = op.to_string();
+                let left = self.next()?.to_string();
+                self.at += 1;
+                let right = self.next()?.to_string();
+                return binary(&left, &op, &right);
+            }
+        }
+        match self.peek(0) {
+            Some("(") if self.peek(1).is_some() => {
+                self.at += 1;
+                let value = self.or()?;
+                if self.next()? != ")" {
+                    return Err("`)` expected".into());
+                }
+                Ok(value)
+            }
+            Some(op) if UNARY.contains(&op) && self.peek(1).is_some() => {
+                let op = op.to_string();
+                self.at += 1;
+                let operand = self.next()?.to_string();
+                unary(self.state, self.io, &op, &operand)
+            }
+            _ => Ok(!self.next()?.is_empty()),
+        }
+    }
+}
+
+fn unary(state: &ShellState, io: &Io, op: &str, operand: &str) -> Result<bool, String> {
+    let path = state.resolve_path(operand);
+    let meta = fs::metadata(&path);
+    Ok(match op {
+        "-n" => !operand.is_empty(),
+        "-z" => operand.is_empty(),
+        "-e" => meta.is_ok(),
+        "-f" => meta.is_ok_and(|meta| meta.is_file()),
+        "-d" => meta.is_ok_and(|meta| meta.is_dir()),
+        "-s" => meta.is_ok_and(|meta| meta.len() > 0),
+        "-L" | "-h" => fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_symlink()),
+        "-r" => accessible(&path, Access::Read),
+        "-w" => accessible(&path, Access::Write),
+        "-x" => accessible(&path, Access::Execute),
+        _ => {
+            let fd: i32 = operand.parse().map_err(|_| format!("`{}`: integer expected", operand))?;
+            match fd {
+                0 => io.stdin.is_terminal(),
+                1 => io.stdout.is_terminal(),
+                2 => io.stderr.is_terminal(),
+                _ => false,
+            }
+        }
+    })
+}
+
+fn binary(left: &str, op: &str, right: &str) -> Result<bool, String> {
+    match op {
+        "=" => return Ok(left == right),
+        "!=" => return Ok(left != right),
+        _ => {}
+    }
+    let number = |arg: &str| -> Result<i64, String> {
+        arg.trim().parse().map_err(|_| format!("`{}`: integer expected", arg))
+    };
+    let (left, right) = (number(left)?, number(right)?);
+    Ok(match op {
+        "-eq" => left == right,
+        "-ne" => left != right,
+        "-lt" => left < right,
+        "-le" => left <= right,
+        "-gt" => left > right,
+        _ => left >= right,
+    })
+}
+
+#[cfg(test)]
+mod test_test {
+    use std::path::PathBuf;
+
+    use x_engine::{Io, ShellState};
+
+    use super::test;
+
+    fn eval_with(io: &Io, args: &[&str]) -> Result<bool, String> {
+        let state = ShellState::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")), "user".into());
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        test(&state, io, &args)
+    }
+
+    fn eval(args: &[&str]) -> Result<bool, String> {
+        eval_with(&Io::capture(), args)
+    }
+
+    #[test]
+    fn strings_and_numbers() {
+        assert_eq!(eval(&[]), Ok(false));
+        assert_eq!(eval(&["x"]), Ok(true));
+        assert_eq!(eval(&[""]), Ok(false));
+        assert_eq!(eval(&["-n", ""]), Ok(false));
+        assert_eq!(eval(&["-z", ""]), Ok(true));
+        assert_eq!(eval(&["a", "!=", "b"]), Ok(true));
+        assert_eq!(eval(&["1", "-eq", "01"]), Ok(true));
+        assert_eq!(eval(&["-3", "-lt", "2"]), Ok(true));
+        // a binary operator second is one, even after a unary operator
+        assert_eq!(eval(&["-n", "=", "-n"]), Ok(true));
+    }
+
+    #[test]
+    fn precedence() {
+        // `-a` binds tighter than `-o`
+        assert_eq!(eval(&["a", "-o", "", "-a", ""]), Ok(true));
+        // `!` applies to the primary after it
+        assert_eq!(eval(&["!", "", "-a", ""]), Ok(false));
+        assert_eq!(eval(&["!", "(", "", "-a", "", ")"]), Ok(true));
+        assert_eq!(eval(&["(", "a", "-o", "", ")", "-a", ""]), Ok(false));
+        assert_eq!(eval(&["!", "!", "x"]), Ok(true));
+        assert_eq!(eval(&["!"]), Ok(true));
+    }
+
+    #[test]
+    fn files() {
+        assert_eq!(eval(&["-f", "Cargo.toml"]), Ok(true));
+        assert_eq!(eval(&["-d", "src"]), Ok(true));
+        assert_eq!(eval(&["-f", "src"]), Ok(false));
+        assert_eq!(eval(&["-e", "missing"]), Ok(false));
+        assert_eq!(eval(&["-s", "Cargo.toml", "-a", "-r", "Cargo.toml"]), Ok(true));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn access() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        // only others may read it, which the owner is not
+        let path = std::env::temp_dir().join(format!("xshell-access-{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o004)).unwrap();
+        let root = std::fs::metadata(&path).unwrap().uid() == 0;
+        let file = path.to_str().unwrap();
+        // root may read and write anything, and run what anyone may run
+        assert_eq!(eval(&["-r", file]), Ok(root));
+        assert_eq!(eval(&["-w", file]), Ok(root));
+        assert_eq!(eval(&["-x", file]), Ok(false));
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(eval(&["-r", file, "-a", "-w", file, "-a", "-x", file]), Ok(true));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn terminal() {
+        use x_protocol::io::{Input, Sink};
+
+        // what `test` reads and writes, not the shell's own streams
+        assert_eq!(eval(&["-t", "1"]), Ok(false));
+        let null = || std::fs::File::open("/dev/null").unwrap();
+        let io = Io {
+            stdin: Input::File(null()),
+            stdout: Sink::File(null()),
+            stderr: Sink::File(null()),
+        };
+        assert_eq!(eval_with(&io, &["-t", "0", "-o", "-t", "1", "-o", "-t", "2"]), Ok(false));
+        assert_eq!(eval(&["-t", "3"]), Ok(false));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval(&["1", "-eq", "x"]), Err("`x`: integer expected".into()));
+        assert_eq!(eval(&["a", "b"]), Err("`b`: unexpected argument".into()));
+        assert_eq!(eval(&["(", "a", "b"]), Err("`)` expected".into()));
+        assert_eq!(eval(&["-t", "x"]), Err("`x`: integer expected".into()));
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::Write;
+
+use x_engine::{ExitStatus, Io, Result, ShellState};
+use x_protocol::io_message;
+use x_util::{set_disposition, Disposition, SIGNALS};
+
+/// Set, reset or list traps, as `trap` does.
+pub fn trap(args: &[String], state: &mut ShellState, io: &mut Io) -> Result<ExitStatus> {
+    let args = match args.first().map(String::as_str) {
+        Some("--") => &args[1..],
+        _ => args,
+    };
+    match args.first().map(String::as_str) {
+        None => return print(state, &sorted(state), io),
+        Some("-p") => {
+            let mut signals = vec![];
+            for spec in &args[1..] {
+                match signal(spec) {
+                    Some(signal) => signals.push(signal),
+                    None => return invalid(spec, io),
+                }
+            }
+            return print(state, &signals, io);
+        }
+        Some("-l") => {
+            let mut signals = SIGNALS.to_vec();
+            signals.sort_by_key(|(_, number)| *number);
+            for (name, number) in signals {
+                writeln!(io.stdout, "{:2}) SIG{}", number, name)?;
+            }
+            return Ok(ExitStatus::SUCCESS);
+        }
+        _ => {}
+    }
+
+    // a lone signal, or a number first, resets instead of naming a command
+    let (action, specs) = if args.len() == 1 || args[0].parse::<u32>().is_ok() {
+        ("-", args)
+    } else {
+        (args[0].as_str(), &args[1..])
+    };
+    let mut status = ExitStatus::SUCCESS;
+    for spec in specs {
+        let Some(signal) = signal(spec) else {
+            status = invalid(spec, io)?;
+            continue;
+        };
+        let disposition = match action {
+            "-" => Disposition::Default,
+            "" => Disposition::Ignore,
+            _ => Disposition::Catch,
+        };
+        if signal != 0 {
+            if let Err(e) = set_disposition(signal, disposition) {
+                writeln!(io.stderr, "trap: {}: {}", spec, io_message(&e))?;
+                status = ExitStatus::FAILURE;
+                continue;
+            }
+        }
+        match disposition {
+            Disposition::Default => state.traps.remove(&signal),
+            _ => state.traps.insert(signal, action.to_string()),
+        };
+    }
+    Ok(status)
+}
+
+/// The number of the signal `spec` names, written `INT`, `SIGINT` or `2`,
+/// where `EXIT` and `0` are the exit of the shell.
+fn signal(spec: &str) -> Option<i32> {
+    if let Ok(number) = spec.parse::<i32>() {
+        return (number == 0 || SIGNALS.iter().any(|(_, n)| *n == number)).then_some(number);
+    }
+    let upper = spec.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    if name == "EXIT" {
+        return Some(0);
+    }
+    SIGNALS.iter().find(|(n, _)| *n == name).map(|(_, number)| *number)
+}
+
+/// The name `trap -p` writes for `signal`.
+fn name(signal: i32) -> String {
+    match SIGNALS.iter().find(|(_, number)| *number == signal) {
+        Some((name, _)) => format!("SIG{}", name),
+        None if signal == 0 => "EXIT".into(),
+        None => signal.to_string(),
+    }
+}
+
+fn sorted(state: &ShellState) -> Vec<i32> {
+    let mut signals: Vec<i32> = state.traps.keys().copied().collect();
+    signals.sort();
+    signals
+}
+
+/// Write the traps of `signals` as commands that set them again.
+fn print(state: &ShellState, signals: &[i32], io: &mut Io) -> Result<ExitStatus> {
+    for signal in signals {
+        if let Some(action) = state.traps.get(signal) {
+            writeln!(io.stdout, "trap -- {} {}", super::single_quote(action), name(*signal))?;
+        }
+    }
+    Ok(ExitStatus::SUCCESS)
+}
+
+fn invalid(spec: &str, io: &mut Io) -> Result<ExitStatus> {
+    writeln!(io.stderr, "trap: {}: invalid signal specification", spec)?;
+    Ok(ExitStatus::FAILURE)
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs,
+    io::{stdout, IsTerminal},
+    path::PathBuf,
+};
+
+use serde_json::{json, Value};
+use x_checker::Checker;
+use x_engine::ShellState;
+use x_parser::{Lexer, Parser};
+use x_protocol::diagnostic::{position, Diagnostic, Severity};
+use x_render::report;
+
+use crate::cli::Format;
+
+/// The diagnostics of one script.
+struct Checked {
+    path: PathBuf,
+    source: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Lint `paths` without running them and print what was found in `format`.
+/// Returns the exit status: 1 when there are errors, 2 when a script could
+/// not be read.
+pub fn run(paths: &[PathBuf], format: Format, state: &ShellState) -> i32 {
+    let (checked, status) = lint(paths, state);
+    print!("{}", output(&checked, format));
+    status
+}
+
+/// The diagnostics of each of `paths` that could be read, with the exit
+/// status.
+fn lint(paths: &[PathBuf], state: &ShellState) -> (Vec<Checked>, i32) {
+    let mut status = 0;
+    let mut checked = vec![];
+
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("xshell: {}: {}", path.display(), e);
+                status = 2;
+                continue;
+            }
+        };
+        let tree = Parser::new(Lexer::new(source.chars())).parse_tree();
+        let diagnostics = Checker::new(state).lint(&tree, &source);
+        if status == 0 && diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            status = 1;
+        }
+        checked.push(Checked {
+            path: path.clone(),
+            source,
+            diagnostics,
+        });
+    }
+
+    (checked, status)
+}
+
+fn output(checked: &[Checked], format: Format) -> String {
+    match format {
+        Format::Human => human(checked),
+        Format::Json => format!("{:#}\n", Value::Array(checked.iter().flat_map(json).collect())),
+        Format::Sarif => format!("{:#}\n", sarif(checked)),
+    }
+}
+
+fn human(checked: &[Checked]) -> String {
+    let color = stdout().is_terminal();
+    let mut out = String::new();
+    let (mut errors, mut warnings) = (0, 0);
+
+    for script in checked {
+        for diagnostic in &script.diagnostics {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            let origin = script.path.display().to_string();
+            out.push_str(&report(diagnostic, &script.source, &origin, color));
+            out.push('\n');
+        }
+    }
+    if errors + warnings > 0 {
+        out.push_str(&format!("{} error(s), {} warning(s)\n", errors, warnings));
+    }
+    out
+}
+
+/// One-based `(line, column)` of the start and end of a span.
+fn region(source: &str, span: &std::ops::Range<usize>) -> ((usize, usize), (usize, usize)) {
+    let (start, end) = (position(source, span.start), position(source, span.end));
+    ((start.0 + 1, start.1 + 1), (end.0 + 1, end.1 + 1))
+}
+
+fn json(script: &Checked) -> Vec<Value> {
+    script
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let ((line, column), (end_line, end_column)) = region(&script.source, &diagnostic.primary.span);
+            let labels: Vec<Value> = diagnostic
+                .secondary
+                .iter()
+                .map(|label| {
+                    let ((line, column), _) = region(&script.source, &label.span);
+                    json!({ "line": line, "column": column, "message": label.message })
+                })
+                .collect();
+            json!({
+                "file": script.path.display().to_string(),
+                "severity": diagnostic.severity.to_string(),
+                "code": diagnostic.code,
+                "message": diagnostic.message,
+                "line": line,
+                "column": column,
+                "end_line": end_line,
+                "end_column": end_column,
+                "label": diagnostic.primary.message,
+                "labels": labels,
+                "notes": diagnostic.notes,
+                "help": diagnostic.help,
+            })
+        })
+        .collect()
+}
+
+/// A SARIF 2.1.0 log, as read by code scanning tools.
+fn sarif(checked: &[Checked]) -> Value {
+    let results: Vec<Value> = checked
+        .iter()
+        .flat_map(|script| {
+            script.diagnostics.iter().map(|diagnostic| {
+                let ((line, column), (end_line, end_column)) =
+                    region(&script.source, &diagnostic.primary.span);
+                let mut text = diagnostic.message.clone();
+                for help in &diagnostic.help {
+                    text.push_str(&format!(" help: {}", help));
+                }
+                json!({
+                    "ruleId": diagnostic.code,
+                    "level": match diagnostic.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    },
+                    "message": { "text": text },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": script.path.display().to_string() },
+                            "region": {
+                                "startLine": line,
+                                "startColumn": column,
+                                "endLine": end_line,
+                                "endColumn": end_column,
+                            }
+                        }
+                    }]
+                })
+            })
+        })
+        .collect();
+
+    json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": { "driver": { "name": "xshell", "version": env!("CARGO_PKG_VERSION") } },
+            "results": results,
+        }]
+    })
+}
+
+#[cfg(test)]
+mod check_test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use serde_json::Value;
+    use x_engine::ShellState;
+    use x_protocol::command::EnvCommand;
+
+    use super::{lint, output};
+    use crate::cli::Format;
+
+    /// Lint `source` as a script, giving the parsed output in `format` and
+    /// the exit status.
+    fn check(source: &str, format: Format) -> (Value, i32) {
+        let mut state = ShellState::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")), "user".into());
+        state.init_commands(vec![Box::new(EnvCommand::new("ls".into(), PathBuf::from("/bin/ls")))]);
+        let dir = std::env::temp_dir().join(format!("xshell-check-{}-{:?}", std::process::id(), format));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("script.xsh");
+        fs::write(&path, source).unwrap();
+
+        let (checked, status) = lint(&[path], &state);
+        let output = output(&checked, format);
+        fs::remove_dir_all(&dir).unwrap();
+        (serde_json::from_str(&output).unwrap(), status)
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(check("ls\n", Format::Json), (Value::Array(vec![]), 0));
+        let (diagnostics, status) = check("ls $nope\n", Format::Json);
+        assert_eq!((diagnostics[0]["severity"].as_str(), status), (Some("warning"), 0));
+        let (diagnostics, status) = check("ls $nope\nlss\n", Format::Json);
+        assert_eq!((diagnostics[1]["severity"].as_str(), status), (Some("error"), 1));
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("missing.xsh");
+        let (checked, status) = lint(&[path], &ShellState::default());
+        assert_eq!((checked.len(), status), (0, 2));
+    }
+
+    #[test]
+    fn json() {
+        let (diagnostics, _) = check("def f [a] { ls $a; }\nf 1 2\n", Format::Json);
+        let [diagnostic] = diagnostics.as_array().unwrap().as_slice() else {
+            panic!("{:#}", diagnostics);
+        };
+        assert!(diagnostic["file"].as_str().unwrap().ends_with("script.xsh"));
+        assert_eq!(diagnostic["code"], "XS102");
+        assert_eq!(diagnostic["severity"], "warning");
+        assert_eq!(diagnostic["message"], "`f` takes 1 argument but 2 were given.");
+        assert_eq!(
+            [&diagnostic["line"], &diagnostic["column"], &diagnostic["end_line"], &diagnostic["end_column"]],
+            [2, 1, 2, 2]
+        );
+        assert_eq!(diagnostic["labels"][0]["message"], "defined here");
+        assert_eq!([&diagnostic["labels"][0]["line"], &diagnostic["labels"][0]["column"]], [1, 5]);
+    }
+
+    #[test]
+    fn sarif() {
+        let (log, _) = check("ls\nlss\n", Format::Sarif);
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "xshell");
+        let [result] = run["results"].as_array().unwrap().as_slice() else {
+            panic!("{:#}", run["results"]);
+        };
+        assert_eq!(result["ruleId"], "XS002");
+        assert_eq!(result["level"], "error");
+        assert!(result["message"]["text"].as_str().unwrap().starts_with("Unknown command `lss`."));
+        let location = &result["locations"][0]["physicalLocation"];
+        assert!(location["artifactLocation"]["uri"].as_str().unwrap().ends_with("script.xsh"));
+        let region = &location["region"];
+        assert_eq!(
+            [&region["startLine"], &region["startColumn"], &region["endLine"], &region["endColumn"]],
+            [2, 1, 2, 4]
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::ffi::OsString;
+use std::path::Path;
 use std::{error::Error, path::PathBuf};
 
 #[derive(Parser, Debug)]
@@ -13,13 +15,62 @@ pub struct Args {
     #[arg(long = "config")]
     pub(crate) config: Option<PathBuf>,
 
-    /// run script
-    #[arg(last = true)]
-    pub(crate) path: Vec<PathBuf>,
+    /// run a script, which is also how a `#!` line starts it
+    #[arg(value_name = "SCRIPT")]
+    pub(crate) path: Option<PathBuf>,
+
+    /// arguments of the script, its `$1`, `$2`, ...
+    #[arg(value_name = "ARG", trailing_var_arg = true, allow_hyphen_values = true)]
+    pub(crate) args: Vec<String>,
 
     /// run the given commands and then exit
     #[arg(short = 'c', long)]
     pub(crate) command: Option<String>,
+
+    /// check scripts for problems without running them
+    #[arg(long = "check", value_name = "SCRIPT", num_args = 1..)]
+    pub(crate) check: Vec<PathBuf>,
+
+    /// output format of --check
+    #[arg(long = "format", value_enum, default_value_t = Format::Human)]
+    pub(crate) format: Format,
+
+    #[command(subcommand)]
+    pub(crate) action: Option<Action>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Action {
+    /// format scripts in place, or stdin to stdout
+    Fmt {
+        /// only list the scripts that are not formatted
+        #[arg(long)]
+        check: bool,
+
+        #[arg(value_name = "SCRIPT")]
+        paths: Vec<PathBuf>,
+    },
+    /// serve the language server protocol over stdio
+    Lsp,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Human,
+    Json,
+    Sarif,
+}
+
+impl Args {
+    /// Parse `argv`. A first argument naming a file is a script, even one
+    /// called like a subcommand, such as `fmt`.
+    pub fn parse_args(argv: impl IntoIterator<Item = OsString>) -> Args {
+        let mut argv: Vec<OsString> = argv.into_iter().collect();
+        if argv.get(1).is_some_and(|arg| Path::new(arg).is_file()) {
+            argv.insert(1, "--".into());
+        }
+        Args::parse_from(argv)
+    }
 }
 
 /// Parse a single key-value pair
@@ -35,3 +86,30 @@ where
         .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{}`", s))?;
     Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
 }
+
+#[cfg(test)]
+mod cli_test {
+    use std::ffi::OsString;
+
+    use super::{Action, Args};
+
+    fn parse(argv: &[&str]) -> Args {
+        Args::parse_args(argv.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn script_arguments() {
+        // what a `#!/usr/bin/env xshell` line runs
+        let args = parse(&["xshell", "/tmp/s.xsh", "foo", "-c", "bar"]);
+        assert_eq!(args.path.unwrap().to_str(), Some("/tmp/s.xsh"));
+        assert_eq!(args.args, ["foo", "-c", "bar"]);
+        assert!(args.command.is_none());
+
+        assert!(matches!(parse(&["xshell", "fmt"]).action, Some(Action::Fmt { .. })));
+        // a file named like a subcommand is a script
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let args = parse(&["xshell", manifest, "fmt"]);
+        assert!(args.action.is_none());
+        assert_eq!(args.args, ["fmt"]);
+    }
+}
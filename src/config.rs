@@ -0,0 +1,38 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use x_engine::{ShellState, Theme};
+
+/// `$XDG_CONFIG_HOME/xshell/config.toml`, or `~/.config/xshell/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("xshell").join("config.toml"))
+}
+
+/// Apply the config file at `path` to `state`.
+///
+/// `theme` names a built-in theme or a theme file, relative to the
+/// directory of the config file.
+pub fn load(path: &Path, state: &mut ShellState) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let config = source
+        .parse::<toml::Table>()
+        .map_err(|e| format!("{}: {}", path.display(), e.message()))?;
+
+    if let Some(theme) = config.get("theme") {
+        let theme = theme
+            .as_str()
+            .ok_or_else(|| format!("{}: `theme` must be a string", path.display()))?;
+        state.theme = match Theme::builtin(theme) {
+            Some(theme) => theme,
+            None => Theme::load(&path.parent().unwrap_or(Path::new(".")).join(theme))?,
+        };
+    }
+
+    Ok(())
+}
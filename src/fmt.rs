@@ -0,0 +1,73 @@
+use std::{
+    fs,
+    io::{read_to_string, stdin},
+    path::PathBuf,
+};
+
+use x_formatter::format;
+use x_protocol::diagnostic::Diagnostic;
+use x_render::report;
+
+/// Format `paths` in place, or stdin to stdout when there are none. With
+/// `check` nothing is written; the scripts that would change are listed.
+/// Returns the exit status: 1 when a script is not formatted under `check`,
+/// 2 when one could not be read or parsed.
+pub fn run(paths: &[PathBuf], check: bool) -> i32 {
+    if paths.is_empty() {
+        let source = match read_to_string(stdin()) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("xshell: <stdin>: {}", e);
+                return 2;
+            }
+        };
+        return match formatted(&source, "<stdin>") {
+            Some(formatted) if check => (formatted != source) as i32,
+            Some(formatted) => {
+                print!("{}", formatted);
+                0
+            }
+            None => 2,
+        };
+    }
+
+    let mut status = 0;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("xshell: {}: {}", path.display(), e);
+                status = 2;
+                continue;
+            }
+        };
+        let Some(formatted) = formatted(&source, &path.display().to_string()) else {
+            status = 2;
+            continue;
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", path.display());
+            status = status.max(1);
+        } else if let Err(e) = fs::write(path, formatted) {
+            eprintln!("xshell: {}: {}", path.display(), e);
+            status = 2;
+        }
+    }
+    status
+}
+
+/// `source` formatted, or `None` after reporting its syntax errors.
+fn formatted(source: &str, origin: &str) -> Option<String> {
+    match format(source) {
+        Ok(formatted) => Some(formatted),
+        Err(errors) => {
+            for err in errors {
+                eprint!("{}", report(&Diagnostic::from_err(&err, source), source, origin, false));
+            }
+            None
+        }
+    }
+}
@@ -1,14 +1,22 @@
+mod check;
 mod cli;
 mod builtin_commands;
+mod config;
+mod fmt;
 
-use std::env::vars;
+use std::env::{args_os, vars};
+use std::fs;
+use std::io::{stdin, stdout};
+use std::process::exit;
 
 use builtin_commands::get_commands;
-use clap::Parser;
-use x_engine::{ShellState, XShellEvent};
+use x_engine::{run_exit_trap, run_script, ShellState, XShellEvent};
 
 fn main() {
-    let args: cli::Args = cli::Args::parse();
+    let args = cli::Args::parse_args(args_os());
+    if let Some(cli::Action::Fmt { check, paths }) = &args.action {
+        exit(fmt::run(paths, *check));
+    }
     let mut xshell_state = ShellState::default();
 
     // init commands
@@ -20,10 +28,54 @@ fn main() {
         .iter()
         .for_each(|(key, value)| xshell_state.add_env(key.clone(), value.clone()));
     for (key, value) in vars() {
-        println!("{} {}", key, value);
         xshell_state.add_env(key, value);
     }
 
+    // load config
+    let config_path = args
+        .config
+        .clone()
+        .or_else(|| config::default_path().filter(|path| path.exists()));
+    if let Some(path) = config_path {
+        if let Err(e) = config::load(&path, &mut xshell_state) {
+            eprintln!("xshell: {}", e);
+        }
+    }
+
+    if let Some(cli::Action::Lsp) = args.action {
+        match x_lsp::serve(xshell_state, stdin().lock(), stdout()) {
+            Ok(status) => exit(status),
+            Err(e) => {
+                eprintln!("xshell: lsp: {}", e);
+                exit(1);
+            }
+        }
+    }
+    if !args.check.is_empty() {
+        exit(check::run(&args.check, args.format, &xshell_state));
+    }
+    // as in `sh -c`, words after the commands are `$0`, `$1`, ...
+    if let Some(path) = &args.path {
+        xshell_state.set_arguments(&path.display().to_string(), &args.args);
+    }
+    if let Some(command) = &args.command {
+        let status = run_script(&mut xshell_state, command, "-c");
+        run_exit_trap(&mut xshell_state);
+        exit(status);
+    }
+    if let Some(path) = &args.path {
+        let status = match fs::read_to_string(path) {
+            Ok(source) => run_script(&mut xshell_state, &source, &path.display().to_string()),
+            Err(e) => {
+                eprintln!("xshell: {}: {}", path.display(), e);
+                127
+            }
+        };
+        run_exit_trap(&mut xshell_state);
+        exit(status);
+    }
+
     let mut xshell_event = XShellEvent::new(xshell_state);
     xshell_event.listen_start().unwrap();
+    exit(xshell_event.status());
 }